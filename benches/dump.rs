@@ -0,0 +1,116 @@
+//! Benchmarks for parsing a regions dump and writing a timesheet from it, run
+//! with `cargo bench`. Criterion saves each run as a baseline, so later runs
+//! report how much faster or slower they are.
+//!
+//! The dump is generated to be about the size of a real one, with factbooks,
+//! nation lists, and embassies, so that parsing does the same work as it would
+//! on a dump from NS.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use flate2::{write::GzEncoder, Compression};
+use srsglass::{Client, Dump, TimesheetOptions};
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Number of regions in the generated dump, about as many as NS has.
+const REGIONS: usize = 30_000;
+
+/// Number of timed runs of each benchmark, as few as Criterion allows, since
+/// each run reads or writes a whole dump.
+const SAMPLES: usize = 10;
+
+/// Start of the generated dump's major update.
+const MAJOR_START: i64 = 1722484800;
+
+/// Write a gzipped regions dump with `count` regions to `path`.
+fn write_dump(path: &Path, count: usize) {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<REGIONS>\n");
+
+    for index in 0..count {
+        // a few large regions among many small ones, as in a real dump
+        let population = match index % 100 {
+            0 => 500,
+            1..=9 => 40,
+            _ => 1 + index % 7,
+        };
+        let nations: Vec<String> = (0..population)
+            .map(|nation| format!("nation_{}_{}", index, nation))
+            .collect();
+        let embassies: String = (1..=index % 6)
+            .map(|offset| format!("<EMBASSY>Region {}</EMBASSY>", (index + offset) % count))
+            .collect();
+        let factbook =
+            "The region of &amp; welcome &quot;all&quot; [b]nations[/b]. ".repeat(index % 8);
+
+        let major = MAJOR_START + (index * 5350 / count) as i64;
+        let minor = major + 43200 - 1800 + (index * 3550 / count) as i64;
+
+        xml.push_str(&format!(
+            "<REGION><NAME>Region {index}</NAME><FACTBOOK><![CDATA[{factbook}]]></FACTBOOK>\
+             <NUMNATIONS>{population}</NUMNATIONS><NATIONS>{nations}</NATIONS>\
+             <DELEGATE>{delegate}</DELEGATE><DELEGATEVOTES>{votes}</DELEGATEVOTES>\
+             <DELEGATEAUTH>XWA</DELEGATEAUTH><GOVERNOR>{governor}</GOVERNOR>\
+             <LASTUPDATE>{minor}</LASTUPDATE><LASTMAJORUPDATE>{major}</LASTMAJORUPDATE>\
+             <LASTMINORUPDATE>{minor}</LASTMINORUPDATE><EMBASSIES>{embassies}</EMBASSIES></REGION>\n",
+            nations = nations.join(":"),
+            delegate = nations[0],
+            votes = population / 2,
+            governor = if index % 3 == 0 { "0" } else { &nations[0] },
+        ));
+    }
+
+    xml.push_str("</REGIONS>\n");
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(xml.as_bytes()).unwrap();
+    fs::write(path, encoder.finish().unwrap()).unwrap();
+}
+
+/// Write the generated dump to a temporary file, returning its path.
+fn dump_path() -> PathBuf {
+    let path = env::temp_dir().join("srsglass-bench-regions.xml.gz");
+    write_dump(&path, REGIONS);
+    path
+}
+
+fn parse_dump(c: &mut Criterion) {
+    let path = dump_path();
+    let client = Client::new("srsglass benchmarks");
+    let light_client = Client::new("srsglass benchmarks")
+        .with_factbooks(false)
+        .with_embassies(false);
+
+    let mut group = c.benchmark_group("parse_dump");
+    group.sample_size(SAMPLES);
+    group.bench_function("full", |b| {
+        b.iter(|| client.get_dump_from_file_offline(&path).unwrap())
+    });
+    group.bench_function("no_text", |b| {
+        b.iter(|| light_client.get_dump_from_file_offline(&path).unwrap())
+    });
+    group.finish();
+}
+
+fn write_timesheet(c: &mut Criterion) {
+    let dump: Dump = Client::new("srsglass benchmarks")
+        .get_dump_from_file_offline(dump_path())
+        .unwrap();
+    let options = TimesheetOptions::default();
+    let output = env::temp_dir().join("srsglass-bench.csv");
+
+    let mut group = c.benchmark_group("timesheet");
+    group.sample_size(SAMPLES);
+    group.bench_function("to_excel", |b| {
+        b.iter(|| dump.to_excel_buffer(&options).unwrap())
+    });
+    group.bench_function("to_csv", |b| {
+        b.iter(|| dump.to_csv(&output, &options).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_dump, write_timesheet);
+criterion_main!(benches);
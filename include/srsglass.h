@@ -0,0 +1,59 @@
+/*
+ * C interface to srsglass, built with
+ * `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+ *
+ * Functions that can fail return NULL or a negative number, after which
+ * srsglass_last_error() describes the failure. Dumps and estimates must be
+ * freed with srsglass_free_dump() and srsglass_free_estimates().
+ */
+
+#ifndef SRSGLASS_H
+#define SRSGLASS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A parsed regions dump. */
+typedef struct Dump SrsglassDump;
+
+/* Estimated update times of every region in a dump, in update order. */
+typedef struct SrsglassEstimates SrsglassEstimates;
+
+/* A region's estimated update times, in seconds from the start of each update. */
+typedef struct SrsglassEstimate {
+    /* The region's name, owned by the estimates it belongs to */
+    const char *name;
+    /* Number of nations updating before the region in major update */
+    int32_t nations_before;
+    /* Number of nations updating before the region in minor update */
+    int32_t nations_before_minor;
+    double major;
+    double minor;
+} SrsglassEstimate;
+
+/* Description of the last failure on this thread, or NULL. */
+const char *srsglass_last_error(void);
+
+SrsglassDump *srsglass_parse_dump(const char *path);
+SrsglassDump *srsglass_parse_dump_buffer(const uint8_t *data, size_t length);
+void srsglass_free_dump(SrsglassDump *dump);
+
+SrsglassEstimates *srsglass_estimate(const SrsglassDump *dump, int32_t major_length,
+                                     int32_t minor_length);
+size_t srsglass_estimates_len(const SrsglassEstimates *estimates);
+const SrsglassEstimate *srsglass_estimates_get(const SrsglassEstimates *estimates, size_t index);
+void srsglass_free_estimates(SrsglassEstimates *estimates);
+
+/* Returns 0 on success and -1 on failure. */
+int srsglass_write_xlsx(const SrsglassDump *dump, const char *path, int32_t major_length,
+                        int32_t minor_length);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
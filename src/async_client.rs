@@ -0,0 +1,333 @@
+//! An async counterpart to [`Client`], enabled with the `async` feature, for
+//! embedding srsglass in async applications such as bots and web servers.
+
+use crate::client::{intersect_batches, tag_batches, user_agent, RateLimiter};
+use crate::model::{compute_dump_date, governorless_in};
+use crate::parser::{
+    parse_dump, parse_foundings, parse_nation_count, parse_nations_dump, parse_region_list,
+    ParsedDump,
+};
+use crate::{Client, Dump, Nation, Progress, Region, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{debug, warn};
+use std::{
+    fs,
+    future::Future,
+    panic,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// A client that downloads dumps without blocking the async runtime, which
+/// must be a Tokio runtime with its timer enabled.
+///
+/// Downloads are awaited, and dumps are parsed on Tokio's blocking threads
+/// once they have been downloaded, since parsing the regions dump takes a few
+/// seconds. Requests stay within NS's rate limit and are retried like
+/// [`Client`]'s.
+pub struct AsyncClient {
+    client: reqwest::Client,
+    inner: Client,
+    rate_limiter: RateLimiter,
+}
+
+impl AsyncClient {
+    pub fn new(user_nation: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(user_agent(user_nation))
+            .build()?;
+
+        Ok(Self::from_parts(client, Client::new(user_nation)))
+    }
+
+    pub(crate) fn from_parts(client: reqwest::Client, inner: Client) -> Self {
+        Self {
+            client,
+            inner,
+            rate_limiter: RateLimiter::default(),
+        }
+    }
+
+    /// Retry requests and downloads that fail with a transient error. See
+    /// [`Client::with_retries`].
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.inner = self.inner.with_retries(retries, backoff);
+        self
+    }
+
+    /// Save downloaded dumps to `save_path`, so that they can be reused later
+    /// with [`Client::get_dump_from_file`].
+    pub fn with_save_path(mut self, save_path: impl Into<PathBuf>) -> Self {
+        self.inner = self.inner.with_save_path(save_path);
+        self
+    }
+
+    /// Keep the regions listed by tag queries on disk between runs. See
+    /// [`Client::with_tag_cache`].
+    pub fn with_tag_cache(mut self, cache_dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        self.inner = self.inner.with_tag_cache(cache_dir, ttl);
+        self
+    }
+
+    /// Set a hook to be called with progress updates while downloading and
+    /// reading dumps.
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.inner = self.inner.with_progress(progress);
+        self
+    }
+
+    /// Run a request, retrying it with exponential backoff if it fails with a
+    /// transient error.
+    async fn with_retry<T, F>(&self, mut request: impl FnMut() -> F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        let mut delay = self.inner.backoff;
+
+        loop {
+            match request().await {
+                Err(error) if attempt < self.inner.retries && error.is_transient() => {
+                    warn!("{}; retrying in {:.0?}", error, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Download a response body, retrying transient failures.
+    async fn download(&self, url: &str) -> Result<Vec<u8>> {
+        self.with_retry(|| self.try_download(url)).await
+    }
+
+    /// Download a response body once NS's rate limit allows it, reporting
+    /// progress as it is read.
+    async fn try_download(&self, url: &str) -> Result<Vec<u8>> {
+        tokio::time::sleep(self.rate_limiter.delay()).await;
+
+        let started = Instant::now();
+        let response = self.client.get(url).send().await?;
+        self.rate_limiter.update(|name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        });
+        let mut response = response.error_for_status()?;
+        let total = response.content_length();
+
+        let mut body = Vec::with_capacity(total.unwrap_or(0) as usize);
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            self.inner.report(Progress::Read {
+                bytes: body.len() as u64,
+                total,
+            });
+        }
+
+        debug!("Downloaded {} in {:.2?}", url, started.elapsed());
+        Ok(body)
+    }
+
+    pub async fn get_dump(&self) -> Result<Dump> {
+        let parsed = self
+            .download_regions(&self.inner.regions_dump_url())
+            .await?;
+        self.online_dump(parsed).await
+    }
+
+    /// Get the archived dump NS generated on the given date. See
+    /// [`Client::get_dump_for_date`].
+    pub async fn get_dump_for_date(&self, date: NaiveDate) -> Result<Dump> {
+        let parsed = self.download_regions(&self.inner.archive_url(date)).await?;
+
+        Ok(Dump {
+            dump_date: date,
+            ..self.online_dump(parsed).await?
+        })
+    }
+
+    /// Build a dump from regions read from a dump, requesting the rest of the
+    /// dump from the API.
+    async fn online_dump(&self, parsed: ParsedDump) -> Result<Dump> {
+        let ParsedDump {
+            regions,
+            incomplete,
+            sha256,
+        } = parsed;
+
+        let governorless = match self.get_governorless_regions().await {
+            Ok(governorless) => governorless,
+            Err(error) => {
+                warn!(
+                    "could not get governorless regions, using governors in the dump instead: {}",
+                    error
+                );
+                governorless_in(&regions)
+            }
+        };
+        let passwordless = self.get_passwordless_regions().await?;
+        let frontiers = self.get_frontier_regions().await?;
+        let invaders = self.get_invader_regions().await?;
+        let defenders = self.get_defender_regions().await?;
+
+        let dump_date = compute_dump_date(&regions)?;
+
+        Ok(Dump {
+            dump_date,
+            regions,
+            governorless,
+            passwordless,
+            frontiers,
+            invaders,
+            defenders,
+            incomplete,
+            sha256: Some(sha256),
+        })
+    }
+
+    pub async fn get_regions(&self) -> Result<Vec<Region>> {
+        Ok(self
+            .download_regions(&self.inner.regions_dump_url())
+            .await?
+            .regions)
+    }
+
+    pub async fn get_regions_for_date(&self, date: NaiveDate) -> Result<Vec<Region>> {
+        Ok(self
+            .download_regions(&self.inner.archive_url(date))
+            .await?
+            .regions)
+    }
+
+    /// Download and parse a regions dump, saving it to the save path if one is
+    /// set.
+    async fn download_regions(&self, url: &str) -> Result<ParsedDump> {
+        let body = self.download(url).await?;
+        let save_path = self.inner.save_path.clone();
+        let options = self.inner.parse_options();
+
+        blocking(move || {
+            if let Some(save_path) = save_path {
+                fs::write(save_path, &body)?;
+            }
+
+            let started = Instant::now();
+            let parsed = parse_dump(body.as_slice(), &options)?;
+
+            debug!(
+                "Parsed {} regions in {:.2?}",
+                parsed.regions.len(),
+                started.elapsed()
+            );
+            Ok(parsed)
+        })
+        .await
+    }
+
+    /// Download and parse the nations data dump.
+    pub async fn get_nations_dump(&self) -> Result<Vec<Nation>> {
+        let body = self.download(&self.inner.nations_dump_url()).await?;
+
+        blocking(move || {
+            let started = Instant::now();
+            let nations = parse_nations_dump(body.as_slice())?;
+
+            debug!(
+                "Parsed {} nations in {:.2?}",
+                nations.len(),
+                started.elapsed()
+            );
+            Ok(nations)
+        })
+        .await
+    }
+
+    /// Get the regions matching all of the given tags. Tags prefixed with `-`
+    /// match regions without that tag.
+    pub async fn get_regions_by_tag(&self, tags: &[&str]) -> Result<Vec<String>> {
+        let (exclude, include): (Vec<&str>, Vec<&str>) =
+            tags.iter().partition(|tag| tag.starts_with('-'));
+        let exclude: Vec<&str> = exclude.iter().map(|tag| &tag[1..]).collect();
+
+        self.get_regions_by_tags(&include, &exclude).await
+    }
+
+    /// Get the regions that have every tag in `include` and none in
+    /// `exclude`, batching and caching requests. See
+    /// [`Client::get_regions_by_tags`].
+    pub async fn get_regions_by_tags(
+        &self,
+        include: &[&str],
+        exclude: &[&str],
+    ) -> Result<Vec<String>> {
+        let mut batches = Vec::new();
+
+        for batch in tag_batches(include, exclude)? {
+            let url = self.inner.tag_url(&batch);
+
+            let regions = match self.inner.cached_tag_batch(&url, &batch) {
+                Some(regions) => regions,
+                None => {
+                    let body = self.download(&url).await?;
+                    let regions = parse_region_list(std::str::from_utf8(&body)?)?;
+                    self.inner.cache_tag_batch(url, &batch, &regions);
+                    regions
+                }
+            };
+
+            batches.push(regions);
+        }
+
+        Ok(intersect_batches(batches))
+    }
+
+    pub async fn get_governorless_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["governorless"], &[]).await
+    }
+
+    pub async fn get_passwordless_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&[], &["password"]).await
+    }
+
+    pub async fn get_frontier_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["frontier"], &[]).await
+    }
+
+    /// Get the regions tagged Invader. See [`Client::get_invader_regions`].
+    pub async fn get_invader_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["invader"], &[]).await
+    }
+
+    pub async fn get_defender_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["defender"], &[]).await
+    }
+
+    /// Get NS's current count of nations in the world.
+    pub async fn get_world_nation_count(&self) -> Result<i32> {
+        let body = self.download(&self.inner.nation_count_url()).await?;
+        parse_nation_count(std::str::from_utf8(&body)?)
+    }
+
+    /// Get the regions founded or refounded since the given time. See
+    /// [`Client::get_new_regions`].
+    pub async fn get_new_regions(&self, since: DateTime<Utc>) -> Result<Vec<String>> {
+        let body = self.download(&self.inner.foundings_url(since)).await?;
+        parse_foundings(std::str::from_utf8(&body)?)
+    }
+}
+
+/// Run work that blocks, such as parsing a dump, on Tokio's blocking threads
+/// so that other tasks keep running meanwhile.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    match tokio::task::spawn_blocking(work).await {
+        Ok(result) => result,
+        // blocking tasks can't be aborted, so the work must have panicked
+        Err(error) => panic::resume_unwind(error.into_panic()),
+    }
+}
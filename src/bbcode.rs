@@ -0,0 +1,49 @@
+//! BBCode tables of a few regions, for NationStates dispatches.
+
+use crate::{Column, Dump, Result, TimesheetOptions};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+impl Dump {
+    /// Write a BBCode table of the timesheet, with each region's update times
+    /// and delegate endorsements, for publishing target lists in a
+    /// NationStates dispatch. Region names use NS's `[region]` tag, which
+    /// links to the region. Like [`Dump::to_markdown`], this is meant for a
+    /// few regions picked out with [`TimesheetOptions::filter`].
+    pub fn to_bbcode(
+        &self,
+        output_file: impl AsRef<Path>,
+        options: &TimesheetOptions,
+    ) -> Result<()> {
+        options.check_precision()?;
+
+        let columns = [Column::Minor, Column::Major, Column::DelegateEndorsements];
+
+        let mut bbcode = format!("[table]\n[tr][td][b]{}[/b][/td]", Column::Region.header());
+        for column in columns {
+            bbcode.push_str(&format!("[td][b]{}[/b][/td]", column.header()));
+        }
+        bbcode.push_str("[/tr]\n");
+
+        for row in self.rows(options)? {
+            bbcode.push_str(&format!("[tr][td][region]{}[/region][/td]", row.name));
+            for column in columns {
+                bbcode.push_str(&format!(
+                    "[td]{}[/td]",
+                    row.text(column, options.timestamp_precision)
+                ));
+            }
+            bbcode.push_str("[/tr]\n");
+        }
+        bbcode.push_str("[/table]\n");
+
+        let mut file = BufWriter::new(File::create(output_file)?);
+        file.write_all(bbcode.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
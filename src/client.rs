@@ -0,0 +1,1024 @@
+//! The NationStates client: transports, rate limiting, and API requests.
+
+#[cfg(feature = "async")]
+use crate::async_client;
+use crate::model::{compute_dump_date, governorless_in};
+use crate::parser::{
+    keep_partial, parse_dump, parse_foundings, parse_nation_count, parse_nations_dump,
+    parse_region_list, stream_dump, HashReader, ParseOptions, ParsedDump, ProgressReader,
+    TeeReader,
+};
+use crate::{expected_dump_date, Dump, DumpExtras, Nation, Region, Result, SrsglassError};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufWriter, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "net")]
+use ureq::Agent;
+
+/// Progress through reading a dump, reported to the hook set with
+/// [`Client::with_progress`].
+#[derive(Clone, Copy, Debug)]
+pub enum Progress {
+    /// Compressed bytes read so far, out of the total size of the dump if known
+    Read { bytes: u64, total: Option<u64> },
+    /// Regions parsed so far
+    Parsed { regions: usize },
+}
+
+pub(crate) type ProgressHook = Arc<dyn Fn(Progress) + Send + Sync>;
+
+pub struct Client {
+    pub(crate) transport: Box<dyn Transport>,
+    pub(crate) dump_base_url: String,
+    pub(crate) api_base_url: String,
+    pub(crate) progress: Option<ProgressHook>,
+    pub(crate) save_path: Option<PathBuf>,
+    pub(crate) retries: u32,
+    pub(crate) backoff: Duration,
+    pub(crate) stale_retries: u32,
+    pub(crate) stale_interval: Duration,
+    pub(crate) read_factbooks: bool,
+    pub(crate) read_embassies: bool,
+    pub(crate) allow_partial: bool,
+    /// Regions listed by each tag query made, by URL
+    pub(crate) tag_cache: Mutex<HashMap<String, Vec<String>>>,
+    /// Directory to keep tag query responses in between runs, if any
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// How long tag query responses kept on disk are used for
+    pub(crate) cache_ttl: Duration,
+}
+
+/// A gzipped dump being downloaded by a [`Transport`].
+pub struct DumpBody {
+    pub reader: Box<dyn Read + Send>,
+    /// The size of the gzipped dump in bytes, if known
+    pub length: Option<u64>,
+}
+
+/// Sends the requests a [`Client`] makes, so that tests and applications can
+/// serve dumps and API responses from somewhere other than NS.
+///
+/// Errors returned by a transport are retried by the client if
+/// [`SrsglassError::is_transient`] says they are transient.
+pub trait Transport: Send + Sync {
+    /// Start downloading the gzipped dump at `url`.
+    fn fetch_dump(&self, url: &str) -> Result<DumpBody>;
+
+    /// Send an API request to `url`, returning the body of the response.
+    fn fetch_api(&self, url: &str) -> Result<String>;
+}
+
+/// The default transport, which sends requests over HTTP while staying within
+/// NS's rate limit.
+#[cfg(feature = "net")]
+struct HttpTransport {
+    agent: Agent,
+    rate_limiter: RateLimiter,
+}
+
+#[cfg(feature = "net")]
+impl HttpTransport {
+    fn new(agent: Agent) -> Self {
+        Self {
+            agent,
+            rate_limiter: RateLimiter::default(),
+        }
+    }
+
+    /// Send a GET request once NS's rate limit allows it.
+    fn get(&self, url: &str) -> Result<ureq::Response> {
+        thread::sleep(self.rate_limiter.delay());
+
+        match self.agent.get(url).call() {
+            Ok(response) => {
+                self.rate_limiter.update(|name| response.header(name));
+                Ok(response)
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                self.rate_limiter.update(|name| response.header(name));
+                Err(ureq::Error::Status(status, response).into())
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+impl Transport for HttpTransport {
+    fn fetch_dump(&self, url: &str) -> Result<DumpBody> {
+        let response = self.get(url)?;
+
+        Ok(DumpBody {
+            length: content_length(&response),
+            reader: response.into_reader(),
+        })
+    }
+
+    fn fetch_api(&self, url: &str) -> Result<String> {
+        Ok(self.get(url)?.into_string()?)
+    }
+}
+
+/// A transport that fails every request, so that dumps can only be read
+/// from files or buffers. This is the default without the `net` feature.
+#[cfg(not(feature = "net"))]
+pub(crate) struct OfflineTransport {
+    /// Why requests cannot be made
+    pub(crate) reason: &'static str,
+}
+
+#[cfg(not(feature = "net"))]
+impl OfflineTransport {
+    fn error(&self) -> SrsglassError {
+        SrsglassError::InvalidOption(self.reason.to_string())
+    }
+}
+
+#[cfg(not(feature = "net"))]
+impl Transport for OfflineTransport {
+    fn fetch_dump(&self, _url: &str) -> Result<DumpBody> {
+        Err(self.error())
+    }
+
+    fn fetch_api(&self, _url: &str) -> Result<String> {
+        Err(self.error())
+    }
+}
+
+/// Delays requests to stay within NS's rate limit, based on the rate limit
+/// headers of previous responses. Used by both [`Client`] and
+/// [`AsyncClient`](async_client::AsyncClient).
+#[cfg(feature = "net")]
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    next_request: Mutex<Option<Instant>>,
+}
+
+#[cfg(feature = "net")]
+impl RateLimiter {
+    /// Get how long to wait until NS will allow another request.
+    pub(crate) fn delay(&self) -> Duration {
+        self.next_request
+            .lock()
+            .unwrap()
+            .map_or(Duration::ZERO, |next_request| {
+                next_request.saturating_duration_since(Instant::now())
+            })
+    }
+
+    /// Delay further requests if a response says the rate limit has been
+    /// reached, either with `Retry-After` or by having no requests remaining
+    /// before the window resets. `header` gets a header of the response by
+    /// name.
+    pub(crate) fn update<'r>(&self, header: impl Fn(&str) -> Option<&'r str>) {
+        let header = |name| header(name).and_then(|value| value.parse::<u64>().ok());
+
+        let delay = match (
+            header("Retry-After"),
+            header("RateLimit-Remaining"),
+            header("RateLimit-Reset"),
+        ) {
+            (Some(retry_after), _, _) => retry_after,
+            (None, Some(0), Some(reset)) => reset,
+            _ => return,
+        };
+
+        *self.next_request.lock().unwrap() = Some(Instant::now() + Duration::from_secs(delay));
+    }
+}
+
+/// Builds a [`Client`] that downloads from a mirror or test server instead of
+/// NS, or connects through a proxy.
+#[cfg(feature = "net")]
+pub struct ClientBuilder {
+    user_nation: String,
+    dump_base_url: String,
+    api_base_url: String,
+    proxy: Option<String>,
+    proxy_from_env: bool,
+}
+
+#[cfg(feature = "net")]
+impl ClientBuilder {
+    pub fn new(user_nation: &str) -> Self {
+        Self {
+            user_nation: user_nation.to_string(),
+            dump_base_url: NS_BASE_URL.to_string(),
+            api_base_url: NS_BASE_URL.to_string(),
+            proxy: None,
+            proxy_from_env: false,
+        }
+    }
+
+    /// Set the base URL dumps are downloaded from, under which the current
+    /// dumps are in `/pages` and archived dumps in `/archive/regions`.
+    /// Defaults to `https://www.nationstates.net`.
+    pub fn dump_base_url(mut self, url: impl Into<String>) -> Self {
+        self.dump_base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the base URL API requests are sent to, under which the API is at
+    /// `/cgi-bin/api.cgi`. Defaults to `https://www.nationstates.net`.
+    pub fn api_base_url(mut self, url: impl Into<String>) -> Self {
+        self.api_base_url = url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send all requests through a proxy, given as
+    /// `<protocol>://<user>:<password>@<host>:<port>`. Only the host is
+    /// required. Overrides [`ClientBuilder::proxy_from_env`].
+    pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Set whether to use the proxy in the `HTTPS_PROXY` or `HTTP_PROXY`
+    /// environment variables, if one is set. Defaults to `false`.
+    pub fn proxy_from_env(mut self, proxy_from_env: bool) -> Self {
+        self.proxy_from_env = proxy_from_env;
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let mut agent = ureq::AgentBuilder::new()
+            .user_agent(&user_agent(&self.user_nation))
+            .try_proxy_from_env(self.proxy_from_env);
+
+        if let Some(proxy) = &self.proxy {
+            let proxy = ureq::Proxy::new(proxy).map_err(|error| {
+                SrsglassError::InvalidOption(format!("Invalid proxy {}: {}", proxy, error))
+            })?;
+            agent = agent.proxy(proxy);
+        }
+
+        Ok(Client::from_parts(
+            Box::new(HttpTransport::new(agent.build())),
+            self.dump_base_url,
+            self.api_base_url,
+        ))
+    }
+
+    /// Build an [`AsyncClient`](async_client::AsyncClient) with the same
+    /// URLs and proxy.
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<async_client::AsyncClient> {
+        let mut client = reqwest::Client::builder().user_agent(user_agent(&self.user_nation));
+
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy).map_err(|error| {
+                SrsglassError::InvalidOption(format!("Invalid proxy {}: {}", proxy, error))
+            })?;
+            client = client.proxy(proxy);
+        } else if !self.proxy_from_env {
+            client = client.no_proxy();
+        }
+
+        Ok(async_client::AsyncClient::from_parts(
+            client.build()?,
+            self.build()?,
+        ))
+    }
+}
+
+impl Client {
+    pub fn new(user_nation: &str) -> Self {
+        #[cfg(feature = "net")]
+        let transport = HttpTransport::new(
+            ureq::AgentBuilder::new()
+                .user_agent(&user_agent(user_nation))
+                .build(),
+        );
+
+        // the user nation only identifies requests, which can't be made
+        #[cfg(not(feature = "net"))]
+        let transport = {
+            let _ = user_nation;
+            OfflineTransport {
+                reason: "srsglass was built without the net feature, so it cannot download dumps",
+            }
+        };
+
+        Self::from_parts(
+            Box::new(transport),
+            NS_BASE_URL.to_string(),
+            NS_BASE_URL.to_string(),
+        )
+    }
+
+    /// Start building a client with a custom base URL or proxy.
+    #[cfg(feature = "net")]
+    pub fn builder(user_nation: &str) -> ClientBuilder {
+        ClientBuilder::new(user_nation)
+    }
+
+    pub(crate) fn from_parts(
+        transport: Box<dyn Transport>,
+        dump_base_url: String,
+        api_base_url: String,
+    ) -> Self {
+        Self {
+            transport,
+            dump_base_url,
+            api_base_url,
+            progress: None,
+            save_path: None,
+            retries: 3,
+            backoff: Duration::from_secs(1),
+            stale_retries: 0,
+            stale_interval: Duration::from_secs(5 * 60),
+            read_factbooks: true,
+            read_embassies: true,
+            allow_partial: false,
+            tag_cache: Mutex::new(HashMap::new()),
+            cache_dir: None,
+            cache_ttl: Duration::ZERO,
+        }
+    }
+
+    /// Send requests through `transport` instead of over HTTP, for example to
+    /// serve fixtures in tests. Dumps and API requests are still requested
+    /// from the client's base URLs.
+    pub fn with_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Box::new(transport);
+        self
+    }
+
+    /// Set whether to read each region's factbook from regions dumps. When
+    /// disabled, factbooks are skipped without being parsed, which makes
+    /// reading dumps faster and uses less memory. Defaults to `true`.
+    pub fn with_factbooks(mut self, read_factbooks: bool) -> Self {
+        self.read_factbooks = read_factbooks;
+        self
+    }
+
+    /// Set whether to read each region's embassies from regions dumps. When
+    /// disabled, embassies are skipped without being parsed. Defaults to
+    /// `true`.
+    pub fn with_embassies(mut self, read_embassies: bool) -> Self {
+        self.read_embassies = read_embassies;
+        self
+    }
+
+    /// Set whether to keep the regions read from a dump that is truncated or
+    /// corrupt, rather than failing with [`SrsglassError::TruncatedDump`].
+    /// Dumps read this way are marked [`Dump::incomplete`]. Downloads are
+    /// still retried first. Defaults to `false`.
+    pub fn with_partial_dumps(mut self, allow_partial: bool) -> Self {
+        self.allow_partial = allow_partial;
+        self
+    }
+
+    /// Retry requests and downloads that fail with a transient error up to
+    /// `retries` times, waiting `backoff` before the first retry and twice as
+    /// long before each one after. Defaults to 3 retries with a backoff of one
+    /// second.
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
+    /// Download the current dump again up to `retries` times, waiting
+    /// `interval` before each attempt, while NS is still serving a dump older
+    /// than [`expected_dump_date`], as it sometimes does for a while after
+    /// major update. Defaults to no retries.
+    pub fn with_stale_retries(mut self, retries: u32, interval: Duration) -> Self {
+        self.stale_retries = retries;
+        self.stale_interval = interval;
+        self
+    }
+
+    /// Save downloaded dumps to `save_path` as they are read, so that they can
+    /// be reused later with [`Client::get_dump_from_file`].
+    pub fn with_save_path(mut self, save_path: impl Into<PathBuf>) -> Self {
+        self.save_path = Some(save_path.into());
+        self
+    }
+
+    /// Keep the regions listed by tag queries in `cache_dir` and reuse them
+    /// for `ttl`, so that repeated runs don't ask NS again. Which regions are
+    /// governorless, passworded, or frontiers rarely changes within a day,
+    /// but changes made within `ttl` of a query are missed. Defaults to not
+    /// caching between runs.
+    pub fn with_tag_cache(mut self, cache_dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        self.cache_dir = Some(cache_dir.into());
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Set a hook to be called with progress updates while reading dumps.
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Wrap a dump reader so that it reports progress to the progress hook.
+    fn track<R: Read>(&self, inner: R, total: Option<u64>) -> ProgressReader<'_, R> {
+        ProgressReader {
+            inner,
+            bytes: 0,
+            total,
+            progress: self.progress.as_ref(),
+        }
+    }
+
+    /// Run a request, retrying it with exponential backoff if it fails with a
+    /// transient error.
+    fn with_retry<T>(&self, mut request: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut attempt = 0;
+        let mut delay = self.backoff;
+
+        loop {
+            match request() {
+                Err(error) if attempt < self.retries && error.is_transient() => {
+                    warn!("{}; retrying in {:.0?}", error, delay);
+                    thread::sleep(delay);
+                    attempt += 1;
+                    delay *= 2;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Get how dumps are read, as set on the client.
+    pub(crate) fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            factbooks: self.read_factbooks,
+            embassies: self.read_embassies,
+            allow_partial: self.allow_partial,
+            progress: self.progress.clone(),
+        }
+    }
+
+    /// Report progress to the progress hook, if one is set.
+    #[cfg(feature = "async")]
+    pub(crate) fn report(&self, progress: Progress) {
+        if let Some(hook) = &self.progress {
+            hook(progress);
+        }
+    }
+
+    pub(crate) fn regions_dump_url(&self) -> String {
+        format!("{}/pages/regions.xml.gz", self.dump_base_url)
+    }
+
+    pub(crate) fn nations_dump_url(&self) -> String {
+        format!("{}/pages/nations.xml.gz", self.dump_base_url)
+    }
+
+    /// Get the URL of the archived regions dump NS generated on the given
+    /// date.
+    pub(crate) fn archive_url(&self, date: NaiveDate) -> String {
+        format!(
+            "{}/archive/regions/{}-regions-xml.gz",
+            self.dump_base_url,
+            date.format("%Y-%m-%d")
+        )
+    }
+
+    /// Get the API URL listing the regions matching all of the given tags.
+    pub(crate) fn tag_url(&self, tags: &[String]) -> String {
+        format!(
+            "{}/cgi-bin/api.cgi?q=regionsbytag;tags={}",
+            self.api_base_url,
+            tags.join(",")
+        )
+    }
+
+    pub(crate) fn nation_count_url(&self) -> String {
+        format!("{}/cgi-bin/api.cgi?q=numnations", self.api_base_url)
+    }
+
+    /// Get the API URL listing founding happenings since the given time.
+    pub(crate) fn foundings_url(&self, since: DateTime<Utc>) -> String {
+        format!(
+            "{}/cgi-bin/api.cgi?q=happenings;filter=founding;limit=200;sincetime={}",
+            self.api_base_url,
+            since.timestamp()
+        )
+    }
+
+    /// Download the current regions dump.
+    ///
+    /// Governorless regions are fetched from the API, falling back to each
+    /// region's governor in the dump if the API request fails.
+    ///
+    /// If NS serves a stale dump, it is downloaded again as set by
+    /// [`Client::with_stale_retries`].
+    pub fn get_dump(&self) -> Result<Dump> {
+        let mut attempt = 0;
+
+        loop {
+            let parsed = self.download_regions(&self.regions_dump_url())?;
+
+            let dump_date = compute_dump_date(&parsed.regions)?;
+            if attempt < self.stale_retries && dump_date < expected_dump_date() {
+                warn!(
+                    "NS is still serving the data dump from {}; downloading again in {:.0?}",
+                    dump_date, self.stale_interval
+                );
+                thread::sleep(self.stale_interval);
+                attempt += 1;
+                continue;
+            }
+
+            return self.online_dump(parsed);
+        }
+    }
+
+    /// Get the archived dump NS generated on the given date.
+    ///
+    /// Governorless, passwordless, frontier, and Invader and Defender tagged
+    /// regions are still fetched from the live API, so they reflect the
+    /// current state of each region rather than its state on that date. If
+    /// the governorless request fails, each region's governor in the archived
+    /// dump is used instead.
+    pub fn get_dump_for_date(&self, date: NaiveDate) -> Result<Dump> {
+        let parsed = self.download_regions(&self.archive_url(date))?;
+
+        Ok(Dump {
+            dump_date: date,
+            ..self.online_dump(parsed)?
+        })
+    }
+
+    pub fn get_dump_from_file<P: AsRef<Path>>(&self, dump_path: P) -> Result<Dump> {
+        let parsed = self.regions_from_file(dump_path)?;
+        self.online_dump(parsed)
+    }
+
+    /// Read a dump from a reader, such as standard input, requesting which
+    /// regions are governorless, passworded, and frontiers as with
+    /// [`Client::get_dump_from_file`].
+    pub fn get_dump_from_reader(&self, dump: impl Read + Send) -> Result<Dump> {
+        let parsed = parse_dump(self.track(dump, None), &self.parse_options())?;
+        self.online_dump(parsed)
+    }
+
+    /// Build a dump from regions read from a dump, requesting the rest of the
+    /// dump from the API.
+    fn online_dump(&self, parsed: ParsedDump) -> Result<Dump> {
+        let ParsedDump {
+            regions,
+            incomplete,
+            sha256,
+        } = parsed;
+
+        let governorless = self.get_governorless_regions().unwrap_or_else(|error| {
+            warn!(
+                "could not get governorless regions, using governors in the dump instead: {}",
+                error
+            );
+            governorless_in(&regions)
+        });
+        let passwordless = self.get_passwordless_regions()?;
+        let frontiers = self.get_frontier_regions()?;
+        let invaders = self.get_invader_regions()?;
+        let defenders = self.get_defender_regions()?;
+
+        let dump_date = compute_dump_date(&regions)?;
+
+        Ok(Dump {
+            dump_date,
+            regions,
+            governorless,
+            passwordless,
+            frontiers,
+            invaders,
+            defenders,
+            incomplete,
+            sha256: Some(sha256),
+        })
+    }
+
+    /// Read a dump from a file without making any API requests.
+    ///
+    /// Governorless regions are determined from each region's governor in the
+    /// dump. The dump does not record whether a region is passworded or a
+    /// frontier, so every region is treated as a passwordless stronghold.
+    pub fn get_dump_from_file_offline<P: AsRef<Path>>(&self, dump_path: P) -> Result<Dump> {
+        let parsed = self.regions_from_file(dump_path)?;
+        self.offline_dump(parsed)
+    }
+
+    /// Read a dump from a reader, such as a dump already in memory, whether
+    /// it is gzipped as NS publishes it or has already been decompressed,
+    /// without making any API requests, as with
+    /// [`Client::get_dump_from_file_offline`].
+    pub fn get_dump_from_reader_offline(&self, dump: impl Read + Send) -> Result<Dump> {
+        let parsed = parse_dump(self.track(dump, None), &self.parse_options())?;
+        self.offline_dump(parsed)
+    }
+
+    /// Build a dump from regions read without making any API requests.
+    fn offline_dump(&self, parsed: ParsedDump) -> Result<Dump> {
+        Ok(Dump {
+            incomplete: parsed.incomplete,
+            sha256: Some(parsed.sha256),
+            ..Dump::from_regions(parsed.regions, DumpExtras::default())?
+        })
+    }
+
+    pub fn get_regions(&self) -> Result<Vec<Region>> {
+        Ok(self.download_regions(&self.regions_dump_url())?.regions)
+    }
+
+    pub fn get_regions_for_date(&self, date: NaiveDate) -> Result<Vec<Region>> {
+        Ok(self.download_regions(&self.archive_url(date))?.regions)
+    }
+
+    /// Download and parse a regions dump, saving it to the save path if one is
+    /// set.
+    fn download_regions(&self, url: &str) -> Result<ParsedDump> {
+        let started = Instant::now();
+        let mut regions = Vec::new();
+        let mut sha256 = String::new();
+
+        // the dump is parsed as it downloads, so the two are timed together,
+        // and a partial dump is only kept once retrying has failed
+        let result = self.with_retry(|| {
+            regions.clear();
+            let on_region = |region| {
+                regions.push(region);
+                Ok(())
+            };
+
+            let body = self.transport.fetch_dump(url)?;
+            let mut hasher = Sha256::new();
+            let reader = HashReader {
+                inner: self.track(body.reader, body.length),
+                hasher: &mut hasher,
+            };
+
+            let result = match &self.save_path {
+                Some(save_path) => stream_dump(
+                    TeeReader {
+                        inner: reader,
+                        writer: BufWriter::new(File::create(save_path)?),
+                    },
+                    &self.parse_options(),
+                    on_region,
+                ),
+                None => stream_dump(reader, &self.parse_options(), on_region),
+            };
+
+            sha256 = format!("{:x}", hasher.finalize());
+            result
+        });
+        let incomplete = keep_partial(result, self.allow_partial)?;
+
+        debug!(
+            "Downloaded and parsed {} regions from {} in {:.2?}",
+            regions.len(),
+            url,
+            started.elapsed()
+        );
+        Ok(ParsedDump {
+            regions,
+            incomplete,
+            sha256,
+        })
+    }
+
+    /// Download the regions dump and pass each region to `on_region` as it is
+    /// read, without keeping the whole dump in memory. Regions are passed in
+    /// the order of the dump, with `nations_before` already counted.
+    ///
+    /// Only the initial request is retried, since regions read before a
+    /// failure have already been passed on. The dump is not saved to the save
+    /// path.
+    pub fn stream_regions(&self, on_region: impl FnMut(Region) -> Result<()>) -> Result<()> {
+        let body = self.with_retry(|| self.transport.fetch_dump(&self.regions_dump_url()))?;
+        stream_dump(
+            self.track(body.reader, body.length),
+            &self.parse_options(),
+            on_region,
+        )
+    }
+
+    /// Read a regions dump from a file, passing each region to `on_region` as
+    /// it is read, as with [`Client::stream_regions`].
+    pub fn stream_regions_from_file<P: AsRef<Path>>(
+        &self,
+        dump_path: P,
+        on_region: impl FnMut(Region) -> Result<()>,
+    ) -> Result<()> {
+        let file = File::open(dump_path)?;
+        let total = file.metadata()?.len();
+        stream_dump(
+            self.track(file, Some(total)),
+            &self.parse_options(),
+            on_region,
+        )
+    }
+
+    pub fn get_regions_from_file<P: AsRef<Path>>(&self, dump_path: P) -> Result<Vec<Region>> {
+        Ok(self.regions_from_file(dump_path)?.regions)
+    }
+
+    /// Read a regions dump from a file.
+    fn regions_from_file<P: AsRef<Path>>(&self, dump_path: P) -> Result<ParsedDump> {
+        let started = Instant::now();
+        let file = File::open(&dump_path)?;
+        let total = file.metadata()?.len();
+        let parsed = parse_dump(self.track(file, Some(total)), &self.parse_options())?;
+
+        debug!(
+            "Parsed {} regions from {} in {:.2?}",
+            parsed.regions.len(),
+            dump_path.as_ref().display(),
+            started.elapsed()
+        );
+        Ok(parsed)
+    }
+
+    /// Download and parse the nations data dump.
+    pub fn get_nations_dump(&self) -> Result<Vec<Nation>> {
+        let started = Instant::now();
+        let nations = self.with_retry(|| {
+            let body = self.transport.fetch_dump(&self.nations_dump_url())?;
+            parse_nations_dump(self.track(body.reader, body.length))
+        })?;
+
+        debug!(
+            "Downloaded and parsed {} nations in {:.2?}",
+            nations.len(),
+            started.elapsed()
+        );
+        Ok(nations)
+    }
+
+    /// Get the regions matching all of the given tags. Tags prefixed with `-`
+    /// match regions without that tag. See [`Client::get_regions_by_tags`].
+    pub fn get_regions_by_tag(&self, tags: &[&str]) -> Result<Vec<String>> {
+        let (exclude, include): (Vec<&str>, Vec<&str>) =
+            tags.iter().partition(|tag| tag.starts_with('-'));
+        let exclude: Vec<&str> = exclude.iter().map(|tag| &tag[1..]).collect();
+
+        self.get_regions_by_tags(&include, &exclude)
+    }
+
+    /// Get the regions that have every tag in `include` and none in
+    /// `exclude`.
+    ///
+    /// NS accepts at most ten tags in a request, so longer queries are split
+    /// into batches and the regions matching every batch are kept. Each
+    /// batch is only requested once over the life of the client, so the
+    /// same tags can be queried again for free, and batches kept on disk
+    /// with [`Client::with_tag_cache`] are not requested at all.
+    pub fn get_regions_by_tags(&self, include: &[&str], exclude: &[&str]) -> Result<Vec<String>> {
+        let mut batches = Vec::new();
+
+        for batch in tag_batches(include, exclude)? {
+            let url = self.tag_url(&batch);
+
+            let regions = match self.cached_tag_batch(&url, &batch) {
+                Some(regions) => regions,
+                None => {
+                    let regions = self.parse_api_response(&url)?;
+                    self.cache_tag_batch(url, &batch, &regions);
+                    regions
+                }
+            };
+
+            batches.push(regions);
+        }
+
+        Ok(intersect_batches(batches))
+    }
+
+    /// Get the regions a batch of tags listed, if it was queried before by
+    /// this client or recently enough to be kept on disk.
+    pub(crate) fn cached_tag_batch(&self, url: &str, batch: &[String]) -> Option<Vec<String>> {
+        if let Some(regions) = self.tag_cache.lock().unwrap().get(url) {
+            return Some(regions.clone());
+        }
+
+        let path = self.tag_cache_path(batch)?;
+        let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+        if age >= self.cache_ttl {
+            return None;
+        }
+
+        let regions: Vec<String> = fs::read_to_string(&path)
+            .ok()?
+            .lines()
+            .map(String::from)
+            .collect();
+
+        debug!("Using {} cached {:.0?} ago", url, age);
+        self.tag_cache
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), regions.clone());
+        Some(regions)
+    }
+
+    /// Keep the regions a batch of tags listed for the rest of the client's
+    /// life, and on disk if a cache directory is set.
+    pub(crate) fn cache_tag_batch(&self, url: String, batch: &[String], regions: &[String]) {
+        if let Some(path) = self.tag_cache_path(batch) {
+            // the cache only saves requests, so failing to write it is not
+            // worth stopping for
+            let written = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(&path, regions.join("\n")));
+            if let Err(error) = written {
+                warn!("could not cache {} in {}: {}", url, path.display(), error);
+            }
+        }
+
+        self.tag_cache.lock().unwrap().insert(url, regions.to_vec());
+    }
+
+    /// Get the file a batch of tags is cached in, if caching on disk.
+    fn tag_cache_path(&self, batch: &[String]) -> Option<PathBuf> {
+        let name: String = batch
+            .join(",")
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | '0'..='9' | '_' | '-' | ',' => c,
+                _ => '_',
+            })
+            .collect();
+
+        // the same tags list different regions on different sites, such as a
+        // test server, so each site's batches are kept apart
+        let site = format!("{:x}", Sha256::digest(&self.api_base_url));
+
+        Some(
+            self.cache_dir
+                .as_ref()?
+                .join(format!("tags-{}-{}.txt", &site[..16], name)),
+        )
+    }
+
+    pub fn get_governorless_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["governorless"], &[])
+    }
+
+    pub fn get_passwordless_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&[], &["password"])
+    }
+
+    pub fn get_frontier_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["frontier"], &[])
+    }
+
+    /// Get the regions tagged Invader, which raiders tag the regions they
+    /// hold with.
+    pub fn get_invader_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["invader"], &[])
+    }
+
+    pub fn get_defender_regions(&self) -> Result<Vec<String>> {
+        self.get_regions_by_tags(&["defender"], &[])
+    }
+
+    /// Get NS's current count of nations in the world.
+    pub fn get_world_nation_count(&self) -> Result<i32> {
+        let started = Instant::now();
+        let url = self.nation_count_url();
+        let count = self.with_retry(|| parse_nation_count(&self.transport.fetch_api(&url)?))?;
+
+        debug!("Fetched {} in {:.2?}", url, started.elapsed());
+        Ok(count)
+    }
+
+    /// Get the regions founded or refounded since the given time, such as
+    /// [`Dump::last_update`], oldest first, from NS's happenings. NS only
+    /// lists recent happenings, so regions founded long ago may be missing.
+    pub fn get_new_regions(&self, since: DateTime<Utc>) -> Result<Vec<String>> {
+        let started = Instant::now();
+        let url = self.foundings_url(since);
+        let regions = self.with_retry(|| parse_foundings(&self.transport.fetch_api(&url)?))?;
+
+        debug!("Fetched {} in {:.2?}", url, started.elapsed());
+        Ok(regions)
+    }
+
+    fn parse_api_response(&self, url: &str) -> Result<Vec<String>> {
+        let started = Instant::now();
+        let regions = self.with_retry(|| parse_region_list(&self.transport.fetch_api(url)?))?;
+
+        debug!("Fetched {} in {:.2?}", url, started.elapsed());
+        Ok(regions)
+    }
+}
+
+const NS_BASE_URL: &str = "https://www.nationstates.net";
+
+/// Most tags NS accepts in one `regionsbytag` request.
+const MAX_TAGS_PER_REQUEST: usize = 10;
+
+/// Split a tag query into batches small enough for one request each, with
+/// excluded tags prefixed with `-` as NS expects.
+pub(crate) fn tag_batches(include: &[&str], exclude: &[&str]) -> Result<Vec<Vec<String>>> {
+    let tags: Vec<String> = include
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .chain(
+            exclude
+                .iter()
+                .map(|tag| format!("-{}", tag.trim().to_lowercase())),
+        )
+        .collect();
+
+    if tags.is_empty() {
+        return Err(SrsglassError::InvalidOption(
+            "A tag query needs at least one tag".to_string(),
+        ));
+    }
+
+    Ok(tags
+        .chunks(MAX_TAGS_PER_REQUEST)
+        .map(<[String]>::to_vec)
+        .collect())
+}
+
+/// Keep the regions listed in every batch of a tag query, in the order of
+/// the first batch.
+pub(crate) fn intersect_batches(batches: Vec<Vec<String>>) -> Vec<String> {
+    let mut batches = batches.into_iter();
+    let first = batches.next().unwrap_or_default();
+    let rest: Vec<HashSet<String>> = batches.map(|batch| batch.into_iter().collect()).collect();
+
+    first
+        .into_iter()
+        .filter(|region| rest.iter().all(|batch| batch.contains(region)))
+        .collect()
+}
+
+/// Build the user agent srsglass identifies itself to NS with.
+#[cfg(feature = "net")]
+pub(crate) fn user_agent(user_nation: &str) -> String {
+    format!(
+        "{}/{} (by:Esfalsa, usedBy:{})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        user_nation
+    )
+}
+
+/// Get the size of a response body from its `Content-Length` header.
+#[cfg(feature = "net")]
+fn content_length(response: &ureq::Response) -> Option<u64> {
+    response.header("Content-Length")?.parse().ok()
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter_waits_out_the_limit() {
+        let rate_limiter = RateLimiter::default();
+        assert_eq!(rate_limiter.delay(), Duration::ZERO);
+
+        // requests left in the window don't need to wait
+        rate_limiter.update(|name| match name {
+            "RateLimit-Remaining" => Some("10"),
+            "RateLimit-Reset" => Some("30"),
+            _ => None,
+        });
+        assert_eq!(rate_limiter.delay(), Duration::ZERO);
+
+        rate_limiter.update(|name| match name {
+            "RateLimit-Remaining" => Some("0"),
+            "RateLimit-Reset" => Some("30"),
+            _ => None,
+        });
+        assert!(rate_limiter.delay() > Duration::from_secs(29));
+
+        rate_limiter.update(|name| (name == "Retry-After").then_some("5"));
+        assert!(rate_limiter.delay() > Duration::from_secs(4));
+        assert!(rate_limiter.delay() <= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn tag_cache_is_kept_per_site() {
+        let batch = ["invader".to_string(), "-defender".to_string()];
+        let client = Client::new("test").with_tag_cache("cache", Duration::from_secs(60));
+        let mut test_client = Client::new("test").with_tag_cache("cache", Duration::from_secs(60));
+        test_client.api_base_url = "http://localhost:8080".to_string();
+
+        let path = client.tag_cache_path(&batch).unwrap();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        assert!(file_name.starts_with("tags-"));
+        assert!(file_name.ends_with("-invader,-defender.txt"));
+        assert_ne!(test_client.tag_cache_path(&batch), Some(path));
+    }
+}
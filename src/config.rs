@@ -0,0 +1,220 @@
+use crate::{Format, LinkStyleArg, ModelArg};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use srsglass::{parse_color, ColorRule, Column, Styling};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Name of the config file srsglass looks for in the working directory and
+/// the user config directory
+pub const DEFAULT_CONFIG_PATH: &str = "srsglass.toml";
+
+/// Defaults read from a config file. Flags passed on the command line take
+/// precedence over these.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The name of your nation, to identify you to NationStates
+    pub nation: Option<String>,
+    /// Length of major update, in seconds
+    pub major: Option<i32>,
+    /// Length of minor update, in seconds
+    pub minor: Option<i32>,
+    /// The number of milliseconds to use in timestamps
+    pub precision: Option<i32>,
+    /// Model used to estimate update times
+    pub model: Option<ModelArg>,
+    /// Time zone of wall-clock update times, such as `Europe/London`
+    pub timezone: Option<String>,
+    /// Number of times to retry failed requests and downloads
+    pub retries: Option<u32>,
+    /// Proxy to send requests through
+    pub proxy: Option<String>,
+    /// Output file format
+    pub format: Option<Format>,
+    /// Template for the name of the output file, as in `--name-template`
+    pub name_template: Option<String>,
+    /// Rules for coloring regions, in the same form as `--color-rule`
+    pub color_rules: Vec<String>,
+    /// Columns to include in timesheets, in the same form as `--columns`
+    pub columns: Vec<String>,
+    /// How to link to each region's page, as in `--link-style`
+    pub link_style: Option<LinkStyleArg>,
+    /// Fill colors for highlighting timesheets
+    pub colors: Colors,
+    /// Named tag queries, each added to timesheets as a column marking the
+    /// regions that match it
+    pub tag_sets: Vec<TagQuery>,
+}
+
+/// A named tag query read from a `[[tag_sets]]` table of a config file.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TagQuery {
+    /// Header of the tag set's column
+    pub name: String,
+    /// Tags regions in the set must have
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Tags regions in the set must not have
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Fill colors read from the `[colors]` table of a config file, each a color
+/// name or hex code as in `--color-rule`. See [`Styling`] for what each is
+/// used for.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub open_target: Option<String>,
+    pub executive_target: Option<String>,
+    pub passworded: Option<String>,
+    pub frontier: Option<String>,
+    pub no_delegate: Option<String>,
+    pub friendly_embassies: Option<String>,
+    pub enemy_embassies: Option<String>,
+}
+
+impl Config {
+    /// Load the config file at `path`. If no path is given, look for
+    /// [`DEFAULT_CONFIG_PATH`] in the working directory and then in the user
+    /// config directory, such as `~/.config/srsglass`.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) => path,
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let config: Config = toml::from_str(&fs::read_to_string(path)?)?;
+        config.check_tag_sets()?;
+
+        Ok(config)
+    }
+
+    /// Check that each tag set's name can head its column, which must differ
+    /// from every other header, ignoring case as Excel does.
+    fn check_tag_sets(&self) -> Result<()> {
+        for (index, tag_set) in self.tag_sets.iter().enumerate() {
+            let name = tag_set.name.to_lowercase();
+
+            if let Some(column) = Column::ALL
+                .into_iter()
+                .find(|column| column.header().to_lowercase() == name)
+            {
+                return Err(anyhow!(
+                    "Tag set {} has the same name as the {} column",
+                    tag_set.name,
+                    column.header()
+                ));
+            }
+
+            if self.tag_sets[..index]
+                .iter()
+                .any(|other| other.name.to_lowercase() == name)
+            {
+                return Err(anyhow!("More than one tag set is named {}", tag_set.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Find the config file to use when none is given, if there is one.
+    fn default_path() -> Option<PathBuf> {
+        let local = PathBuf::from(DEFAULT_CONFIG_PATH);
+        if local.exists() {
+            return Some(local);
+        }
+
+        let user = dirs::config_dir()?
+            .join("srsglass")
+            .join(DEFAULT_CONFIG_PATH);
+        user.exists().then_some(user)
+    }
+
+    /// Parse the configured color rules.
+    pub fn color_rules(&self) -> Result<Vec<ColorRule>> {
+        Ok(self
+            .color_rules
+            .iter()
+            .map(|rule| rule.parse())
+            .collect::<srsglass::Result<_>>()?)
+    }
+
+    /// Get the configured styling, using the default color for any that are
+    /// not set.
+    pub fn styling(&self) -> Result<Styling> {
+        let mut styling = Styling::default();
+
+        for (color, field) in [
+            (&self.colors.open_target, &mut styling.open_target),
+            (&self.colors.executive_target, &mut styling.executive_target),
+            (&self.colors.passworded, &mut styling.passworded),
+            (&self.colors.frontier, &mut styling.frontier),
+            (&self.colors.no_delegate, &mut styling.no_delegate),
+            (
+                &self.colors.friendly_embassies,
+                &mut styling.friendly_embassies,
+            ),
+            (&self.colors.enemy_embassies, &mut styling.enemy_embassies),
+        ] {
+            if let Some(color) = color {
+                *field = parse_color(color)?;
+            }
+        }
+
+        Ok(styling)
+    }
+
+    /// Parse the configured columns.
+    pub fn columns(&self) -> Result<Vec<Column>> {
+        Ok(self
+            .columns
+            .iter()
+            .map(|column| column.parse())
+            .collect::<srsglass::Result<_>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag_sets(names: &[&str]) -> Config {
+        Config {
+            tag_sets: names
+                .iter()
+                .map(|name| TagQuery {
+                    name: name.to_string(),
+                    include: vec!["invader".to_string()],
+                    exclude: Vec::new(),
+                })
+                .collect(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn tag_set_names_must_differ_from_other_headers() {
+        assert!(tag_sets(&["Invaders", "Defenders"])
+            .check_tag_sets()
+            .is_ok());
+
+        let error = tag_sets(&["password"]).check_tag_sets().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Tag set password has the same name as the Password column"
+        );
+
+        let error = tag_sets(&["Invaders", "INVADERS"])
+            .check_tag_sets()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "More than one tag set is named INVADERS");
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::warn;
+use std::{
+    io::{self, Write},
+    process::Command,
+    thread,
+    time::Duration,
+};
+
+/// Count down to `target` on a single line, redrawn every second, ringing the
+/// terminal bell as each of the `alerts` (in seconds before `target`) passes
+/// and once more when the region is due to update. With `notify`, each alert
+/// is also sent as a desktop notification.
+pub fn run(region: &str, target: DateTime<Utc>, alerts: &[u64], notify: bool) -> Result<()> {
+    let mut alerts = pending_alerts(alerts, seconds_until(target));
+
+    let mut stdout = io::stdout();
+
+    loop {
+        let remaining = seconds_until(target);
+        if remaining <= 0.0 {
+            alert(&format!("{} is due to update now", region), notify);
+            writeln!(stdout, "\r{} is due to update now{:10}", region, "")?;
+            return Ok(());
+        }
+
+        write!(
+            stdout,
+            "\rT-{} until {} updates ",
+            format_countdown(remaining.ceil() as u64),
+            region
+        )?;
+        stdout.flush()?;
+
+        for alert_at in due_alerts(&mut alerts, remaining) {
+            alert(
+                &format!("T-{} until {} updates", format_countdown(alert_at), region),
+                notify,
+            );
+        }
+
+        // wake up as the next whole second remains, so the countdown ticks
+        // over on time
+        let until_tick = remaining - (remaining.ceil() - 1.0);
+        thread::sleep(Duration::from_secs_f64(until_tick.max(0.01)));
+    }
+}
+
+/// Get the alerts still to come with `remaining` seconds left, in the order
+/// they come, skipping those already passed and any given twice.
+fn pending_alerts(alerts: &[u64], remaining: f64) -> Vec<u64> {
+    let mut alerts: Vec<u64> = alerts
+        .iter()
+        .copied()
+        .filter(|&alert| remaining > alert as f64)
+        .collect();
+    alerts.sort_unstable_by(|a, b| b.cmp(a));
+    alerts.dedup();
+    alerts
+}
+
+/// Remove and return the pending alerts that are due with `remaining`
+/// seconds left.
+fn due_alerts(alerts: &mut Vec<u64>, remaining: f64) -> Vec<u64> {
+    let due = alerts
+        .iter()
+        .take_while(|&&alert| remaining <= alert as f64)
+        .count();
+    alerts.drain(..due).collect()
+}
+
+fn seconds_until(target: DateTime<Utc>) -> f64 {
+    (target - Utc::now()).num_milliseconds() as f64 / 1000.0
+}
+
+/// Format a number of seconds as `H:MM:SS`, or `M:SS` under an hour.
+fn format_countdown(seconds: u64) -> String {
+    let (h, m, s) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// Ring the terminal bell, and send a desktop notification if asked to.
+fn alert(message: &str, notify: bool) {
+    print!("\x07");
+    let _ = io::stdout().flush();
+
+    if !notify {
+        return;
+    }
+
+    // a missed notification shouldn't stop the countdown
+    if let Err(error) = send_notification(message) {
+        warn!("could not send desktop notification: {}", error);
+    }
+}
+
+/// PowerShell script showing `$env:SRSGLASS_NOTIFICATION` as a toast
+/// notification. Toasts must come from an app Windows knows, so it borrows
+/// PowerShell's.
+const WINDOWS_TOAST: &str = r#"
+[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null
+$template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$text = $template.GetElementsByTagName('text')
+$text.Item(0).AppendChild($template.CreateTextNode('srsglass')) | Out-Null
+$text.Item(1).AppendChild($template.CreateTextNode($env:SRSGLASS_NOTIFICATION)) | Out-Null
+$app = '{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe'
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($app).Show([Windows.UI.Notifications.ToastNotification]::new($template))
+"#;
+
+/// Send a desktop notification with the platform's own notifier.
+fn send_notification(message: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {:?} with title \"srsglass\"",
+            message
+        ));
+        command
+    } else if cfg!(windows) {
+        // Windows has no notifier command, so show a toast through PowerShell,
+        // passing the message in the environment to keep it out of the script
+        let mut command = Command::new("powershell.exe");
+        command
+            .args(["-NoProfile", "-NonInteractive", "-Command", WINDOWS_TOAST])
+            .env("SRSGLASS_NOTIFICATION", message);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.arg("srsglass").arg(message);
+        command
+    };
+
+    let status = command.status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "{:?} exited with {}",
+            command.get_program(),
+            status
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown_shows_hours_only_when_needed() {
+        assert_eq!(format_countdown(0), "0:00");
+        assert_eq!(format_countdown(59), "0:59");
+        assert_eq!(format_countdown(61), "1:01");
+        assert_eq!(format_countdown(3599), "59:59");
+        assert_eq!(format_countdown(3600), "1:00:00");
+        assert_eq!(format_countdown(37230), "10:20:30");
+    }
+
+    #[test]
+    fn passed_and_repeated_alerts_are_pruned() {
+        let mut alerts = pending_alerts(&[10, 60, 300, 60, 900], 120.0);
+        assert_eq!(alerts, [60, 10]);
+
+        // nothing is due until the first alert's time comes
+        assert!(due_alerts(&mut alerts, 60.5).is_empty());
+        assert_eq!(due_alerts(&mut alerts, 60.0), [60]);
+
+        // a tick that skips past several alerts rings each of them once
+        let mut alerts = pending_alerts(&[5, 10, 20], 30.0);
+        assert_eq!(due_alerts(&mut alerts, 4.0), [20, 10, 5]);
+        assert!(alerts.is_empty());
+    }
+}
@@ -0,0 +1,29 @@
+//! CSV timesheets.
+
+use crate::{Dump, Result, TimesheetOptions};
+use std::path::Path;
+
+impl Dump {
+    /// Write the timesheet to a CSV file, with the same per-region columns as
+    /// [`Dump::to_excel`].
+    pub fn to_csv(&self, output_file: impl AsRef<Path>, options: &TimesheetOptions) -> Result<()> {
+        options.check_precision()?;
+
+        let mut writer = csv::Writer::from_path(output_file)?;
+
+        writer.write_record(options.columns.iter().map(|&column| options.header(column)))?;
+
+        for row in self.rows(options)? {
+            writer.write_record(
+                options
+                    .columns
+                    .iter()
+                    .map(|&column| row.text(column, options.timestamp_precision)),
+            )?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
+}
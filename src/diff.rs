@@ -0,0 +1,210 @@
+use crate::{canonical_name, Dump, Region, Result};
+use rust_xlsxwriter::Workbook;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// A change to a region between two dumps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    Founded,
+    CeasedToExist,
+    PasswordAdded,
+    PasswordRemoved,
+    DelegateChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// The delegate gained or lost executive authority
+    ExecutiveChanged {
+        from: bool,
+        to: bool,
+    },
+    GovernorChanged {
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
+
+impl Change {
+    /// Get the values before and after the change, if it has any.
+    pub fn values(&self) -> Option<(String, String)> {
+        let name = |name: &Option<String>| name.clone().unwrap_or_else(|| "None".to_string());
+
+        match self {
+            Change::DelegateChanged { from, to } | Change::GovernorChanged { from, to } => {
+                Some((name(from), name(to)))
+            }
+            Change::ExecutiveChanged { from, to } => Some((from.to_string(), to.to_string())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Change::Founded => "Founded",
+            Change::CeasedToExist => "Ceased to exist",
+            Change::PasswordAdded => "Password added",
+            Change::PasswordRemoved => "Password removed",
+            Change::DelegateChanged { .. } => "Delegate changed",
+            Change::ExecutiveChanged { .. } => "Executive authority changed",
+            Change::GovernorChanged { .. } => "Governor changed",
+        };
+
+        f.write_str(description)
+    }
+}
+
+/// A change to a single region.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionChange {
+    pub region: String,
+    pub change: Change,
+}
+
+/// Find the changes to each region between an older and a newer dump, in the
+/// update order of the newer dump, followed by regions that ceased to exist.
+///
+/// Password changes are found by comparing the passwordless regions of each
+/// dump. NS's dumps do not record passwords, so these are only meaningful
+/// between dumps fetched at different times, as archived dumps are given the
+/// current password status of each region.
+pub fn diff(old: &Dump, new: &Dump) -> Vec<RegionChange> {
+    let old_regions: HashMap<String, &Region> = old
+        .regions
+        .iter()
+        .filter_map(|region| Some((region.canonical_name()?, region)))
+        .collect();
+
+    let new_names: HashSet<String> = new
+        .regions
+        .iter()
+        .filter_map(Region::canonical_name)
+        .collect();
+
+    let old_passwordless = passwordless(old);
+    let new_passwordless = passwordless(new);
+
+    let mut changes = Vec::new();
+
+    for region in &new.regions {
+        let Some(name) = &region.name else {
+            continue;
+        };
+        let key = canonical_name(name);
+
+        let mut push = |change| {
+            changes.push(RegionChange {
+                region: name.clone(),
+                change,
+            })
+        };
+
+        let Some(old_region) = old_regions.get(&key) else {
+            push(Change::Founded);
+            continue;
+        };
+
+        match (old_passwordless.get(&key), new_passwordless.get(&key)) {
+            (Some(true), Some(false)) => push(Change::PasswordAdded),
+            (Some(false), Some(true)) => push(Change::PasswordRemoved),
+            _ => (),
+        }
+
+        if old_region.delegate != region.delegate {
+            push(Change::DelegateChanged {
+                from: old_region.delegate.clone(),
+                to: region.delegate.clone(),
+            });
+        } else if let (Some(from), Some(to)) = (old_region.delegate_exec, region.delegate_exec) {
+            // a new delegate's authority is not a change to the old one's
+            if from != to {
+                push(Change::ExecutiveChanged { from, to });
+            }
+        }
+
+        if old_region.governor != region.governor {
+            push(Change::GovernorChanged {
+                from: old_region.governor.clone(),
+                to: region.governor.clone(),
+            });
+        }
+    }
+
+    for region in &old.regions {
+        let Some(name) = &region.name else {
+            continue;
+        };
+
+        if !new_names.contains(&canonical_name(name)) {
+            changes.push(RegionChange {
+                region: name.clone(),
+                change: Change::CeasedToExist,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Map each region in a dump to whether it is passwordless.
+fn passwordless(dump: &Dump) -> HashMap<String, bool> {
+    let passwordless: HashSet<String> = dump
+        .passwordless
+        .iter()
+        .map(|name| canonical_name(name))
+        .collect();
+
+    dump.regions
+        .iter()
+        .filter_map(|region| {
+            let name = region.canonical_name()?;
+            let is_passwordless = passwordless.contains(&name);
+            Some((name, is_passwordless))
+        })
+        .collect()
+}
+
+/// Build a workbook listing the changes between two dumps.
+pub fn to_workbook(old: &Dump, new: &Dump, changes: &[RegionChange]) -> Result<Workbook> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name("Changes")?;
+
+    worksheet.write_row(0, 0, ["Region", "Link", "Change", "Old", "New"])?;
+
+    worksheet.set_column_width(0, 45)?;
+    worksheet.set_column_width(2, 30)?;
+    worksheet.set_column_width(3, 45)?;
+    worksheet.set_column_width(4, 45)?;
+
+    worksheet.write_column(0, 6, ["Old Dump", "New Dump"])?;
+    worksheet.write_string(0, 7, old.dump_date.to_string())?;
+    worksheet.write_string(1, 7, new.dump_date.to_string())?;
+
+    worksheet.set_freeze_panes(1, 0)?;
+
+    for (row_index, change) in (1..).zip(changes) {
+        worksheet.write_string(row_index, 0, &change.region)?;
+        worksheet.write_url(
+            row_index,
+            1,
+            format!(
+                "https://www.nationstates.net/region={}",
+                canonical_name(&change.region)
+            )
+            .as_str(),
+        )?;
+        worksheet.write_string(row_index, 2, change.change.to_string())?;
+
+        if let Some((from, to)) = change.change.values() {
+            worksheet.write_string(row_index, 3, from)?;
+            worksheet.write_string(row_index, 4, to)?;
+        }
+    }
+
+    Ok(workbook)
+}
@@ -0,0 +1,190 @@
+//! The error type returned by srsglass, so that callers can tell failures
+//! apart without matching on messages.
+
+use std::{error::Error, fmt, io};
+
+/// A boxed error from a dependency, kept as the source of an
+/// [`SrsglassError`].
+type Source = Box<dyn Error + Send + Sync>;
+
+/// An error from downloading, reading, or writing a dump or timesheet.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SrsglassError {
+    /// A request to NationStates failed, with the HTTP status if NS responded
+    Network { status: Option<u16>, source: Source },
+    /// A dump or API response could not be parsed
+    Parse(Source),
+    /// Reading or writing a file failed
+    Io(io::Error),
+    /// A dump ended before all of its regions were read, because it was
+    /// truncated or corrupt, with the number of regions read before then and
+    /// the error reading the rest, if any
+    TruncatedDump {
+        regions: usize,
+        source: Option<Source>,
+    },
+    /// A dump was parsed but is missing data srsglass needs
+    InvalidDump(String),
+    /// An option, color rule, or column was invalid
+    InvalidOption(String),
+    /// Writing an Excel workbook failed
+    Excel(rust_xlsxwriter::XlsxError),
+    /// Writing a CSV, ODS, JSON, SQLite, or Parquet timesheet failed
+    Output(Source),
+}
+
+pub type Result<T> = std::result::Result<T, SrsglassError>;
+
+impl SrsglassError {
+    /// Check whether the error is likely to go away if the request is
+    /// retried: rate limiting, server errors, and dropped connections.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SrsglassError::Network {
+                status: Some(status),
+                ..
+            } => *status == 429 || *status >= 500,
+            SrsglassError::Network { status: None, .. } => true,
+            // reading a response body can fail partway through a download
+            SrsglassError::Io(_) | SrsglassError::TruncatedDump { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SrsglassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SrsglassError::Network {
+                status: Some(status),
+                ..
+            } => write!(f, "Request to NationStates failed with status {}", status),
+            SrsglassError::Network {
+                status: None,
+                source,
+            } => write!(f, "Request to NationStates failed: {}", source),
+            SrsglassError::Parse(source) => write!(f, "Could not parse response: {}", source),
+            SrsglassError::Io(source) => source.fmt(f),
+            SrsglassError::TruncatedDump {
+                regions,
+                source: Some(source),
+            } => write!(
+                f,
+                "Data dump is truncated or corrupt after {} regions: {}",
+                regions, source
+            ),
+            SrsglassError::TruncatedDump {
+                regions,
+                source: None,
+            } => write!(f, "Data dump ended early after {} regions", regions),
+            SrsglassError::InvalidDump(message) | SrsglassError::InvalidOption(message) => {
+                f.write_str(message)
+            }
+            SrsglassError::Excel(source) => write!(f, "Could not write workbook: {}", source),
+            SrsglassError::Output(source) => write!(f, "Could not write timesheet: {}", source),
+        }
+    }
+}
+
+impl Error for SrsglassError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SrsglassError::Network { source, .. }
+            | SrsglassError::Parse(source)
+            | SrsglassError::Output(source)
+            | SrsglassError::TruncatedDump {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            SrsglassError::Io(source) => Some(source),
+            SrsglassError::Excel(source) => Some(source),
+            SrsglassError::TruncatedDump { source: None, .. }
+            | SrsglassError::InvalidDump(_)
+            | SrsglassError::InvalidOption(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SrsglassError {
+    fn from(error: io::Error) -> Self {
+        SrsglassError::Io(error)
+    }
+}
+
+#[cfg(feature = "net")]
+impl From<ureq::Error> for SrsglassError {
+    fn from(error: ureq::Error) -> Self {
+        let status = match &error {
+            ureq::Error::Status(status, _) => Some(*status),
+            ureq::Error::Transport(_) => None,
+        };
+
+        SrsglassError::Network {
+            status,
+            source: Box::new(error),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<reqwest::Error> for SrsglassError {
+    fn from(error: reqwest::Error) -> Self {
+        SrsglassError::Network {
+            status: error.status().map(|status| status.as_u16()),
+            source: Box::new(error),
+        }
+    }
+}
+
+impl From<quick_xml::Error> for SrsglassError {
+    fn from(error: quick_xml::Error) -> Self {
+        match error {
+            // quick_xml shares IO errors between clones, so rewrap them
+            quick_xml::Error::Io(error) => SrsglassError::Io(io::Error::new(error.kind(), error)),
+            error => SrsglassError::Parse(Box::new(error)),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident, $($error:ty),+) => {
+        $(
+            impl From<$error> for SrsglassError {
+                fn from(error: $error) -> Self {
+                    SrsglassError::$variant(Box::new(error))
+                }
+            }
+        )+
+    };
+}
+
+impl_from!(
+    Parse,
+    quick_xml::events::attributes::AttrError,
+    std::num::ParseIntError,
+    std::num::ParseFloatError,
+    std::str::Utf8Error,
+    chrono::ParseError
+);
+
+impl_from!(Output, csv::Error, zip::result::ZipError);
+
+#[cfg(feature = "serde")]
+impl_from!(Output, serde_json::Error);
+
+#[cfg(feature = "sqlite")]
+impl_from!(Output, rusqlite::Error);
+
+#[cfg(feature = "parquet")]
+impl_from!(
+    Output,
+    parquet::errors::ParquetError,
+    arrow_schema::ArrowError
+);
+
+impl From<rust_xlsxwriter::XlsxError> for SrsglassError {
+    fn from(error: rust_xlsxwriter::XlsxError) -> Self {
+        SrsglassError::Excel(error)
+    }
+}
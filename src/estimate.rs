@@ -0,0 +1,563 @@
+use crate::{canonical_name, Dump, Region, Result, SrsglassError};
+use std::str::FromStr;
+
+/// Model used to estimate when each region updates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Model {
+    /// Assume nations update at a constant rate, as Spyglass does.
+    #[default]
+    Linear,
+    /// Fit a cost per nation and a cost per region to the update timestamps
+    /// observed in the dump, so that runs of small regions, which take longer
+    /// per nation to update, are accounted for.
+    Nonlinear,
+}
+
+/// An update in which regions are estimated to update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Update {
+    Major,
+    Minor,
+}
+
+/// How far through each update a region is estimated to update, as a
+/// fraction of the length of the update.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    /// Number of nations updating before the region in major update
+    pub nations_before: i32,
+    /// Number of nations updating before the region in minor update, counted
+    /// in [`minor_order`]
+    pub nations_before_minor: i32,
+    pub major: f64,
+    pub minor: f64,
+}
+
+impl Position {
+    /// Get the position in the given update.
+    pub fn get(&self, update: Update) -> f64 {
+        match update {
+            Update::Major => self.major,
+            Update::Minor => self.minor,
+        }
+    }
+}
+
+impl Update {
+    /// Get the timestamp at which a region last updated in this update.
+    pub fn timestamp(self, region: &Region) -> Option<i64> {
+        match self {
+            Update::Major => region.last_major,
+            Update::Minor => region.last_minor,
+        }
+    }
+
+    /// Get the timestamp at which a region last updated in this update, or
+    /// `None` if it has not updated in it since it was founded, which NS
+    /// records as a timestamp of 0.
+    pub fn observed(self, region: &Region) -> Option<i64> {
+        self.timestamp(region).filter(|&time| time > 0)
+    }
+}
+
+/// Estimated update times of a region, in seconds from the start of each
+/// update.
+///
+/// A region that has not updated in an update since it was founded has no
+/// observed place in it, so it is assumed to update after every region that
+/// has. Such estimates are flagged by a missing [`RegionEstimate::last_major`]
+/// or [`RegionEstimate::last_minor`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionEstimate<'a> {
+    pub name: &'a str,
+    /// Number of nations updating before the region in major update
+    pub nations_before: i32,
+    /// Number of nations updating before the region in minor update
+    pub nations_before_minor: i32,
+    pub major: f64,
+    pub minor: f64,
+    /// When the region updated in the last major update, if it did
+    pub last_major: Option<i64>,
+    /// When the region updated in the last minor update, if it did
+    pub last_minor: Option<i64>,
+}
+
+/// Relative cost of updating each nation and each region.
+#[derive(Clone, Copy, Debug)]
+struct Costs {
+    nation: f64,
+    region: f64,
+}
+
+impl Costs {
+    /// Costs under which update speed is proportional to nations alone.
+    const LINEAR: Costs = Costs {
+        nation: 1.0,
+        region: 0.0,
+    };
+
+    /// Fit costs by least squares to the offset of each region's observed
+    /// update timestamp from the first timestamp, given the number of nations
+    /// and regions updating before it. Returns `None` if there are too few
+    /// timestamps or the fit is not meaningful.
+    fn fit(samples: &[(i32, usize, Option<i64>)]) -> Option<Costs> {
+        let samples: Vec<(i32, usize, i64)> = samples
+            .iter()
+            .filter_map(|&(nations, regions, time)| Some((nations, regions, time?)))
+            .collect();
+
+        let start = samples.iter().map(|&(_, _, time)| time).min()?;
+
+        let (mut s_nn, mut s_nr, mut s_rr, mut s_nt, mut s_rt) = (0.0, 0.0, 0.0, 0.0, 0.0);
+
+        for (nations, regions, time) in samples {
+            let n = nations as f64;
+            let r = regions as f64;
+            let t = (time - start) as f64;
+
+            s_nn += n * n;
+            s_nr += n * r;
+            s_rr += r * r;
+            s_nt += n * t;
+            s_rt += r * t;
+        }
+
+        let determinant = s_nn * s_rr - s_nr * s_nr;
+        if determinant.abs() < f64::EPSILON {
+            return None;
+        }
+
+        let nation = (s_nt * s_rr - s_nr * s_rt) / determinant;
+        let region = (s_nn * s_rt - s_nr * s_nt) / determinant;
+
+        (nation >= 0.0 && region >= 0.0 && nation + region > 0.0)
+            .then_some(Costs { nation, region })
+    }
+
+    fn cost(&self, nations: i32, regions: usize) -> f64 {
+        self.nation * nations as f64 + self.region * regions as f64
+    }
+}
+
+/// Get the indices of the regions in the dump in the order they are expected
+/// to update. Newly founded regions, which have not updated yet, are moved to
+/// the end, since NS updates them after every existing region.
+pub fn update_order(dump: &Dump) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..dump.regions.len()).collect();
+    order.sort_by_key(|&index| dump.regions[index].is_new());
+    order
+}
+
+/// Estimate how far through major and minor update each region in the dump
+/// updates. Major update follows [`update_order`], and minor update follows
+/// [`minor_order`], since regions founded after major update are placed
+/// differently at minor. The result is indexed in the same order as the
+/// regions in the dump, and regions missing the data needed for an estimate
+/// are `None`.
+///
+/// With [`Model::Nonlinear`], an update whose timestamps in the dump cannot
+/// be fit falls back to the linear model.
+pub fn positions(dump: &Dump, model: Model) -> Result<Vec<Option<Position>>> {
+    let major_order = update_order(dump);
+    let minor_order = minor_order(dump);
+
+    let major = positions_in_order(dump, model, Update::Major, &major_order)?;
+    let minor = positions_in_order(dump, model, Update::Minor, &minor_order)?;
+
+    // index minor positions by dump order to match them up with major
+    let mut minor_positions = vec![None; dump.regions.len()];
+    for (index, minor) in minor_order.into_iter().zip(minor) {
+        minor_positions[index] = minor;
+    }
+
+    let mut positions = vec![None; dump.regions.len()];
+
+    for (index, major) in major_order.into_iter().zip(major) {
+        if let (Some((nations_before, major)), Some((nations_before_minor, minor))) =
+            (major, minor_positions[index])
+        {
+            positions[index] = Some(Position {
+                nations_before,
+                nations_before_minor,
+                major,
+                minor,
+            });
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Get how long an update took, in seconds, from the first and last update
+/// timestamps in the dump. Regions that have never updated are ignored.
+pub fn observed_length(dump: &Dump, update: Update) -> Option<i64> {
+    let timestamps = dump
+        .regions
+        .iter()
+        .filter_map(|region| update.observed(region));
+
+    let (first, last) = timestamps.fold(None, |range, time| match range {
+        None => Some((time, time)),
+        Some((first, last)) => Some((i64::min(first, time), i64::max(last, time))),
+    })?;
+
+    Some(last - first)
+}
+
+/// Number of equal parts of an update over which the spread of observed times
+/// around the estimates is measured separately.
+const UNCERTAINTY_BINS: usize = 20;
+
+/// How far observed update times stray from the estimates in each part of an
+/// update, since estimates late in an update are less reliable than early
+/// ones.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Uncertainty {
+    /// Root mean square difference between where regions updated in the last
+    /// update and where they were estimated to, as a fraction of the update,
+    /// in each part of the update that any region updated in
+    bins: [Option<f64>; UNCERTAINTY_BINS],
+}
+
+impl Uncertainty {
+    /// Measure the spread of the regions' observed positions in the last
+    /// update around their estimated positions.
+    pub fn measure(dump: &Dump, model: Model, update: Update) -> Result<Self> {
+        let mut squares = [0.0; UNCERTAINTY_BINS];
+        let mut counts = [0; UNCERTAINTY_BINS];
+
+        // observed positions are fractions of the last update's length, so
+        // that a longer or shorter update is not mistaken for spread
+        let observed = observed_start(dump, update)
+            .zip(observed_length(dump, update).filter(|&length| length > 0));
+
+        if let Some((start, length)) = observed {
+            for (region, position) in dump.regions.iter().zip(positions(dump, model)?) {
+                let (Some(position), Some(time)) = (position, update.observed(region)) else {
+                    continue;
+                };
+
+                let estimated = position.get(update);
+                let residual = (time - start) as f64 / length as f64 - estimated;
+
+                let bin =
+                    ((estimated * UNCERTAINTY_BINS as f64) as usize).min(UNCERTAINTY_BINS - 1);
+                squares[bin] += residual * residual;
+                counts[bin] += 1;
+            }
+        }
+
+        let mut bins = [None; UNCERTAINTY_BINS];
+        for (bin, (square, count)) in bins.iter_mut().zip(squares.into_iter().zip(counts)) {
+            if count > 0 {
+                *bin = Some((square / count as f64).sqrt());
+            }
+        }
+
+        Ok(Self { bins })
+    }
+
+    /// Get the uncertainty of an estimate at a position in the update, as a
+    /// fraction of the update, or `None` if no region updated near there.
+    pub fn at(&self, position: f64) -> Option<f64> {
+        let bin = ((position * UNCERTAINTY_BINS as f64) as usize).min(UNCERTAINTY_BINS - 1);
+        self.bins[bin]
+    }
+}
+
+/// When a region was seen to update early in the update in progress, used to
+/// rescale the estimates for the rest of the update to its actual speed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Anchor {
+    /// Name of the region
+    pub region: String,
+    /// Seconds from the start of the update at which the region updated
+    pub seconds: f64,
+}
+
+impl FromStr for Anchor {
+    type Err = SrsglassError;
+
+    /// Parse an anchor of the form `region=time`, where the time is a number
+    /// of seconds from the start of the update or `H:MM:SS`.
+    fn from_str(s: &str) -> Result<Self> {
+        // region names cannot contain `=`, but split at the last one anyway
+        let (region, time) = s.rsplit_once('=').ok_or_else(|| {
+            SrsglassError::InvalidOption(format!("Anchor {} must be of the form region=time", s))
+        })?;
+
+        let seconds = time
+            .trim()
+            .split(':')
+            .try_fold(0.0, |seconds, part| {
+                Some(seconds * 60.0 + part.parse::<f64>().ok()?)
+            })
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .ok_or_else(|| SrsglassError::InvalidOption(format!("Invalid anchor time {}", time)))?;
+
+        Ok(Self {
+            region: region.trim().to_string(),
+            seconds,
+        })
+    }
+}
+
+/// Mapping from positions in an update to seconds from its start, passing
+/// through the observed times of any anchors and otherwise assuming the
+/// update runs at a constant speed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    /// Positions and the times they are reached, sorted by position, starting
+    /// at the start of the update
+    points: Vec<(f64, f64)>,
+}
+
+impl Calibration {
+    /// Calibrate an update of the given length in seconds to the anchors.
+    /// Between anchors, times are interpolated linearly, and past the last
+    /// anchor, the update is assumed to keep the speed it had since the
+    /// anchor before it.
+    pub fn new(
+        dump: &Dump,
+        model: Model,
+        update: Update,
+        length: i32,
+        anchors: &[Anchor],
+    ) -> Result<Self> {
+        let mut points = vec![(0.0, 0.0)];
+
+        if !anchors.is_empty() {
+            let positions = positions(dump, model)?;
+            let mut anchored = Vec::new();
+
+            for anchor in anchors {
+                let name = canonical_name(&anchor.region);
+                let (region, position) = dump
+                    .regions
+                    .iter()
+                    .zip(&positions)
+                    .find(|(region, _)| region.canonical_name().as_ref() == Some(&name))
+                    .and_then(|(region, position)| Some((region, (*position)?)))
+                    .ok_or_else(|| {
+                        SrsglassError::InvalidOption(format!(
+                            "Could not find anchor region {}",
+                            anchor.region
+                        ))
+                    })?;
+
+                // the position of a region that has not updated in the update
+                // is only assumed, so it cannot say how fast the update runs
+                if update.observed(region).is_none() {
+                    return Err(SrsglassError::InvalidOption(format!(
+                        "Anchor region {} has not updated in {} update since it was founded",
+                        anchor.region,
+                        match update {
+                            Update::Major => "major",
+                            Update::Minor => "minor",
+                        }
+                    )));
+                }
+
+                anchored.push((position.get(update), anchor));
+            }
+
+            anchored.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            // the update only moves forward, so a region can't have updated
+            // before one ahead of it in the update
+            if let Some(pair) = anchored
+                .windows(2)
+                .find(|pair| pair[1].1.seconds < pair[0].1.seconds)
+            {
+                return Err(SrsglassError::InvalidOption(format!(
+                    "Anchor region {} updated before {}, which updates ahead of it",
+                    pair[1].1.region, pair[0].1.region
+                )));
+            }
+
+            points.extend(
+                anchored
+                    .iter()
+                    .map(|(position, anchor)| (*position, anchor.seconds)),
+            );
+            // anchors at the same position say nothing about the speed of
+            // the update, so only the first is kept
+            points.dedup_by(|a, b| a.0 == b.0);
+        }
+
+        if points.len() < 2 {
+            points.push((1.0, length as f64));
+        }
+
+        Ok(Self { points })
+    }
+
+    /// Get the seconds from the start of the update at which a position in
+    /// the update is reached.
+    pub fn time(&self, position: f64) -> f64 {
+        let index = self
+            .points
+            .partition_point(|&(point, _)| point <= position)
+            .clamp(1, self.points.len() - 1);
+        let (start, start_time) = self.points[index - 1];
+        let (end, end_time) = self.points[index];
+
+        start_time + (position - start) * (end_time - start_time) / (end - start)
+    }
+}
+
+impl Dump {
+    /// Estimate when each region updates, in seconds from the start of major
+    /// and minor update, given the length of each update. Regions missing a
+    /// name or the data needed for an estimate are skipped.
+    ///
+    /// This uses the linear model; see [`positions`] for others.
+    pub fn estimate_times(
+        &self,
+        major_length: i32,
+        minor_length: i32,
+    ) -> Result<Vec<RegionEstimate<'_>>> {
+        // without anchors, both updates are assumed to run at a constant speed
+        self.estimate_with_anchors(major_length, minor_length, Update::Major, &[])
+    }
+
+    /// Estimate when each region updates like [`Dump::estimate_times`], but
+    /// rescale the estimates for one update to pass through the observed
+    /// update times of regions early in it, so that the rest of the update
+    /// is estimated from how fast it is actually running.
+    pub fn estimate_with_anchors(
+        &self,
+        major_length: i32,
+        minor_length: i32,
+        update: Update,
+        anchors: &[Anchor],
+    ) -> Result<Vec<RegionEstimate<'_>>> {
+        let positions = positions(self, Model::Linear)?;
+        let calibrate = |other, length| {
+            let anchors = if other == update { anchors } else { &[] };
+            Calibration::new(self, Model::Linear, other, length, anchors)
+        };
+        let major = calibrate(Update::Major, major_length)?;
+        let minor = calibrate(Update::Minor, minor_length)?;
+
+        Ok(update_order(self)
+            .into_iter()
+            .filter_map(|index| {
+                let position = positions[index]?;
+
+                Some(RegionEstimate {
+                    name: self.regions[index].name.as_deref()?,
+                    nations_before: position.nations_before,
+                    nations_before_minor: position.nations_before_minor,
+                    major: major.time(position.major),
+                    minor: minor.time(position.minor),
+                    last_major: Update::Major.observed(&self.regions[index]),
+                    last_minor: Update::Minor.observed(&self.regions[index]),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Get the indices of the regions in the dump in the order they last updated
+/// in minor update, which can differ from the order of the dump when regions
+/// are founded or refounded between updates. Regions that did not update in
+/// minor update keep their relative order at the end.
+pub fn minor_order(dump: &Dump) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..dump.regions.len()).collect();
+
+    order.sort_by_key(|&index| {
+        Update::Minor
+            .observed(&dump.regions[index])
+            .unwrap_or(i64::MAX)
+    });
+
+    order
+}
+
+/// Get the time the first region updated in the last update, which is
+/// when the update started.
+pub fn observed_start(dump: &Dump, update: Update) -> Option<i64> {
+    dump.regions
+        .iter()
+        .filter_map(|region| update.observed(region))
+        .min()
+}
+
+/// Get how many nations had updated and how many seconds had passed since
+/// the first region updated when each region updated in the last update,
+/// in update order. Regions without a known update time are left out.
+pub fn observed_progress(dump: &Dump, update: Update) -> Vec<(i32, i64)> {
+    let order: Vec<usize> = match update {
+        Update::Major => (0..dump.regions.len()).collect(),
+        Update::Minor => minor_order(dump),
+    };
+
+    let mut nations = 0;
+    let mut start = None;
+    let mut progress = Vec::new();
+
+    for index in order {
+        let region = &dump.regions[index];
+
+        if let Some(time) = update.observed(region) {
+            let start = *start.get_or_insert(time);
+            progress.push((nations, time - start));
+        }
+
+        nations += region.population.unwrap_or(0);
+    }
+
+    progress
+}
+
+/// Estimate how far through an update each region updates if regions update
+/// in the given order, counting nations cumulatively in that order. Returns
+/// the number of nations updating before each region in `order` along with
+/// its position, or `None` if the region's population is unknown.
+pub fn positions_in_order(
+    dump: &Dump,
+    model: Model,
+    update: Update,
+    order: &[usize],
+) -> Result<Vec<Option<(i32, f64)>>> {
+    let total_population = dump.total_population()?;
+    let total_regions = dump.regions.len();
+
+    let mut nations = 0;
+    let mut samples = Vec::with_capacity(order.len());
+
+    for (position, &index) in order.iter().enumerate() {
+        let region = &dump.regions[index];
+        let population = region.population;
+
+        samples.push(population.map(|_| (nations, position, update.observed(region))));
+        nations += population.unwrap_or(0);
+    }
+
+    let costs = match model {
+        Model::Linear => Costs::LINEAR,
+        Model::Nonlinear => {
+            let known: Vec<_> = samples.iter().flatten().copied().collect();
+            Costs::fit(&known).unwrap_or(Costs::LINEAR)
+        }
+    };
+
+    let total = costs.cost(total_population, total_regions);
+
+    Ok(samples
+        .into_iter()
+        .map(|sample| {
+            let (nations_before, regions_before, _) = sample?;
+            Some((
+                nations_before,
+                costs.cost(nations_before, regions_before) / total,
+            ))
+        })
+        .collect())
+}
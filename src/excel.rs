@@ -0,0 +1,791 @@
+//! Excel timesheets, with their update charts and overflow sheets.
+
+use crate::estimate::{self, Update};
+use crate::rows::{format_duration, region_type, split_duration, Row};
+use crate::{
+    canonical_name, Attribute, ColorRule, Column, Dump, LinkStyle, Result, TimesheetOptions,
+};
+use rust_xlsxwriter::{
+    column_number_to_name, Chart, ChartType, Color, ConditionalFormatFormula, ExcelDateTime,
+    Format, Table, Url, Workbook, Worksheet,
+};
+use std::{borrow::Cow, path::Path};
+
+/// Maximum length of cell contents in Excel, in characters.
+/// https://support.microsoft.com/en-us/office/excel-specifications-and-limits-1672b34d-7043-467e-8e27-269d656771c3
+const CELL_LIMIT: usize = 32767;
+
+/// Note ending text cut off to fit in an Excel cell.
+const TRUNCATED_NOTE: &str = " [truncated]";
+
+/// Truncate text to the maximum length of an Excel cell, ending it with a
+/// note if anything was cut off so that the loss is not silent.
+fn truncate_cell(text: &str) -> Cow<'_, str> {
+    match split_cell(text) {
+        (text, None) => Cow::Borrowed(text),
+        (kept, Some(_)) => Cow::Owned(format!("{}{}", kept, TRUNCATED_NOTE)),
+    }
+}
+
+/// Split text into the part that fits in an Excel cell along with the
+/// truncation note, and the part cut off, if it is too long for a cell.
+fn split_cell(text: &str) -> (&str, Option<&str>) {
+    if text.chars().nth(CELL_LIMIT).is_none() {
+        return (text, None);
+    }
+
+    let kept = CELL_LIMIT - TRUNCATED_NOTE.chars().count();
+    let (index, _) = text.char_indices().nth(kept).unwrap();
+
+    (&text[..index], Some(&text[index..]))
+}
+
+impl Dump {
+    /// Write the observed progress of the last major and minor updates to a
+    /// worksheet, with a chart plotting it against the progress estimated
+    /// from the update lengths, so that stalls and fast or slow updates
+    /// stand out.
+    fn write_update_chart(
+        &self,
+        worksheet: &mut Worksheet,
+        options: &TimesheetOptions,
+    ) -> Result<()> {
+        let sheet = worksheet.name();
+        let mut chart = Chart::new(ChartType::ScatterStraight);
+
+        for (column, update, name) in [(0, Update::Major, "Major"), (2, Update::Minor, "Minor")] {
+            let progress = estimate::observed_progress(self, update);
+
+            worksheet.write_row(0, column, ["Nations", name])?;
+            for (row, (nations, elapsed)) in progress.iter().enumerate() {
+                worksheet.write_number(row as u32 + 1, column, *nations)?;
+                worksheet.write_number(row as u32 + 1, column + 1, *elapsed as f64)?;
+            }
+
+            if !progress.is_empty() {
+                let last = progress.len() as u32;
+                chart
+                    .add_series()
+                    .set_name(&format!("Observed {}", name))
+                    .set_categories((sheet.as_str(), 1, column, last, column))
+                    .set_values((sheet.as_str(), 1, column + 1, last, column + 1));
+            }
+        }
+
+        // the progress estimated by a linear model, as a straight line from
+        // the start to the end of each update
+        let total_population = self.total_population()?;
+        worksheet.write_row(0, 4, ["Nations", "Estimated Major", "Estimated Minor"])?;
+        worksheet.write_row(1, 4, [0, 0, 0])?;
+        worksheet.write_row(
+            2,
+            4,
+            [total_population, options.major_length, options.minor_length],
+        )?;
+
+        for (column, name) in [(5, "Estimated Major"), (6, "Estimated Minor")] {
+            chart
+                .add_series()
+                .set_name(name)
+                .set_categories((sheet.as_str(), 1, 4, 2, 4))
+                .set_values((sheet.as_str(), 1, column, 2, column));
+        }
+
+        chart.title().set_name("Update Speed");
+        chart.x_axis().set_name("Nations Updated");
+        chart.y_axis().set_name("Seconds Since Start");
+        chart.set_width(960).set_height(540);
+
+        worksheet.insert_chart(1, 8, &chart)?;
+
+        Ok(())
+    }
+
+    /// Write the timesheet to an Excel workbook.
+    pub fn to_excel(
+        &self,
+        output_file: impl AsRef<Path>,
+        options: &TimesheetOptions,
+    ) -> Result<()> {
+        let mut workbook = self.to_workbook(options)?;
+        workbook.save(output_file)?;
+
+        Ok(())
+    }
+
+    /// Write the timesheet to an Excel workbook in memory, for serving it
+    /// without saving it to a file.
+    pub fn to_excel_buffer(&self, options: &TimesheetOptions) -> Result<Vec<u8>> {
+        let mut workbook = self.to_workbook(options)?;
+        Ok(workbook.save_to_buffer()?)
+    }
+
+    /// Build the timesheet workbook without saving it, so that further
+    /// worksheets can be added to it.
+    pub fn to_workbook(&self, options: &TimesheetOptions) -> Result<Workbook> {
+        options.check_precision()?;
+
+        let total_population = self.total_population()?;
+        let observed_major = estimate::observed_length(self, Update::Major);
+        let observed_minor = estimate::observed_length(self, Update::Minor);
+
+        let TimesheetOptions {
+            major_length,
+            minor_length,
+            ..
+        } = *options;
+
+        let mut workbook = Workbook::new();
+        // name the sheet so that an incomplete timesheet is obvious
+        let worksheet = workbook.add_worksheet().set_name(if self.incomplete {
+            "Timesheet (Incomplete)"
+        } else {
+            "Timesheet"
+        })?;
+
+        let rows = self.rows(options)?;
+
+        // count regions by the first color rule they match, as they are
+        // colored in the timesheet
+        let mut color_counts = vec![0; options.color_rules.len() + 1];
+        for row in &rows {
+            let rule = options
+                .color_rules
+                .iter()
+                .position(|rule| rule.matches(row));
+            color_counts[rule.unwrap_or(options.color_rules.len())] += 1;
+        }
+        let region_count = rows.len();
+
+        // the text cut off from cells that are too long, kept before the rows
+        // are written
+        let overflow: Vec<(&str, Column, String)> = if options.overflow_sheet {
+            rows.iter()
+                .flat_map(|row| {
+                    options
+                        .columns
+                        .iter()
+                        .filter(|column| {
+                            matches!(
+                                column,
+                                Column::Embassies
+                                    | Column::FriendlyEmbassies
+                                    | Column::EnemyEmbassies
+                                    | Column::Factbook
+                            )
+                        })
+                        .filter_map(|&column| {
+                            let text = row.text(column, options.timestamp_precision);
+                            let overflow = split_cell(&text).1?.to_string();
+                            Some((row.name, column, overflow))
+                        })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        write_region_table(worksheet, rows, options)?;
+
+        let worksheet = workbook.add_worksheet().set_name("Summary")?;
+
+        worksheet.write_column(
+            0,
+            0,
+            [
+                "World Data",
+                "Nations",
+                "Major Length",
+                "Secs/Nation",
+                "Nations/Sec",
+                "Minor Length",
+                "Secs/Nation",
+                "Nations/Sec",
+                "Observed Major",
+                "Observed Minor",
+                "",
+                "Srsglass Version",
+                "Date Generated",
+                "Dump Date",
+                "Last Update (UTC)",
+                "Dump SHA-256",
+                "",
+                "Settings",
+                "Model",
+                "Precision",
+                "Time Zone",
+                "Relative To",
+                "",
+                "Regions",
+            ],
+        )?;
+
+        worksheet.write_number(1, 1, total_population)?;
+        worksheet.write_number(2, 1, major_length)?;
+        worksheet.write_number(3, 1, major_length as f64 / total_population as f64)?;
+        worksheet.write_number(4, 1, total_population as f64 / major_length as f64)?;
+        worksheet.write_number(5, 1, minor_length)?;
+        worksheet.write_number(6, 1, minor_length as f64 / total_population as f64)?;
+        worksheet.write_number(7, 1, total_population as f64 / minor_length as f64)?;
+        if let Some(observed_major) = observed_major {
+            worksheet.write_number(8, 1, observed_major as f64)?;
+        }
+        if let Some(observed_minor) = observed_minor {
+            worksheet.write_number(9, 1, observed_minor as f64)?;
+        }
+        worksheet.write_string(11, 1, env!("CARGO_PKG_VERSION"))?;
+
+        worksheet.write_datetime_with_format(
+            12,
+            1,
+            &ExcelDateTime::from_timestamp(chrono::Utc::now().timestamp())?,
+            &Format::new().set_num_format("yyyy-mm-dd;@"),
+        )?;
+
+        worksheet.write_datetime_with_format(
+            13,
+            1,
+            &ExcelDateTime::parse_from_str(&self.dump_date.to_string())?,
+            &Format::new().set_num_format("yyyy-mm-dd"),
+        )?;
+
+        if let Some(last_update) = self.last_update() {
+            worksheet.write_datetime_with_format(
+                14,
+                1,
+                &ExcelDateTime::from_timestamp(last_update.timestamp())?,
+                &Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"),
+            )?;
+        }
+
+        if let Some(sha256) = &self.sha256 {
+            worksheet.write_string(15, 1, sha256)?;
+        }
+
+        if self.incomplete {
+            let warning = Format::new().set_background_color(Color::Red);
+            worksheet.write_string_with_format(16, 0, "Incomplete Dump", &warning)?;
+            worksheet.write_string_with_format(16, 1, "Regions missing", &warning)?;
+        }
+
+        worksheet.write_string(18, 1, format!("{:?}", options.model))?;
+        worksheet.write_number(19, 1, options.timestamp_precision)?;
+        worksheet.write_string(20, 1, options.timezone.name())?;
+        if let Some(relative_to) = &options.relative_to {
+            worksheet.write_string(21, 1, relative_to)?;
+        }
+
+        worksheet.write_number(23, 1, region_count as f64)?;
+
+        // list the regions matching each color rule beside its color
+        for (index, (rule, count)) in options.color_rules.iter().zip(&color_counts).enumerate() {
+            let row = 24 + index as u32;
+            worksheet.write_string_with_format(
+                row,
+                0,
+                describe_rule(rule),
+                &Format::new().set_background_color(rule.color),
+            )?;
+            worksheet.write_number(row, 1, *count as f64)?;
+        }
+
+        let row = 24 + options.color_rules.len() as u32;
+        worksheet.write_string(row, 0, "Uncolored")?;
+        worksheet.write_number(row, 1, color_counts[options.color_rules.len()] as f64)?;
+
+        // set column widths to fit the labels, dates, and the last update time
+        worksheet.set_column_width(0, 24)?;
+        worksheet.set_column_width(1, 19)?;
+
+        let worksheet = workbook.add_worksheet().set_name("Minor Order")?;
+
+        write_region_table(worksheet, self.minor_rows(options)?, options)?;
+
+        if options.passworded_sheet {
+            let worksheet = workbook.add_worksheet().set_name("Passworded")?;
+
+            let passworded = self
+                .rows(options)?
+                .into_iter()
+                .filter(|row| row.has_password)
+                .collect();
+
+            write_region_table(worksheet, passworded, options)?;
+        }
+
+        if options.update_chart {
+            let worksheet = workbook.add_worksheet().set_name("Update Speed")?;
+            self.write_update_chart(worksheet, options)?;
+        }
+
+        if options.overflow_sheet {
+            let worksheet = workbook.add_worksheet().set_name("Overflow")?;
+            worksheet.write_row(0, 0, ["Region", "Column", "Overflow"])?;
+
+            for (row, (name, column, overflow)) in (1..).zip(&overflow) {
+                worksheet.write_string(row, 0, *name)?;
+                worksheet.write_string(row, 1, options.header(*column))?;
+
+                // text still too long for one cell continues in the next
+                let chars: Vec<char> = overflow.chars().collect();
+                for (col, part) in (2..).zip(chars.chunks(CELL_LIMIT)) {
+                    worksheet.write_string(row, col, part.iter().collect::<String>())?;
+                }
+            }
+
+            worksheet.set_column_width(0, 24)?;
+            worksheet.set_column_width(1, 18)?;
+        }
+
+        Ok(workbook)
+    }
+}
+
+/// Describe the regions a color rule matches, such as "No Governor, No
+/// Password".
+fn describe_rule(rule: &ColorRule) -> String {
+    if rule.conditions.is_empty() {
+        return "All Other Regions".to_string();
+    }
+
+    let conditions: Vec<&str> = rule
+        .conditions
+        .iter()
+        .map(|condition| match (condition.attribute, condition.negated) {
+            (Attribute::Governor, false) => "Governor",
+            (Attribute::Governor, true) => "No Governor",
+            (Attribute::Executive, false) => "Exec. Delegate",
+            (Attribute::Executive, true) => "Non-Exec. Delegate",
+            (Attribute::Password, false) => "Password",
+            (Attribute::Password, true) => "No Password",
+            (Attribute::Frontier, false) => "Frontier",
+            (Attribute::Frontier, true) => "Stronghold",
+            (Attribute::Occupied, false) => "Occupied",
+            (Attribute::Occupied, true) => "Not Occupied",
+        })
+        .collect();
+
+    conditions.join(", ")
+}
+
+/// Write the region table of a timesheet to a worksheet as an Excel table,
+/// with a header row and a row for each region.
+///
+/// Region names and links are colored with conditional formats built from the
+/// color rules, so that editing a region's attribute columns in Excel updates
+/// its color. Attribute columns that the rules need are added after the
+/// selected columns if they are not already included.
+fn write_region_table(
+    worksheet: &mut Worksheet,
+    rows: Vec<Row>,
+    options: &TimesheetOptions,
+) -> Result<()> {
+    let TimesheetOptions {
+        timestamp_precision,
+        spyglass_times,
+        ref color_rules,
+        styling,
+        ..
+    } = *options;
+
+    let mut columns = options.linked_columns();
+    for rule in color_rules {
+        for condition in &rule.conditions {
+            let column = condition.attribute.column();
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+    }
+
+    let no_delegate_fill = Format::new().set_background_color(styling.no_delegate);
+    let friendly_fill = Format::new().set_background_color(styling.friendly_embassies);
+    let enemy_fill = Format::new().set_background_color(styling.enemy_embassies);
+    let updated_format = Format::new().set_font_strikethrough();
+
+    let row_count = rows.len() as u32;
+
+    worksheet.write_row(0, 0, columns.iter().map(|&column| options.header(column)))?;
+
+    let duration_string = match timestamp_precision {
+        0 => "[h]:mm:ss",
+        1 => "[h]:mm:ss.0",
+        2 => "[h]:mm:ss.00",
+        3 => "[h]:mm:ss.000",
+        _ => unreachable!(),
+    };
+
+    let duration_format = Format::new().set_num_format(duration_string);
+
+    // relative times are in seconds, signed so leads and lags stand apart
+    let decimals = match timestamp_precision {
+        0 => String::new(),
+        precision => format!(".{}", "0".repeat(precision as usize)),
+    };
+    let relative_format =
+        Format::new().set_num_format(format!("+0{0};-0{0};0{0}", decimals).as_str());
+    let uncertainty_format = Format::new().set_num_format(format!("\"±\"0{}", decimals).as_str());
+
+    for (col, column) in (0..).zip(&columns) {
+        match column {
+            Column::Region => {
+                worksheet.set_column_width(col, 45)?;
+            }
+            Column::Progress => {
+                worksheet.set_column_format(col, &Format::new().set_num_format("0.00%"))?;
+            }
+            Column::Minor
+            | Column::Major
+            | Column::MinorTrigger
+            | Column::MajorTrigger
+            | Column::MinorObserved
+            | Column::MajorObserved => {
+                worksheet.set_column_format(col, &duration_format)?;
+                // set column width to fit timestamp
+                worksheet.set_column_width(col, 10)?;
+            }
+            Column::MinorTime | Column::MajorTime => {
+                worksheet.set_column_width(col, 18)?;
+            }
+            Column::Founded => {
+                worksheet
+                    .set_column_format(col, &Format::new().set_num_format("yyyy-mm-dd hh:mm:ss"))?;
+                worksheet.set_column_width(col, 18)?;
+            }
+            Column::MinorRelative
+            | Column::MajorRelative
+            | Column::MinorResidual
+            | Column::MajorResidual => {
+                worksheet.set_column_format(col, &relative_format)?;
+            }
+            Column::MinorUncertainty | Column::MajorUncertainty => {
+                worksheet.set_column_format(col, &uncertainty_format)?;
+            }
+            _ => (),
+        }
+    }
+
+    if options.freeze_header_row || options.freeze_first_column {
+        worksheet.set_freeze_panes(
+            options.freeze_header_row.into(),
+            options.freeze_first_column.into(),
+        )?;
+    }
+
+    for (row_index, row) in (1..).zip(rows) {
+        for (col, &column) in (0..).zip(&columns) {
+            match column {
+                Column::Region => {
+                    // regions that have already updated are struck through,
+                    // leaving their fill to the color rules
+                    if options.link_style == LinkStyle::Name {
+                        let url = Url::new(row.link.as_str()).set_text(row.name);
+                        if row.already_updated {
+                            worksheet.write_url_with_format(
+                                row_index,
+                                col,
+                                url,
+                                &updated_format,
+                            )?;
+                        } else {
+                            worksheet.write_url(row_index, col, url)?;
+                        }
+                    } else if row.already_updated {
+                        worksheet.write_string_with_format(
+                            row_index,
+                            col,
+                            row.name,
+                            &updated_format,
+                        )?;
+                    } else {
+                        worksheet.write_string(row_index, col, row.name)?;
+                    }
+                }
+                Column::Link => {
+                    worksheet.write_url(row_index, col, row.link.as_str())?;
+                }
+                Column::Population => {
+                    worksheet.write_number(row_index, col, row.population)?;
+                }
+                Column::WaMembers => {
+                    if let Some(wa_members) = row.wa_members {
+                        worksheet.write_number(row_index, col, wa_members)?;
+                    }
+                }
+                Column::TotalNations => {
+                    worksheet.write_number(row_index, col, row.nations_before)?;
+                }
+                Column::Progress => {
+                    worksheet.write_number(row_index, col, row.progress)?;
+                }
+                Column::Minor
+                | Column::Major
+                | Column::MinorTrigger
+                | Column::MajorTrigger
+                | Column::MinorObserved
+                | Column::MajorObserved => {
+                    let time = match column {
+                        Column::Minor => row.minor,
+                        Column::Major => row.major,
+                        Column::MinorTrigger => row.minor_trigger,
+                        Column::MajorTrigger => row.major_trigger,
+                        Column::MinorObserved => match row.minor_observed {
+                            Some(observed) => observed,
+                            None => continue,
+                        },
+                        _ => match row.major_observed {
+                            Some(observed) => observed,
+                            None => continue,
+                        },
+                    };
+
+                    if spyglass_times {
+                        worksheet.write_string(
+                            row_index,
+                            col,
+                            format_duration(time, timestamp_precision),
+                        )?;
+                    } else {
+                        let (h, m, s, ms) = split_duration(time);
+                        worksheet.write_datetime(
+                            row_index,
+                            col,
+                            &ExcelDateTime::from_hms_milli(h, m, s, ms)?,
+                        )?;
+                    }
+                }
+                Column::Missing => {
+                    worksheet.write_string(
+                        row_index,
+                        col,
+                        row.text(column, timestamp_precision),
+                    )?;
+                }
+                Column::MinorWindow => {
+                    worksheet.write_string(row_index, col, row.minor_window)?;
+                }
+                Column::MajorWindow => {
+                    worksheet.write_string(row_index, col, row.major_window)?;
+                }
+                Column::MinorTime => {
+                    worksheet.write_string(row_index, col, &row.minor_time)?;
+                }
+                Column::MajorTime => {
+                    worksheet.write_string(row_index, col, &row.major_time)?;
+                }
+                Column::MinorRelative
+                | Column::MajorRelative
+                | Column::MinorResidual
+                | Column::MajorResidual => {
+                    let relative = match column {
+                        Column::MinorRelative => row.minor_relative,
+                        Column::MajorRelative => row.major_relative,
+                        Column::MinorResidual => row.minor_residual(),
+                        _ => row.major_residual(),
+                    };
+
+                    if let Some(relative) = relative {
+                        worksheet.write_number(row_index, col, relative)?;
+                    }
+                }
+                Column::MinorUncertainty | Column::MajorUncertainty => {
+                    let uncertainty = if column == Column::MinorUncertainty {
+                        row.minor_uncertainty
+                    } else {
+                        row.major_uncertainty
+                    };
+
+                    if let Some(uncertainty) = uncertainty {
+                        worksheet.write_number(row_index, col, uncertainty)?;
+                    }
+                }
+                Column::Delegate | Column::Governor => {
+                    let nation = if column == Column::Delegate {
+                        row.delegate
+                    } else {
+                        row.governor
+                    };
+
+                    if let Some(nation) = nation {
+                        worksheet.write_url_with_text(
+                            row_index,
+                            col,
+                            format!(
+                                "https://www.nationstates.net/nation={}",
+                                canonical_name(nation)
+                            )
+                            .as_str(),
+                            nation,
+                        )?;
+                    }
+                }
+                Column::DelegateVotes => {
+                    worksheet.write_number(row_index, col, row.delegate_votes)?;
+                }
+                Column::DelegateEndorsements => {
+                    if row.delegate_votes == 0 {
+                        worksheet.write_number_with_format(
+                            row_index,
+                            col,
+                            row.delegate_endorsements,
+                            &no_delegate_fill,
+                        )?;
+                    } else {
+                        worksheet.write_number(row_index, col, row.delegate_endorsements)?;
+                    }
+                }
+                Column::EndorsementsNeeded => {
+                    worksheet.write_number(row_index, col, row.endorsements_needed)?;
+                }
+                Column::Updated => {
+                    worksheet.write_boolean(row_index, col, row.already_updated)?;
+                }
+                Column::HasGovernor => {
+                    worksheet.write_boolean(row_index, col, row.has_governor)?;
+                }
+                Column::Executive => {
+                    worksheet.write_boolean(row_index, col, row.delegate_exec)?;
+                }
+                Column::DelegateAppearance
+                | Column::DelegateBorderControl
+                | Column::DelegateCommunications
+                | Column::DelegateEmbassies
+                | Column::DelegatePolls => {
+                    let authority = row.delegate_authority;
+                    let held = match column {
+                        Column::DelegateAppearance => authority.appearance,
+                        Column::DelegateBorderControl => authority.border_control,
+                        Column::DelegateCommunications => authority.communications,
+                        Column::DelegateEmbassies => authority.embassies,
+                        _ => authority.polls,
+                    };
+
+                    worksheet.write_boolean(row_index, col, held)?;
+                }
+                Column::Password => {
+                    worksheet.write_boolean(row_index, col, row.has_password)?;
+                }
+                Column::Occupied => {
+                    worksheet.write_boolean(row_index, col, row.is_occupied)?;
+                }
+                Column::Type => {
+                    worksheet.write_string(row_index, col, region_type(row.is_frontier))?;
+                }
+                Column::New => {
+                    worksheet.write_boolean(row_index, col, row.is_new)?;
+                }
+                Column::Founded => {
+                    if let Some(founded) = row.founded {
+                        worksheet.write_datetime(
+                            row_index,
+                            col,
+                            &ExcelDateTime::from_timestamp(founded)?,
+                        )?;
+                    }
+                }
+                Column::EmbassyCount => {
+                    worksheet.write_number(row_index, col, row.embassies.len() as u32)?;
+                }
+                Column::Embassies => {
+                    let embassy_list = row.embassies.join(",");
+                    worksheet.write_string(row_index, col, truncate_cell(&embassy_list))?;
+                }
+                Column::HasFriendlyEmbassy => {
+                    worksheet.write_boolean(row_index, col, !row.friendly_embassies.is_empty())?;
+                }
+                Column::FriendlyEmbassies | Column::EnemyEmbassies => {
+                    let (embassies, fill) = if column == Column::FriendlyEmbassies {
+                        (&row.friendly_embassies, &friendly_fill)
+                    } else {
+                        (&row.enemy_embassies, &enemy_fill)
+                    };
+
+                    if embassies.is_empty() {
+                        continue;
+                    }
+
+                    let embassy_list = embassies.join(",");
+                    worksheet.write_string_with_format(
+                        row_index,
+                        col,
+                        truncate_cell(&embassy_list),
+                        fill,
+                    )?;
+                }
+                Column::Factbook => {
+                    worksheet.write_string(row_index, col, truncate_cell(row.factbook))?;
+                }
+                Column::TagSet(index) => {
+                    worksheet.write_boolean(row_index, col, row.in_tag_set(index))?;
+                }
+                Column::Flag | Column::Banner => {
+                    let url = if column == Column::Flag {
+                        row.flag
+                    } else {
+                        row.banner
+                    };
+
+                    if let Some(url) = url {
+                        worksheet.write_url(row_index, col, url)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if row_count > 0 && !columns.is_empty() {
+        // a table, which has an autofilter and banded rows by default, so the
+        // regions can be filtered and sorted from the header row
+        worksheet.add_table(0, 0, row_count, columns.len() as u16 - 1, &Table::new())?;
+
+        // the formulas refer to the first row of the range, and Excel adjusts
+        // them for each row after
+        let formulas: Vec<String> = color_rules
+            .iter()
+            .map(|rule| {
+                let checks: Vec<String> = rule
+                    .conditions
+                    .iter()
+                    .map(|condition| {
+                        let column = condition.attribute.column();
+                        let col = columns
+                            .iter()
+                            .position(|&other| other == column)
+                            .expect("rule attributes should have columns");
+                        let cell = format!("${}2", column_number_to_name(col as u16));
+
+                        match (condition.attribute, condition.negated) {
+                            (Attribute::Frontier, false) => format!("{}=\"Frontier\"", cell),
+                            (Attribute::Frontier, true) => format!("{}<>\"Frontier\"", cell),
+                            (_, false) => cell,
+                            (_, true) => format!("NOT({})", cell),
+                        }
+                    })
+                    .collect();
+
+                match checks.len() {
+                    0 => "=TRUE".to_string(),
+                    1 => format!("={}", checks[0]),
+                    _ => format!("=AND({})", checks.join(",")),
+                }
+            })
+            .collect();
+
+        for (col, column) in (0..).zip(&columns) {
+            if !matches!(column, Column::Region | Column::Link) {
+                continue;
+            }
+
+            for (rule, formula) in color_rules.iter().zip(&formulas) {
+                let conditional_format = ConditionalFormatFormula::new()
+                    .set_rule(formula.as_str())
+                    .set_format(Format::new().set_background_color(rule.color))
+                    .set_stop_if_true(true);
+
+                worksheet.add_conditional_format(1, col, row_count, col, &conditional_format)?;
+            }
+        }
+    }
+
+    Ok(())
+}
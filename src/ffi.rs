@@ -0,0 +1,254 @@
+//! A C interface to the parser, estimator, and Excel writer, enabled with the
+//! `ffi` feature, for embedding srsglass in tools that aren't written in Rust.
+//! Build it as a shared library with
+//! `cargo rustc --release --lib --features ffi --crate-type cdylib`, and see
+//! `include/srsglass.h` for the declarations.
+//!
+//! Functions that can fail return null or a negative number, after which
+//! [`srsglass_last_error`] describes the failure. Dumps and estimates are
+//! owned by the caller and must be freed with [`srsglass_free_dump`] and
+//! [`srsglass_free_estimates`].
+
+use crate::{Client, Dump, Result, SrsglassError, TimesheetOptions};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, recording its error or panic for [`srsglass_last_error`] and
+/// returning `failed` instead, so that neither crosses into C.
+fn guard<T>(failed: T, f: impl FnOnce() -> Result<T>) -> T {
+    let message = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => return value,
+        Ok(Err(error)) => error.to_string(),
+        Err(_) => "srsglass panicked".to_string(),
+    };
+
+    // messages never contain NUL bytes, but don't fail to report one that does
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+
+    failed
+}
+
+/// Read a C string argument, which must not be null.
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string.
+unsafe fn read_str<'a>(string: *const c_char, name: &str) -> Result<&'a str> {
+    if string.is_null() {
+        return Err(SrsglassError::InvalidOption(format!("{} is null", name)));
+    }
+
+    Ok(CStr::from_ptr(string).to_str()?)
+}
+
+/// Read a dump argument, which must not be null.
+///
+/// # Safety
+///
+/// `dump` must be null or a dump returned by this library and not yet freed.
+unsafe fn read_dump<'a>(dump: *const Dump) -> Result<&'a Dump> {
+    dump.as_ref()
+        .ok_or_else(|| SrsglassError::InvalidOption("dump is null".to_string()))
+}
+
+/// Get a description of the last failure on this thread, or null if nothing
+/// has failed. The string is owned by srsglass and valid until the next
+/// failure on the same thread.
+#[no_mangle]
+pub extern "C" fn srsglass_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Read a gzipped or uncompressed regions dump from a file without making any requests, as
+/// with [`Client::get_dump_from_file_offline`]. Returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_parse_dump(path: *const c_char) -> *mut Dump {
+    guard(ptr::null_mut(), || {
+        let path = read_str(path, "path")?;
+        let dump = Client::new("srsglass-ffi").get_dump_from_file_offline(path)?;
+        Ok(Box::into_raw(Box::new(dump)))
+    })
+}
+
+/// Read a gzipped or uncompressed regions dump from `length` bytes at `data`,
+/// as with [`Client::get_dump_from_reader_offline`]. Returns null on failure.
+///
+/// # Safety
+///
+/// `data` must be null or point to `length` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_parse_dump_buffer(data: *const u8, length: usize) -> *mut Dump {
+    guard(ptr::null_mut(), || {
+        if data.is_null() {
+            return Err(SrsglassError::InvalidOption("data is null".to_string()));
+        }
+
+        let data = slice::from_raw_parts(data, length);
+        let dump = Client::new("srsglass-ffi").get_dump_from_reader_offline(data)?;
+        Ok(Box::into_raw(Box::new(dump)))
+    })
+}
+
+/// Free a dump. Does nothing if `dump` is null.
+///
+/// # Safety
+///
+/// `dump` must be null or a dump returned by this library and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_free_dump(dump: *mut Dump) {
+    if !dump.is_null() {
+        drop(Box::from_raw(dump));
+    }
+}
+
+/// A region's estimated update times, in seconds from the start of each
+/// update.
+#[repr(C)]
+pub struct SrsglassEstimate {
+    /// The region's name, owned by the estimates it belongs to
+    pub name: *const c_char,
+    /// Number of nations updating before the region in major update
+    pub nations_before: i32,
+    /// Number of nations updating before the region in minor update
+    pub nations_before_minor: i32,
+    pub major: f64,
+    pub minor: f64,
+}
+
+/// Estimated update times of every region in a dump, in update order.
+pub struct SrsglassEstimates {
+    estimates: Vec<SrsglassEstimate>,
+    // keeps the names the estimates point to alive
+    _names: Vec<CString>,
+}
+
+/// Estimate when each region in a dump updates, as with
+/// [`Dump::estimate_times`]. Returns null on failure.
+///
+/// # Safety
+///
+/// `dump` must be null or a dump returned by this library and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_estimate(
+    dump: *const Dump,
+    major_length: i32,
+    minor_length: i32,
+) -> *mut SrsglassEstimates {
+    guard(ptr::null_mut(), || {
+        let dump = read_dump(dump)?;
+
+        let mut names = Vec::new();
+        let mut estimates = Vec::new();
+
+        for estimate in dump.estimate_times(major_length, minor_length)? {
+            let name = CString::new(estimate.name).map_err(|_| {
+                SrsglassError::InvalidDump(format!("Invalid region name {}", estimate.name))
+            })?;
+
+            estimates.push(SrsglassEstimate {
+                name: name.as_ptr(),
+                nations_before: estimate.nations_before,
+                nations_before_minor: estimate.nations_before_minor,
+                major: estimate.major,
+                minor: estimate.minor,
+            });
+            names.push(name);
+        }
+
+        Ok(Box::into_raw(Box::new(SrsglassEstimates {
+            estimates,
+            _names: names,
+        })))
+    })
+}
+
+/// Get the number of regions estimated, or 0 if `estimates` is null.
+///
+/// # Safety
+///
+/// `estimates` must be null or estimates returned by this library and not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_estimates_len(estimates: *const SrsglassEstimates) -> usize {
+    estimates
+        .as_ref()
+        .map_or(0, |estimates| estimates.estimates.len())
+}
+
+/// Get the estimate at `index`, or null if `estimates` is null or `index` is
+/// out of range. The estimate is valid until the estimates are freed.
+///
+/// # Safety
+///
+/// `estimates` must be null or estimates returned by this library and not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_estimates_get(
+    estimates: *const SrsglassEstimates,
+    index: usize,
+) -> *const SrsglassEstimate {
+    estimates
+        .as_ref()
+        .and_then(|estimates| estimates.estimates.get(index))
+        .map_or(ptr::null(), |estimate| estimate as *const _)
+}
+
+/// Free estimates. Does nothing if `estimates` is null.
+///
+/// # Safety
+///
+/// `estimates` must be null or estimates returned by this library and not yet
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_free_estimates(estimates: *mut SrsglassEstimates) {
+    if !estimates.is_null() {
+        drop(Box::from_raw(estimates));
+    }
+}
+
+/// Write a dump to an Excel timesheet at `path` with the default options and
+/// the given update lengths. Returns 0 on success and -1 on failure.
+///
+/// # Safety
+///
+/// `dump` must be null or a dump returned by this library and not yet freed,
+/// and `path` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn srsglass_write_xlsx(
+    dump: *const Dump,
+    path: *const c_char,
+    major_length: i32,
+    minor_length: i32,
+) -> c_int {
+    guard(-1, || {
+        let dump = read_dump(dump)?;
+        let path = read_str(path, "path")?;
+
+        let options = TimesheetOptions {
+            major_length,
+            minor_length,
+            ..TimesheetOptions::default()
+        };
+        dump.to_excel(path, &options)?;
+
+        Ok(0)
+    })
+}
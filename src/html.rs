@@ -0,0 +1,261 @@
+//! Standalone HTML timesheets, sortable and filterable in the browser.
+
+use crate::{canonical_name, Column, Dump, LinkStyle, Result, TimesheetOptions};
+use rust_xlsxwriter::Color;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Styles for HTML timesheets
+const HTML_STYLE: &str = "body{font-family:sans-serif;margin:1em}\
+table{border-collapse:collapse;font-size:0.9em}\
+th,td{border:1px solid #ccc;padding:2px 6px;white-space:nowrap}\
+td.wrap{white-space:normal;min-width:20em}\
+th{position:sticky;top:0;background:#eee;cursor:pointer}\
+td img{height:2em;vertical-align:middle}\
+tr.updated{color:#888;text-decoration:line-through}\
+#filter{margin-bottom:0.5em;padding:4px;width:20em;max-width:100%}";
+
+/// Script for sorting and filtering HTML timesheets. Cells are sorted by
+/// their `data-sort` attribute if they have one, and otherwise by their text,
+/// numerically if possible.
+const HTML_SCRIPT: &str = r#"
+const table = document.querySelector("table");
+const body = table.tBodies[0];
+const key = (row, column) => {
+  const cell = row.cells[column];
+  const value = cell.dataset.sort ?? cell.textContent;
+  const number = Number(value);
+  return value !== "" && !Number.isNaN(number) ? number : value.toLowerCase();
+};
+table.querySelectorAll("th").forEach((header, column) => {
+  header.addEventListener("click", () => {
+    const ascending = header.dataset.order !== "asc";
+    table.querySelectorAll("th").forEach((other) => delete other.dataset.order);
+    header.dataset.order = ascending ? "asc" : "desc";
+    const rows = Array.from(body.rows);
+    rows.sort((a, b) => {
+      const [x, y] = [key(a, column), key(b, column)];
+      return (x < y ? -1 : x > y ? 1 : 0) * (ascending ? 1 : -1);
+    });
+    body.append(...rows);
+  });
+});
+document.getElementById("filter").addEventListener("input", (event) => {
+  const filter = event.target.value.toLowerCase();
+  for (const row of body.rows) {
+    row.hidden = !row.dataset.region.toLowerCase().includes(filter);
+  }
+});
+"#;
+
+/// Get the CSS color for a cell fill.
+pub(crate) fn css_color(color: Color) -> String {
+    let rgb = match color {
+        Color::RGB(rgb) => rgb,
+        Color::Black => 0x000000,
+        Color::Blue => 0x0000FF,
+        Color::Brown => 0x800000,
+        Color::Cyan => 0x00FFFF,
+        Color::Gray => 0x808080,
+        Color::Green => 0x008000,
+        Color::Lime => 0x00FF00,
+        Color::Magenta => 0xFF00FF,
+        Color::Navy => 0x000080,
+        Color::Orange => 0xFF6600,
+        Color::Pink => 0xFFC0CB,
+        Color::Purple => 0x800080,
+        Color::Red => 0xFF0000,
+        Color::Silver => 0xC0C0C0,
+        Color::White => 0xFFFFFF,
+        Color::Yellow => 0xFFFF00,
+        Color::Theme(..) | Color::Default | Color::Automatic => return "inherit".to_string(),
+    };
+
+    format!("#{:06x}", rgb)
+}
+
+/// Escape text for use in HTML.
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Dump {
+    /// Write the timesheet to a standalone HTML file, with a table that can be
+    /// sorted by clicking a column header and filtered by region name. Rows
+    /// are colored the same way as in Excel timesheets.
+    pub fn to_html(&self, output_file: impl AsRef<Path>, options: &TimesheetOptions) -> Result<()> {
+        options.check_precision()?;
+
+        let mut html = String::new();
+
+        html.push_str(&format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>srsglass {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+            self.dump_date, HTML_STYLE
+        ));
+
+        html.push_str(&format!(
+            "<p>Dump date {}{} &middot; {} nations &middot; major {}s &middot; minor {}s &middot; srsglass {}</p>\n",
+            self.dump_date,
+            self.last_update()
+                .map(|time| format!(" (last update {})", time.format("%Y-%m-%d %H:%M:%S UTC")))
+                .unwrap_or_default(),
+            self.total_population()?,
+            options.major_length,
+            options.minor_length,
+            env!("CARGO_PKG_VERSION")
+        ));
+        if self.incomplete {
+            html.push_str(
+                "<p><strong>Incomplete dump:</strong> regions after the last one listed are missing</p>\n",
+            );
+        }
+        html.push_str("<input id=\"filter\" type=\"search\" placeholder=\"Filter regions\">\n");
+
+        let columns = options.linked_columns();
+
+        html.push_str("<table>\n<thead><tr>");
+        for &column in &columns {
+            html.push_str(&format!("<th>{}</th>", escape_html(options.header(column))));
+        }
+        html.push_str("</tr></thead>\n<tbody>\n");
+
+        let cell = |value: &str, sort: Option<String>, color: Option<Color>| {
+            let mut attributes = String::new();
+            if let Some(sort) = sort {
+                attributes.push_str(&format!(" data-sort=\"{}\"", sort));
+            }
+            if let Some(color) = color {
+                attributes.push_str(&format!(" style=\"background:{}\"", css_color(color)));
+            }
+            format!("<td{}>{}</td>", attributes, escape_html(value))
+        };
+        // embassy lists and factbooks wrap rather than widening the table
+        let wrapped_cell = |value: &str, color: Option<Color>| {
+            let style = color
+                .map(|color| format!(" style=\"background:{}\"", css_color(color)))
+                .unwrap_or_default();
+            format!("<td class=\"wrap\"{}>{}</td>", style, escape_html(value))
+        };
+        let nation_cell = |nation: Option<&str>| match nation {
+            Some(nation) => format!(
+                "<td><a href=\"https://www.nationstates.net/nation={}\">{}</a></td>",
+                escape_html(&canonical_name(nation)),
+                escape_html(nation)
+            ),
+            None => "<td></td>".to_string(),
+        };
+        // flags and banners are shown as thumbnails linking to the full image
+        let image_cell = |url: Option<&str>| match url {
+            Some(url) => format!(
+                "<td><a href=\"{}\"><img src=\"{}\" alt=\"\" loading=\"lazy\"></a></td>",
+                escape_html(url),
+                escape_html(url)
+            ),
+            None => "<td></td>".to_string(),
+        };
+
+        for row in self.rows(options)? {
+            let color = options
+                .color_rules
+                .iter()
+                .find(|rule| rule.matches(&row))
+                .map(|rule| rule.color);
+
+            // regions that have already updated are grayed out and struck
+            // through
+            let class = if row.already_updated {
+                " class=\"updated\""
+            } else {
+                ""
+            };
+
+            // the filter matches on the region name wherever its column is
+            html.push_str(&format!(
+                "<tr{} data-region=\"{}\">",
+                class,
+                escape_html(row.name)
+            ));
+
+            for &column in &columns {
+                html.push_str(&match column {
+                    Column::Region if options.link_style == LinkStyle::Name => format!(
+                        "<td{}><a href=\"{}\">{}</a></td>",
+                        color
+                            .map(|color| format!(" style=\"background:{}\"", css_color(color)))
+                            .unwrap_or_default(),
+                        escape_html(&row.link),
+                        escape_html(row.name)
+                    ),
+                    Column::Region => cell(row.name, None, color),
+                    Column::Link => format!(
+                        "<td{}><a href=\"{}\">{}</a></td>",
+                        color
+                            .map(|color| format!(" style=\"background:{}\"", css_color(color)))
+                            .unwrap_or_default(),
+                        escape_html(&row.link),
+                        escape_html(&row.link)
+                    ),
+                    Column::Progress => cell(
+                        &row.text(column, options.timestamp_precision),
+                        Some(row.progress.to_string()),
+                        None,
+                    ),
+                    Column::Minor => cell(
+                        &row.text(column, options.timestamp_precision),
+                        Some(row.minor.to_string()),
+                        None,
+                    ),
+                    Column::Major => cell(
+                        &row.text(column, options.timestamp_precision),
+                        Some(row.major.to_string()),
+                        None,
+                    ),
+                    Column::Delegate => nation_cell(row.delegate),
+                    Column::DelegateEndorsements => cell(
+                        &row.delegate_endorsements.to_string(),
+                        None,
+                        (row.delegate_votes == 0).then_some(options.styling.no_delegate),
+                    ),
+                    Column::Governor => nation_cell(row.governor),
+                    Column::Flag => image_cell(row.flag),
+                    Column::Banner => image_cell(row.banner),
+                    Column::Embassies => wrapped_cell(&row.embassies.join(", "), None),
+                    Column::FriendlyEmbassies => wrapped_cell(
+                        &row.friendly_embassies.join(", "),
+                        (!row.friendly_embassies.is_empty())
+                            .then_some(options.styling.friendly_embassies),
+                    ),
+                    Column::EnemyEmbassies => wrapped_cell(
+                        &row.enemy_embassies.join(", "),
+                        (!row.enemy_embassies.is_empty())
+                            .then_some(options.styling.enemy_embassies),
+                    ),
+                    Column::Factbook => wrapped_cell(row.factbook, None),
+                    _ => cell(&row.text(column, options.timestamp_precision), None, None),
+                });
+            }
+
+            html.push_str("</tr>\n");
+        }
+
+        html.push_str("</tbody>\n</table>\n");
+        html.push_str(&format!(
+            "<script>{}</script>\n</body>\n</html>\n",
+            HTML_SCRIPT
+        ));
+
+        let mut file = BufWriter::new(File::create(output_file)?);
+        file.write_all(html.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
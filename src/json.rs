@@ -0,0 +1,118 @@
+//! JSON timesheets.
+
+use crate::estimate::{self, Update};
+use crate::{Dump, Result, TimesheetOptions};
+use std::{fs::File, io::BufWriter, path::Path};
+
+#[derive(serde::Serialize)]
+struct JsonTimesheet<'a> {
+    world: JsonWorld,
+    regions: Vec<JsonRegion<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct JsonWorld {
+    nations: i32,
+    major_length: i32,
+    major_secs_per_nation: f64,
+    major_nations_per_sec: f64,
+    minor_length: i32,
+    minor_secs_per_nation: f64,
+    minor_nations_per_sec: f64,
+    observed_major_length: Option<i64>,
+    observed_minor_length: Option<i64>,
+    version: &'static str,
+    date_generated: String,
+    dump_date: String,
+    last_update: Option<String>,
+    incomplete: bool,
+    dump_sha256: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct JsonRegion<'a> {
+    name: &'a str,
+    link: String,
+    population: i32,
+    nations_before: i32,
+    progress: f64,
+    minor: f64,
+    major: f64,
+    delegate: Option<&'a str>,
+    delegate_votes: i32,
+    delegate_endorsements: i32,
+    delegate_exec: bool,
+    governorless: bool,
+    governor: Option<&'a str>,
+    passwordless: bool,
+    frontier: bool,
+    new: bool,
+    embassies: Vec<&'a str>,
+    friendly_embassies: Vec<&'a str>,
+    enemy_embassies: Vec<&'a str>,
+    factbook: &'a str,
+}
+
+impl Dump {
+    /// Write the timesheet to a JSON file. Update times are given as seconds
+    /// from the start of the update.
+    pub fn to_json(&self, output_file: impl AsRef<Path>, options: &TimesheetOptions) -> Result<()> {
+        let total_population = self.total_population()?;
+
+        let TimesheetOptions {
+            major_length,
+            minor_length,
+            ..
+        } = *options;
+
+        let world = JsonWorld {
+            nations: total_population,
+            major_length,
+            major_secs_per_nation: major_length as f64 / total_population as f64,
+            major_nations_per_sec: total_population as f64 / major_length as f64,
+            minor_length,
+            minor_secs_per_nation: minor_length as f64 / total_population as f64,
+            minor_nations_per_sec: total_population as f64 / minor_length as f64,
+            observed_major_length: estimate::observed_length(self, Update::Major),
+            observed_minor_length: estimate::observed_length(self, Update::Minor),
+            version: env!("CARGO_PKG_VERSION"),
+            date_generated: chrono::Utc::now().date_naive().to_string(),
+            dump_date: self.dump_date.to_string(),
+            last_update: self.last_update().map(|time| time.to_rfc3339()),
+            incomplete: self.incomplete,
+            dump_sha256: self.sha256.clone(),
+        };
+
+        let regions = self
+            .rows(options)?
+            .into_iter()
+            .map(|row| JsonRegion {
+                name: row.name,
+                link: row.link,
+                population: row.population,
+                nations_before: row.nations_before,
+                progress: row.progress,
+                minor: row.minor,
+                major: row.major,
+                delegate: row.delegate,
+                delegate_votes: row.delegate_votes,
+                delegate_endorsements: row.delegate_endorsements,
+                delegate_exec: row.delegate_exec,
+                governorless: !row.has_governor,
+                governor: row.governor,
+                passwordless: !row.has_password,
+                frontier: row.is_frontier,
+                new: row.is_new,
+                embassies: row.embassies,
+                friendly_embassies: row.friendly_embassies,
+                enemy_embassies: row.enemy_embassies,
+                factbook: row.factbook,
+            })
+            .collect();
+
+        let file = BufWriter::new(File::create(output_file)?);
+        serde_json::to_writer(file, &JsonTimesheet { world, regions })?;
+
+        Ok(())
+    }
+}
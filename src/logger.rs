@@ -0,0 +1,57 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Prints log messages for the command-line interface: progress messages to
+/// stdout, and warnings, errors, and debug detail to stderr.
+struct Logger {
+    level: LevelFilter,
+}
+
+impl Logger {
+    /// Whether a message comes from srsglass itself rather than one of its
+    /// dependencies.
+    fn is_own(metadata: &Metadata) -> bool {
+        metadata.target().starts_with("srsglass")
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // messages from dependencies are only useful when tracing
+        metadata.level() <= self.level
+            && (Self::is_own(metadata) || self.level == LevelFilter::Trace)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        match record.level() {
+            Level::Info => println!("{}", record.args()),
+            Level::Warn => eprintln!("Warning: {}", record.args()),
+            Level::Error => eprintln!("Error: {}", record.args()),
+            Level::Debug | Level::Trace if Self::is_own(record.metadata()) => {
+                eprintln!("{}", record.args())
+            }
+            Level::Debug | Level::Trace => eprintln!("[{}] {}", record.target(), record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the logger, showing messages up to a level set by the number of
+/// `--verbose` and `--quiet` flags passed.
+pub fn init(verbose: u8, quiet: u8) {
+    let level = match i16::from(verbose) - i16::from(quiet) {
+        ..=-2 => LevelFilter::Error,
+        -1 => LevelFilter::Warn,
+        0 => LevelFilter::Info,
+        1 => LevelFilter::Debug,
+        2.. => LevelFilter::Trace,
+    };
+
+    if log::set_boxed_logger(Box::new(Logger { level })).is_ok() {
+        log::set_max_level(level);
+    }
+}
@@ -1,81 +1,1204 @@
-use anyhow::Result;
-use clap::Parser;
-use srsglass::Client;
-use std::path::Path;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use config::Config;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{debug, error, info, warn};
+#[cfg(feature = "serde")]
+use manifest::Manifest;
+use serde::{Deserialize, Serialize};
+use srsglass::{
+    canonical_name, diff,
+    estimate::{self, Model, Update},
+    expected_dump_date, triggers, Client, ColorRule, Column, Dump, LinkStyle, NationCountWarning,
+    Progress, TagSet, TimesheetOptions,
+};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+mod config;
+mod countdown;
+mod logger;
+#[cfg(feature = "serde")]
+mod manifest;
+
+/// Output file format for the timesheet
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Xlsx,
+    Csv,
+    Html,
+    Ods,
+    Markdown,
+    Bbcode,
+    #[cfg(feature = "serde")]
+    Json,
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Xlsx => "xlsx",
+            Format::Csv => "csv",
+            Format::Html => "html",
+            Format::Ods => "ods",
+            Format::Markdown => "md",
+            Format::Bbcode => "txt",
+            #[cfg(feature = "serde")]
+            Format::Json => "json",
+            #[cfg(feature = "sqlite")]
+            Format::Sqlite => "sqlite",
+            #[cfg(feature = "parquet")]
+            Format::Parquet => "parquet",
+        }
+    }
+}
+
+/// Model used to estimate update times, as read from the command line and
+/// config files. See [`Model`].
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+enum ModelArg {
+    /// Assume nations update at a constant rate, as Spyglass does.
+    #[default]
+    Linear,
+    /// Fit a cost per nation and a cost per region to the update timestamps
+    /// observed in the dump, so that runs of small regions, which take longer
+    /// per nation to update, are accounted for.
+    Nonlinear,
+}
+
+impl From<ModelArg> for Model {
+    fn from(model: ModelArg) -> Model {
+        match model {
+            ModelArg::Linear => Model::Linear,
+            ModelArg::Nonlinear => Model::Nonlinear,
+        }
+    }
+}
+
+impl From<Model> for ModelArg {
+    fn from(model: Model) -> ModelArg {
+        match model {
+            Model::Linear => ModelArg::Linear,
+            Model::Nonlinear => ModelArg::Nonlinear,
+        }
+    }
+}
+
+/// An update, as read from the command line. See [`Update`].
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum UpdateArg {
+    Major,
+    Minor,
+}
+
+impl From<UpdateArg> for Update {
+    fn from(update: UpdateArg) -> Update {
+        match update {
+            UpdateArg::Major => Update::Major,
+            UpdateArg::Minor => Update::Minor,
+        }
+    }
+}
+
+/// How timesheets link to each region's page, as read from the command line
+/// and config files. See [`LinkStyle`].
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+enum LinkStyleArg {
+    /// Link to each region in a separate Link column.
+    #[default]
+    Column,
+    /// Link each region's name instead of adding a Link column, in HTML, ODS,
+    /// and Excel timesheets. CSV timesheets, which can't hold links, keep the
+    /// column.
+    Name,
+    /// Link to each region in a separate Link column, with the site's
+    /// styling turned off so that pages load faster.
+    Template,
+}
+
+impl From<LinkStyleArg> for LinkStyle {
+    fn from(link_style: LinkStyleArg) -> LinkStyle {
+        match link_style {
+            LinkStyleArg::Column => LinkStyle::Column,
+            LinkStyleArg::Name => LinkStyle::Name,
+            LinkStyleArg::Template => LinkStyle::Template,
+        }
+    }
+}
 
 /// A command-line utility for generating NationStates region update timesheets
-#[derive(Parser, Debug)]
+#[derive(Parser, Clone, Debug)]
 #[command(author, version, about)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The name of your nation, to identify you to NationStates
-    #[arg(short = 'n', long = "nation")]
-    user_nation: String,
+    #[arg(short = 'n', long = "nation", global = true)]
+    user_nation: Option<String>,
+
+    /// Path to the config file [default: srsglass.toml in the working
+    /// directory, or in the user config directory]
+    #[arg(short, long, global = true)]
+    config: Option<String>,
 
     /// Name of the output file [default: srsglassYYYY-MM-DD.xlsx]
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     outfile: Option<String>,
 
-    /// Length of major update, in seconds
-    #[arg(long = "major", default_value_t = 5350)]
-    major_length: i32,
+    /// Print more detail, including how long each step takes; pass twice to
+    /// include messages from dependencies
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Print only warnings and errors; pass twice to print only errors
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    quiet: u8,
+
+    /// Template for the name of the output file, with {dump_date},
+    /// {generated}, {major}, and {minor} replaced by the dump date, today's
+    /// date, and the major and minor update lengths
+    #[arg(long = "name-template", conflicts_with = "outfile")]
+    name_template: Option<String>,
 
-    /// Length of minor update, in seconds
-    #[arg(long = "minor", default_value_t = 3550)]
-    minor_length: i32,
+    /// Output file formats, separated by commas [default: xlsx]
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    format: Vec<Format>,
+
+    /// Length of major update, in seconds [default: 5350]
+    #[arg(long = "major")]
+    major_length: Option<i32>,
+
+    /// Length of minor update, in seconds [default: 3550]
+    #[arg(long = "minor")]
+    minor_length: Option<i32>,
+
+    /// Use the update lengths observed in the dump instead of --major and --minor
+    #[arg(long = "auto-length", default_value_t = false)]
+    auto_length: bool,
 
     /// Use the current data dump instead of downloading
     #[arg(short = 'd', long = "dump", default_value_t = false)]
     use_dump: bool,
 
-    /// Path to the data dump
-    #[arg(short = 'p', long = "path", default_value = "regions.xml.gz")]
-    dump_path: String,
+    /// Use the current data dump without making any API requests
+    #[arg(long = "offline", default_value_t = false, requires = "use_dump")]
+    offline: bool,
+
+    /// Path to the data dump, either gzipped or already decompressed, or - to read it from standard
+    /// input [default: regions.xml.gz in the data directory]
+    #[arg(short = 'p', long = "path")]
+    dump_path: Option<String>,
+
+    /// Directory to save data dumps in and read them from [default:
+    /// ~/.local/share/srsglass]
+    #[arg(long = "data-dir", conflicts_with = "dump_path")]
+    data_dir: Option<String>,
+
+    /// Use the existing data dump even if a newer one is available
+    #[arg(long = "force-stale", default_value_t = false, requires = "use_dump")]
+    force_stale: bool,
+
+    /// Save the downloaded data dump to the dump path for reuse with --dump
+    #[arg(long = "save-dump", default_value_t = false)]
+    save_dump: bool,
+
+    /// Check that the data dump has this SHA-256, as recorded in the summary
+    /// or manifest of a timesheet made from it, and stop if it does not
+    #[arg(long = "sha256")]
+    sha256: Option<String>,
+
+    /// Write a JSON manifest next to the timesheet recording the dump, settings, and srsglass
+    /// version used, for checking that everyone has the same sheet
+    #[cfg(feature = "serde")]
+    #[arg(long = "manifest", default_value_t = false)]
+    manifest: bool,
+
+    /// Download the archived data dump from this date (YYYY-MM-DD)
+    #[arg(long = "date")]
+    date: Option<NaiveDate>,
+
+    /// Number of times to retry failed requests and downloads [default: 3]
+    #[arg(long = "retries")]
+    retries: Option<u32>,
+
+    /// Number of times to download the data dump again while NS is still serving an out-of-date
+    /// one, as it sometimes does just after major update
+    #[arg(long = "stale-retries", default_value_t = 0)]
+    stale_retries: u32,
+
+    /// Minutes to wait before downloading an out-of-date data dump again
+    #[arg(long = "stale-retry-interval", default_value_t = 5)]
+    stale_retry_interval: u64,
+
+    /// Directory to cache API responses listing governorless, passwordless,
+    /// frontier, and tagged regions in [default: ~/.cache/srsglass]
+    #[arg(long = "cache-dir")]
+    cache_dir: Option<String>,
+
+    /// Minutes to reuse cached API responses for
+    #[arg(long = "cache-ttl", default_value_t = 60)]
+    cache_ttl: u64,
+
+    /// Request regions by tag from the API every run instead of caching them
+    #[arg(
+        long = "no-cache",
+        default_value_t = false,
+        conflicts_with = "cache_dir"
+    )]
+    no_cache: bool,
+
+    /// Send requests through this proxy, instead of any proxy set in the
+    /// HTTPS_PROXY environment variable
+    #[arg(long = "proxy")]
+    proxy: Option<String>,
+
+    /// Skip reading factbooks from the dump, leaving the WFE column empty
+    #[arg(long = "no-factbook", default_value_t = false)]
+    no_factbook: bool,
+
+    /// Skip reading embassies from the dump, leaving the embassy columns empty
+    #[arg(long = "no-embassies", default_value_t = false)]
+    no_embassies: bool,
+
+    /// Keep the regions read from a truncated or corrupt data dump instead of failing, marking the
+    /// timesheet as incomplete
+    #[arg(long = "allow-partial", default_value_t = false)]
+    allow_partial: bool,
+
+    /// Keep regions without any nations in the timesheet and estimates, rather
+    /// than leaving them out as NS does not update them
+    #[arg(long = "include-empty", default_value_t = false)]
+    include_empty: bool,
+
+    /// Download the nations data dump to count delegate endorsements and WA
+    /// members, adding columns with the WA members in each region and the
+    /// endorsements needed to beat each delegate
+    #[arg(long = "nations", default_value_t = false)]
+    use_nations: bool,
+
+    /// Add regions founded since the data dump, from NS's happenings, to the
+    /// end of the update order
+    #[arg(
+        long = "new-regions",
+        default_value_t = false,
+        conflicts_with = "offline"
+    )]
+    new_regions: bool,
+
+    /// The number of milliseconds to use in timestamps [default: 0]
+    #[arg(long = "precision")]
+    precision: Option<i32>,
+
+    /// Model used to estimate update times [default: linear]
+    #[arg(long = "model", value_enum)]
+    model: Option<ModelArg>,
+
+    /// Observed update time of a region early in tonight's update, as REGION=SECONDS or
+    /// REGION=H:MM:SS from the start of the update, to rescale later estimates to the update's
+    /// actual speed; may be repeated
+    #[arg(long = "anchor")]
+    anchors: Vec<estimate::Anchor>,
+
+    /// The update in progress, which the anchors and --started were observed in
+    #[arg(long = "anchor-update", value_enum, default_value_t = UpdateArg::Major)]
+    anchor_update: UpdateArg,
+
+    /// Wall-clock time tonight's update was seen to start, as H:MM:SS in the --timezone, to mark
+    /// regions that have presumably updated already
+    #[arg(long = "started")]
+    started: Option<NaiveTime>,
+
+    /// Wall-clock time to mark regions as updated by, as H:MM:SS in the --timezone [default: the
+    /// current time]
+    #[arg(long = "now", requires = "started")]
+    now: Option<NaiveTime>,
+
+    /// Time zone of the minor-time and major-time columns, such as
+    /// Europe/London [default: US/Eastern]
+    #[arg(long = "timezone")]
+    timezone: Option<String>,
+
+    /// Write update times as H:MM:SS strings, as Spyglass does
+    #[arg(long = "spyglass-times", default_value_t = false)]
+    spyglass_times: bool,
+
+    /// Add a worksheet listing only passworded regions to Excel timesheets
+    #[arg(long = "passworded-sheet", default_value_t = false)]
+    passworded_sheet: bool,
+
+    /// Add a worksheet to Excel timesheets charting the speed of the last major and minor updates
+    #[arg(long = "update-chart", default_value_t = false)]
+    update_chart: bool,
+
+    /// Add a worksheet to Excel timesheets with the rest of any factbook or embassy list too long
+    /// for a cell
+    #[arg(long = "overflow-sheet", default_value_t = false)]
+    overflow_sheet: bool,
+
+    /// Rule for coloring regions, as COLOR=CONDITIONS (e.g. lime=-governor,-password);
+    /// conditions are governor, exec, password, frontier, and occupied; may be
+    /// repeated, and replaces the default rules
+    #[arg(long = "color-rule")]
+    color_rules: Vec<ColorRule>,
+
+    /// Columns to include in CSV, HTML, ODS, and Excel timesheets, in order, separated by
+    /// commas, by the keys and defaults listed for `Column` in the library docs
+    #[arg(long = "columns", value_delimiter = ',')]
+    columns: Vec<Column>,
+
+    /// How to link to each region's page [default: column]
+    #[arg(long = "link-style", value_enum)]
+    link_style: Option<LinkStyleArg>,
+
+    /// Only include this region in the timesheet; may be repeated
+    #[arg(long = "region")]
+    regions: Vec<String>,
+
+    /// File listing regions to include in the timesheet, one per line
+    #[arg(long = "regions")]
+    regions_path: Option<String>,
+
+    /// Only include regions with this tag, or without it if prefixed with -;
+    /// may be repeated
+    #[arg(long = "tag", allow_hyphen_values = true)]
+    tags: Vec<String>,
+
+    /// Number of regions before and after each included region to include as well
+    #[arg(long = "context", default_value_t = 0)]
+    context: usize,
+
+    /// File listing friendly regions, such as jump points, one per line; embassies
+    /// with them are highlighted
+    #[arg(long = "friendly")]
+    friendly_path: Option<String>,
+
+    /// File listing enemy regions, such as raider headquarters, one per line;
+    /// embassies with them are highlighted
+    #[arg(long = "enemy")]
+    enemy_path: Option<String>,
+
+    /// File listing target regions to find triggers for, one per line
+    #[arg(long = "targets")]
+    targets_path: Option<String>,
+
+    /// Minimum number of seconds between a trigger and its target updating
+    #[arg(long = "lead", default_value_t = 6.0)]
+    lead_time: f64,
+
+    /// Update to find triggers for
+    #[arg(long = "trigger-update", value_enum, default_value_t = UpdateArg::Major)]
+    trigger_update: UpdateArg,
+
+    /// Add columns with the seconds between this region updating and each
+    /// region updating, such as to find triggers for a target
+    #[arg(long = "relative-to")]
+    relative_to: Option<String>,
+
+    /// Add columns with the time to be in position for each region, this many seconds before its
+    /// estimated update time
+    #[arg(long = "trigger-offset")]
+    trigger_offset: Option<f64>,
+
+    /// Add columns bucketing regions into early, middle, and late updaters, with this many
+    /// seconds from the start of each update counting as early [default: 600]
+    #[arg(long = "early-window")]
+    early_window: Option<f64>,
+
+    /// Add columns bucketing regions into early, middle, and late updaters, with this many
+    /// seconds before the end of each update counting as late [default: 600]
+    #[arg(long = "late-window")]
+    late_window: Option<f64>,
+
+    /// Keep running, generating a new timesheet named after each new data dump
+    /// once NS publishes it
+    #[arg(
+        long = "watch",
+        default_value_t = false,
+        conflicts_with_all = ["outfile", "date", "use_dump"]
+    )]
+    watch: bool,
+
+    /// Minutes to wait between checks for a new data dump in watch mode
+    #[arg(long = "watch-interval", default_value_t = 10, requires = "watch")]
+    watch_interval: u64,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+enum Command {
+    /// Compare two dumps and list the changes to each region between them
+    Diff {
+        /// The older dump, as a path to a dump file or a date (YYYY-MM-DD) to
+        /// download the archived dump from
+        old: String,
+
+        /// The newer dump, as a path to a dump file or a date (YYYY-MM-DD) to
+        /// download the archived dump from
+        new: String,
+    },
+
+    /// Print one region's estimated update times, position in the update,
+    /// and whether it can be targeted, without writing a timesheet
+    Query {
+        /// The region to look up
+        region: String,
+    },
+
+    /// Count down to a region's estimated update, alerting as it draws near
+    Countdown {
+        /// The region to count down to
+        #[arg(long = "region")]
+        region: String,
+
+        /// Wall-clock time the update starts, as H:MM:SS in the --timezone
+        #[arg(long = "start")]
+        start: NaiveTime,
+
+        /// Update to count down to
+        #[arg(long = "update", value_enum, default_value_t = UpdateArg::Major)]
+        update: UpdateArg,
+
+        /// Seconds before the region updates to alert at, separated by commas
+        #[arg(long = "alert-at", value_delimiter = ',', default_values_t = [300, 60, 30, 10])]
+        alerts: Vec<u64>,
+
+        /// Send a desktop notification at each alert as well as ringing the
+        /// terminal bell
+        #[arg(long = "notify", default_value_t = false)]
+        notify: bool,
+    },
+
+    /// Print a completion script for a shell, to be saved wherever the shell
+    /// loads completions from
+    Completions {
+        /// The shell to complete srsglass in
+        shell: Shell,
+    },
+}
+
+/// Load a dump from a file, or download the archived dump if given a date.
+fn load_dump(client: &Client, source: &str) -> Result<Dump> {
+    let dump = match source.parse::<NaiveDate>() {
+        Ok(date) => {
+            info!("Downloading data dump from {}", date);
+            client.get_dump_for_date(date)?
+        }
+        Err(_) => {
+            info!("Using data dump {}", source);
+            client.get_dump_from_file(source)?
+        }
+    };
+
+    Ok(dump)
+}
+
+/// Get the directory to cache API responses in by default, such as
+/// `~/.cache/srsglass`.
+fn default_cache_dir() -> Option<PathBuf> {
+    Some(dirs::cache_dir()?.join("srsglass"))
+}
+
+/// Get the directory to keep data dumps in by default, such as
+/// `~/.local/share/srsglass`.
+fn default_data_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("srsglass"))
+}
+
+/// Insert columns right after the major update time, or at the end if it is
+/// not shown, leaving out any that are already shown.
+fn insert_after_major(columns: &mut Vec<Column>, inserted: [Column; 2]) {
+    // insert in reverse so that the columns end up in the order given
+    for column in inserted.into_iter().rev() {
+        if !columns.contains(&column) {
+            let index = columns
+                .iter()
+                .position(|&column| column == Column::Major)
+                .map_or(columns.len(), |index| index + 1);
+            columns.insert(index, column);
+        }
+    }
+}
+
+/// Get the latest moment at the given wall-clock time up to a point in time,
+/// so that a time just before midnight is taken to be yesterday's.
+fn latest_at(time: NaiveTime, until: DateTime<Tz>) -> Result<DateTime<Utc>> {
+    let timezone = until.timezone();
+    let mut date = until.date_naive();
+    if time > until.time() {
+        date = date - Days::new(1);
+    }
+
+    timezone
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|moment| moment.with_timezone(&Utc))
+        .ok_or_else(|| anyhow!("{} does not exist in time zone {}", time, timezone))
+}
+
+/// Get when a region next updates, given the wall-clock time its update
+/// starts and how many seconds into the update it is estimated to update. An
+/// update that has already started counts if the region has yet to update in
+/// it.
+fn next_update(start: NaiveTime, offset: f64, now: DateTime<Tz>) -> Result<DateTime<Utc>> {
+    let target = latest_at(start, now)? + TimeDelta::milliseconds((offset * 1000.0) as i64);
 
-    /// The number of milliseconds to use in timestamps
-    #[arg(long = "precision", default_value_t = 0)]
-    precision: i32,
+    if target > now.with_timezone(&Utc) {
+        Ok(target)
+    } else {
+        Ok(target + TimeDelta::days(1))
+    }
+}
+
+/// Fill in the `{placeholder}`s of an output file name template.
+fn render_name_template(template: &str, values: &[(&str, String)]) -> Result<String> {
+    let mut name = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+
+        let Some(end) = rest[start..].find('}') else {
+            return Err(anyhow!(
+                "Unclosed placeholder in name template {}",
+                template
+            ));
+        };
+        let placeholder = &rest[start + 1..start + end];
+
+        let Some((_, value)) = values.iter().find(|(key, _)| *key == placeholder) else {
+            return Err(anyhow!(
+                "Unknown placeholder {{{}}} in name template",
+                placeholder
+            ));
+        };
+        name.push_str(value);
+
+        rest = &rest[start + end + 1..];
+    }
+
+    name.push_str(rest);
+    Ok(name)
+}
+
+/// Print any problems found with a dump's nation counts, listing at most the
+/// first few suspicious regions.
+fn report_nation_count_warnings(warnings: &[NationCountWarning]) {
+    const MAX_REPORTED: usize = 20;
+
+    if warnings.is_empty() {
+        return;
+    }
+
+    warn!("the nation counts in this dump look wrong, so update times may be off");
+    for warning in warnings.iter().take(MAX_REPORTED) {
+        warn!("  {}", warning);
+    }
+    if warnings.len() > MAX_REPORTED {
+        warn!("  and {} more", warnings.len() - MAX_REPORTED);
+    }
+}
+
+/// Read a file listing region names, one per line.
+fn read_region_list(path: &str) -> Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
 }
 
 fn main() -> Result<()> {
     let args = Cli::parse();
+    logger::init(args.verbose, args.quiet);
+
+    // completions need neither a nation nor a dump
+    if let Some(Command::Completions { shell }) = args.command {
+        clap_complete::generate(shell, &mut Cli::command(), "srsglass", &mut io::stdout());
+        return Ok(());
+    }
 
-    println!("Running srsglass with user nation {}", args.user_nation);
+    if !args.watch {
+        run(args, None)?;
+        return Ok(());
+    }
 
-    let user_agent = format!(
-        "{}/{} (by:Esfalsa, usedBy:{})",
-        env!("CARGO_PKG_NAME"),
-        env!("CARGO_PKG_VERSION"),
-        args.user_nation
+    if args.command.is_some() {
+        return Err(anyhow!("--watch can only be used to generate timesheets"));
+    }
+
+    let interval = Duration::from_secs(args.watch_interval * 60);
+    let mut last_dump = None;
+
+    loop {
+        // NS publishes each dump a while after major update, so there is no
+        // point downloading until a newer one is expected
+        if last_dump.is_some_and(|date| expected_dump_date() <= date) {
+            thread::sleep(interval);
+            continue;
+        }
+
+        match run(args.clone(), last_dump) {
+            Ok(Some(date)) => {
+                last_dump = Some(date);
+                info!("Waiting for the next data dump");
+                continue;
+            }
+            Ok(None) => info!(
+                "No new data dump yet, checking again in {} minutes",
+                args.watch_interval
+            ),
+            // keep watching through failures, which are usually NS being
+            // briefly unavailable
+            Err(error) => error!("{}; trying again in {} minutes", error, args.watch_interval),
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+/// Generate timesheets as set by the command-line arguments, returning the
+/// date of the dump used. In watch mode, nothing is generated if the dump is
+/// no newer than `last_dump`.
+fn run(args: Cli, last_dump: Option<NaiveDate>) -> Result<Option<NaiveDate>> {
+    let config = Config::load(args.config.as_deref().map(Path::new))?;
+
+    // Flags take precedence over the config file, which takes precedence over
+    // the built-in defaults
+    let user_nation = args.user_nation.or(config.nation.clone()).ok_or(anyhow!(
+        "No nation given; pass --nation or set nation in the config file"
+    ))?;
+    let formats = if !args.format.is_empty() {
+        args.format
+    } else {
+        vec![config.format.unwrap_or(Format::Xlsx)]
+    };
+    let mut major_length = args.major_length.or(config.major).unwrap_or(5350);
+    let mut minor_length = args.minor_length.or(config.minor).unwrap_or(3550);
+    let precision = args.precision.or(config.precision).unwrap_or(0);
+    let model = Model::from(args.model.or(config.model).unwrap_or_default());
+    let timezone = match args.timezone.or(config.timezone.clone()) {
+        Some(name) => name
+            .parse::<Tz>()
+            .map_err(|_| anyhow!("Unknown time zone {}", name))?,
+        None => chrono_tz::US::Eastern,
+    };
+    let retries = args.retries.or(config.retries).unwrap_or(3);
+
+    let now = match args.now {
+        Some(time) => latest_at(time, Utc::now().with_timezone(&timezone))?,
+        None => Utc::now(),
+    };
+    let update_started = args
+        .started
+        .map(|time| latest_at(time, now.with_timezone(&timezone)))
+        .transpose()?;
+
+    info!("Running srsglass with user nation {}", user_nation);
+
+    let progress_bar = if args.quiet > 0 {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(0)
+    }
+    .with_style(
+        ProgressStyle::with_template(
+            "{spinner} [{elapsed_precise}] [{bar:40}] {bytes}/{total_bytes} {msg}",
+        )?
+        .progress_chars("=> "),
     );
 
-    let client = Client::new(&user_agent);
+    // fall back to the working directory if there is no data directory
+    let dump_path = match &args.dump_path {
+        Some(dump_path) => PathBuf::from(dump_path),
+        None => args
+            .data_dir
+            .clone()
+            .map(PathBuf::from)
+            .or_else(default_data_dir)
+            .unwrap_or_default()
+            .join("regions.xml.gz"),
+    };
+    let from_stdin = args.dump_path.as_deref() == Some("-");
+
+    let mut client = Client::builder(&user_nation).proxy_from_env(true);
+    if let Some(proxy) = args.proxy.or(config.proxy.clone()) {
+        client = client.proxy(proxy);
+    }
+
+    let mut client = client
+        .build()?
+        .with_retries(retries, Duration::from_secs(1))
+        .with_stale_retries(
+            args.stale_retries,
+            Duration::from_secs(args.stale_retry_interval * 60),
+        )
+        .with_factbooks(!args.no_factbook)
+        .with_embassies(!args.no_embassies)
+        .with_partial_dumps(args.allow_partial)
+        .with_progress({
+            let progress_bar = progress_bar.clone();
+            move |progress| match progress {
+                Progress::Read { bytes, total } => {
+                    if let Some(total) = total {
+                        progress_bar.set_length(total);
+                    }
+                    progress_bar.set_position(bytes);
+                }
+                Progress::Parsed { regions } => {
+                    progress_bar.set_message(format!("{} regions", regions));
+                }
+            }
+        });
+
+    // a dump piped in has nowhere to be saved to
+    if args.save_dump && !from_stdin {
+        if let Some(parent) = dump_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        client = client.with_save_path(&dump_path);
+    }
+
+    if !args.no_cache {
+        match args
+            .cache_dir
+            .clone()
+            .map(PathBuf::from)
+            .or_else(default_cache_dir)
+        {
+            Some(cache_dir) => {
+                client = client.with_tag_cache(cache_dir, Duration::from_secs(args.cache_ttl * 60));
+            }
+            None => debug!("No cache directory found; API responses will not be cached"),
+        }
+    }
+
+    if let Some(Command::Diff { old, new }) = &args.command {
+        let old = load_dump(&client, old)?;
+        progress_bar.finish_and_clear();
+        progress_bar.reset();
+        let new = load_dump(&client, new)?;
+        progress_bar.finish_and_clear();
+
+        let changes = diff::diff(&old, &new);
+        info!(
+            "Found {} changes between {} and {}",
+            changes.len(),
+            old.dump_date,
+            new.dump_date
+        );
+
+        let outfile = args.outfile.clone().unwrap_or(format!(
+            "srsglass-diff{}_{}.xlsx",
+            old.dump_date, new.dump_date
+        ));
+        diff::to_workbook(&old, &new, &changes)?.save(&outfile)?;
+        info!("Saved changes to {}", outfile);
 
-    let dump_path = Path::new(&args.dump_path);
+        return Ok(None);
+    }
 
-    let dump = if args.use_dump && dump_path.exists() {
-        println!("Using existing data dump");
-        client.get_dump_from_file(dump_path)?
+    let mut dump = if let Some(date) = args.date {
+        info!("Downloading data dump from {}", date);
+        client.get_dump_for_date(date)?
+    } else if args.offline {
+        info!("Using existing data dump offline; password, frontier, and occupied status will not be shown");
+        let dump = if from_stdin {
+            client.get_dump_from_reader_offline(io::stdin())?
+        } else {
+            client.get_dump_from_file_offline(&dump_path)?
+        };
+
+        if dump.is_stale() {
+            warn!("data dump from {} is out of date", dump.dump_date);
+        }
+
+        dump
+    } else if args.use_dump && (from_stdin || dump_path.exists()) {
+        info!("Using existing data dump");
+        let dump = if from_stdin {
+            client.get_dump_from_reader(io::stdin())?
+        } else {
+            client.get_dump_from_file(&dump_path)?
+        };
+
+        if dump.is_stale() && !args.force_stale {
+            info!(
+                "Data dump from {} is out of date, downloading current data dump (use --force-stale to skip)",
+                dump.dump_date
+            );
+            client.get_dump()?
+        } else {
+            dump
+        }
     } else {
-        println!("Downloading data dump");
+        info!("Downloading data dump");
         client.get_dump()?
     };
 
-    println!("Saving timesheet");
+    progress_bar.finish_and_clear();
+
+    if last_dump.is_some_and(|date| dump.dump_date <= date) {
+        return Ok(None);
+    }
+
+    if let Some(sha256) = &dump.sha256 {
+        debug!("Data dump has SHA-256 {}", sha256);
+    }
+    if let Some(expected) = &args.sha256 {
+        dump.verify_sha256(expected)?;
+        info!("Data dump matches the expected SHA-256");
+    }
+
+    match dump.last_update() {
+        Some(last_update) => info!(
+            "Data dump from {}, last updated {}",
+            dump.dump_date,
+            last_update.format("%Y-%m-%d %H:%M:%S UTC")
+        ),
+        None => info!("Data dump from {}", dump.dump_date),
+    }
+
+    // NS's count of nations is only used as a check, so go on without it
+    let world_nations = if args.offline {
+        None
+    } else {
+        client.get_world_nation_count().ok()
+    };
+    report_nation_count_warnings(&dump.check_nation_counts(world_nations));
+
+    if args.new_regions {
+        match dump
+            .last_update()
+            .map(|since| client.get_new_regions(since))
+        {
+            Some(Ok(new_regions)) => info!(
+                "Added {} regions founded since the data dump",
+                dump.add_new_regions(&new_regions)
+            ),
+            Some(Err(error)) => warn!(
+                "could not get regions founded since the data dump: {}",
+                error
+            ),
+            None => warn!("the data dump has no update times, so new regions can't be added"),
+        }
+    }
+
+    if !args.include_empty {
+        dump.remove_empty_regions();
+    }
+
+    if args.use_nations {
+        info!("Downloading nations data dump");
+        progress_bar.reset();
+        progress_bar.set_message("");
+        let nations = client.get_nations_dump()?;
+        dump.add_endorsements(&nations);
+        dump.add_wa_members(&nations);
+        progress_bar.finish_and_clear();
+    }
+
+    if args.auto_length {
+        match estimate::observed_length(&dump, Update::Major) {
+            Some(length) => major_length = length as i32,
+            None => warn!(
+                "could not observe major update length, using {}",
+                major_length
+            ),
+        }
+        match estimate::observed_length(&dump, Update::Minor) {
+            Some(length) => minor_length = length as i32,
+            None => warn!(
+                "could not observe minor update length, using {}",
+                minor_length
+            ),
+        }
+        info!(
+            "Using observed update lengths of {}s (major) and {}s (minor)",
+            major_length, minor_length
+        );
+    }
+
+    let styling = config.styling()?;
+
+    let color_rules = if !args.color_rules.is_empty() {
+        args.color_rules
+    } else if !config.color_rules.is_empty() {
+        config.color_rules()?
+    } else {
+        styling.color_rules()
+    };
+
+    let mut columns = if !args.columns.is_empty() {
+        args.columns
+    } else if !config.columns.is_empty() {
+        config.columns()?
+    } else {
+        Column::DEFAULT.to_vec()
+    };
+
+    // a column listed twice would have two headers with the same name, which
+    // Excel tables don't allow
+    if let Some(column) = (1..columns.len())
+        .find(|&index| columns[..index].contains(&columns[index]))
+        .map(|index| columns[index])
+    {
+        return Err(anyhow!("Column {} is listed more than once", column.key()));
+    }
+
+    // show relative times next to the update times unless they were placed
+    // elsewhere already
+    if args.relative_to.is_some() {
+        insert_after_major(&mut columns, [Column::MinorRelative, Column::MajorRelative]);
+    }
+
+    // likewise for the times to be in position by
+    if args.trigger_offset.is_some() {
+        insert_after_major(&mut columns, [Column::MinorTrigger, Column::MajorTrigger]);
+    }
+
+    // and for the parts of the update regions update in
+    if args.early_window.is_some() || args.late_window.is_some() {
+        insert_after_major(&mut columns, [Column::MinorWindow, Column::MajorWindow]);
+    }
+
+    // WA members and exact endorsement counts need the nations dump, so only
+    // show the columns that depend on them then
+    let mut inserted = Vec::new();
+    if args.use_nations {
+        inserted.extend([
+            (Column::WaMembers, Column::Population),
+            (Column::EndorsementsNeeded, Column::DelegateEndorsements),
+        ]);
+    }
+    // likewise for whether a region has friendly embassies, which are only
+    // known given a list of friendly regions
+    if args.friendly_path.is_some() {
+        inserted.push((Column::HasFriendlyEmbassy, Column::Embassies));
+    }
+    // and for whether regions have already updated, which is only known once
+    // an update is underway
+    if args.started.is_some() {
+        inserted.push((Column::Updated, Column::Major));
+    }
+    for (column, after) in inserted {
+        if !columns.contains(&column) {
+            let index = columns
+                .iter()
+                .position(|&existing| existing == after)
+                .map_or(columns.len(), |index| index + 1);
+            columns.insert(index, column);
+        }
+    }
+
+    // no filter is kept apart from a filter that matches nothing, which
+    // would otherwise list every region
+    let mut filter = args.regions;
+    if let Some(regions_path) = &args.regions_path {
+        filter.extend(read_region_list(regions_path)?);
+    }
+    let mut filter = (!filter.is_empty()).then_some(filter);
+
+    if !args.tags.is_empty() {
+        let tags: Vec<&str> = args.tags.iter().map(String::as_str).collect();
+        let tagged = client.get_regions_by_tag(&tags)?;
+
+        let tagged = match filter {
+            None => tagged,
+            Some(filter) => {
+                let tagged: HashSet<String> =
+                    tagged.iter().map(|name| canonical_name(name)).collect();
+                filter
+                    .into_iter()
+                    .filter(|name| tagged.contains(&canonical_name(name)))
+                    .collect()
+            }
+        };
+
+        if tagged.is_empty() {
+            return Err(anyhow!("No regions match the given tags"));
+        }
+        filter = Some(tagged);
+    }
+
+    // tag sets from the config file each get a column at the end
+    let mut tag_sets = Vec::new();
+    if args.offline && !config.tag_sets.is_empty() {
+        warn!("tag sets can't be fetched offline, so their columns will not be shown");
+    } else {
+        for query in &config.tag_sets {
+            let include: Vec<&str> = query.include.iter().map(String::as_str).collect();
+            let exclude: Vec<&str> = query.exclude.iter().map(String::as_str).collect();
+
+            columns.push(Column::TagSet(tag_sets.len()));
+            tag_sets.push(TagSet {
+                name: query.name.clone(),
+                regions: client.get_regions_by_tags(&include, &exclude)?,
+            });
+        }
+    }
+
+    let options = TimesheetOptions {
+        major_length,
+        minor_length,
+        timestamp_precision: precision,
+        model,
+        anchors: args.anchors,
+        anchor_update: args.anchor_update.into(),
+        update_started,
+        now: Some(now),
+        trigger_offset: args.trigger_offset.unwrap_or(0.0),
+        early_window: args.early_window.unwrap_or(600.0),
+        late_window: args.late_window.unwrap_or(600.0),
+        relative_to: args.relative_to,
+        timezone,
+        spyglass_times: args.spyglass_times,
+        passworded_sheet: args.passworded_sheet,
+        update_chart: args.update_chart,
+        overflow_sheet: args.overflow_sheet,
+        color_rules,
+        styling,
+        filter,
+        filter_context: args.context,
+        friendly: match &args.friendly_path {
+            Some(path) => read_region_list(path)?,
+            None => Vec::new(),
+        },
+        enemy: match &args.enemy_path {
+            Some(path) => read_region_list(path)?,
+            None => Vec::new(),
+        },
+        columns,
+        tag_sets,
+        link_style: args
+            .link_style
+            .or(config.link_style)
+            .unwrap_or_default()
+            .into(),
+        ..TimesheetOptions::default()
+    };
+
+    if let Some(Command::Query { region }) = &args.command {
+        print!("{}", dump.describe_region(region, &options)?);
+        return Ok(None);
+    }
 
-    // Use dump's date to dynamically create the filename if none is specified
-    let outfile = match args.outfile {
-        Some(filepath) => filepath,
-        None => format!("srsglass{}.xlsx", dump.dump_date),
+    if let Some(Command::Countdown {
+        region,
+        start,
+        update,
+        alerts,
+        notify,
+    }) = &args.command
+    {
+        let offset = dump.estimated_time(region, (*update).into(), &options)?;
+        let target = next_update(*start, offset, now.with_timezone(&timezone))?;
+        info!(
+            "{} is estimated to update at {}",
+            region,
+            target.with_timezone(&timezone).format("%-I:%M:%S %p %Z")
+        );
+
+        countdown::run(region, target, alerts, *notify)?;
+        return Ok(None);
+    }
+
+    let triggers = match &args.targets_path {
+        Some(targets_path) => {
+            let targets = read_region_list(targets_path)?;
+
+            let triggers = triggers::find_triggers(
+                &dump,
+                &targets,
+                args.lead_time,
+                args.trigger_update.into(),
+                &options,
+            )?;
+            print!("{}", triggers::to_text(&triggers, precision));
+
+            triggers
+        }
+        None => Vec::new(),
+    };
+
+    let outfile = match args.name_template.or(config.name_template.clone()) {
+        Some(template) if args.outfile.is_none() => Some(render_name_template(
+            &template,
+            &[
+                ("dump_date", dump.dump_date.to_string()),
+                ("generated", chrono::Local::now().date_naive().to_string()),
+                ("major", major_length.to_string()),
+                ("minor", minor_length.to_string()),
+            ],
+        )?),
+        _ => args.outfile,
     };
 
-    dump.to_excel(
-        &outfile,
-        args.major_length,
-        args.minor_length,
-        args.precision,
-    )?;
+    let mut saved = Vec::new();
+    for &format in &formats {
+        info!("Saving timesheet");
+        let started = Instant::now();
+
+        // Use dump's date to dynamically create the filename if none is specified,
+        // and give each format its own extension if there are several
+        let outfile = match &outfile {
+            Some(filepath) if formats.len() == 1 => filepath.clone(),
+            Some(filepath) => Path::new(filepath)
+                .with_extension(format.extension())
+                .to_string_lossy()
+                .into_owned(),
+            None => format!("srsglass{}.{}", dump.dump_date, format.extension()),
+        };
+
+        match format {
+            Format::Xlsx => {
+                let mut workbook = dump.to_workbook(&options)?;
+
+                if !triggers.is_empty() {
+                    triggers::add_worksheet(&mut workbook, &triggers)?;
+                }
+
+                workbook.save(&outfile)?;
+            }
+            Format::Csv => dump.to_csv(&outfile, &options)?,
+            Format::Html => dump.to_html(&outfile, &options)?,
+            Format::Ods => dump.to_ods(&outfile, &options)?,
+            Format::Markdown => dump.to_markdown(&outfile, &options)?,
+            Format::Bbcode => dump.to_bbcode(&outfile, &options)?,
+            #[cfg(feature = "serde")]
+            Format::Json => dump.to_json(&outfile, &options)?,
+            #[cfg(feature = "sqlite")]
+            Format::Sqlite => dump.to_sqlite(&outfile, &options)?,
+            #[cfg(feature = "parquet")]
+            Format::Parquet => dump.to_parquet(&outfile, &options)?,
+        }
+
+        debug!("Wrote {} in {:.2?}", outfile, started.elapsed());
+        info!("Saved timesheet to {}", outfile);
+        saved.push(outfile);
+    }
 
-    println!("Saved timesheet to {}", outfile);
+    #[cfg(feature = "serde")]
+    if args.manifest {
+        let manifest_path = Path::new(&saved[0]).with_extension("manifest.json");
+        Manifest::new(&dump, &options, saved)?.save(&manifest_path)?;
+        info!("Saved manifest to {}", manifest_path.display());
+    }
 
-    Ok(())
+    Ok(Some(dump.dump_date))
 }
@@ -0,0 +1,93 @@
+use crate::ModelArg;
+use anyhow::Result;
+use serde::Serialize;
+use srsglass::{Dump, TimesheetOptions};
+use std::{fs::File, path::Path};
+
+/// A record of how a timesheet was generated, written next to it so that
+/// everyone working from copies of it can check they have the same sheet.
+#[derive(Serialize, Debug)]
+pub struct Manifest {
+    pub version: &'static str,
+    pub generated: String,
+    pub dump_date: String,
+    pub dump_sha256: Option<String>,
+    /// Arguments srsglass was run with
+    pub arguments: Vec<String>,
+    pub settings: Settings,
+    /// Number of regions in the dump
+    pub regions: usize,
+    /// Number of regions listed in the timesheet
+    pub rows: usize,
+    pub files: Vec<String>,
+}
+
+/// Settings the timesheet was generated with, after defaults and the config
+/// file were applied.
+#[derive(Serialize, Debug)]
+pub struct Settings {
+    pub major_length: i32,
+    pub minor_length: i32,
+    pub model: ModelArg,
+    pub precision: i32,
+    pub timezone: String,
+}
+
+impl Manifest {
+    pub fn new(dump: &Dump, options: &TimesheetOptions, files: Vec<String>) -> Result<Manifest> {
+        Ok(Manifest {
+            version: env!("CARGO_PKG_VERSION"),
+            generated: chrono::Utc::now().to_rfc3339(),
+            dump_date: dump.dump_date.to_string(),
+            dump_sha256: dump.sha256.clone(),
+            arguments: std::env::args().skip(1).collect(),
+            settings: Settings {
+                major_length: options.major_length,
+                minor_length: options.minor_length,
+                model: options.model.into(),
+                precision: options.timestamp_precision,
+                timezone: options.timezone.name().to_string(),
+            },
+            regions: dump.regions.len(),
+            rows: dump.row_count(options)?,
+            files,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use srsglass::DumpExtras;
+
+    #[test]
+    fn manifest_records_the_dump_and_settings() {
+        let file = File::open("tests/fixtures/regions.xml.gz").unwrap();
+        let dump = Dump::from_reader(file, DumpExtras::default()).unwrap();
+        let options = TimesheetOptions {
+            major_length: 5400,
+            timestamp_precision: 2,
+            filter: Some(vec!["lazarus".to_string(), "Osiris".to_string()]),
+            ..TimesheetOptions::default()
+        };
+
+        let manifest = Manifest::new(&dump, &options, vec!["srsglass.xlsx".to_string()]).unwrap();
+
+        assert_eq!(manifest.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.dump_date, dump.dump_date.to_string());
+        assert_eq!(manifest.dump_sha256, dump.sha256);
+        assert!(manifest.dump_sha256.is_some());
+        assert_eq!(manifest.settings.major_length, 5400);
+        assert_eq!(manifest.settings.minor_length, 3550);
+        assert_eq!(manifest.settings.precision, 2);
+        assert_eq!(manifest.settings.timezone, "US/Eastern");
+        assert_eq!(manifest.regions, dump.regions.len());
+        assert_eq!(manifest.rows, 2);
+        assert_eq!(manifest.files, ["srsglass.xlsx"]);
+    }
+}
@@ -0,0 +1,52 @@
+//! Markdown tables of a few regions, for forum and Discord posts.
+
+use crate::{Column, Dump, Result, TimesheetOptions};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+impl Dump {
+    /// Write a compact Markdown table of the timesheet, with each region's
+    /// link, update times, and delegate endorsements, for pasting into forum
+    /// or Discord posts. Meant for a few regions picked out with
+    /// [`TimesheetOptions::filter`], so [`TimesheetOptions::columns`] is
+    /// ignored.
+    pub fn to_markdown(
+        &self,
+        output_file: impl AsRef<Path>,
+        options: &TimesheetOptions,
+    ) -> Result<()> {
+        options.check_precision()?;
+
+        let columns = [
+            Column::Region,
+            Column::Link,
+            Column::Minor,
+            Column::Major,
+            Column::DelegateEndorsements,
+        ];
+        // pipes would end a cell early
+        let cell = |text: &str| text.replace('|', "\\|");
+
+        let mut markdown = format!(
+            "| {} |\n| --- | --- | ---: | ---: | ---: |\n",
+            columns.map(Column::header).join(" | ")
+        );
+
+        for row in self.rows(options)? {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|&column| cell(&row.text(column, options.timestamp_precision)))
+                .collect();
+            markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+
+        let mut file = BufWriter::new(File::create(output_file)?);
+        file.write_all(markdown.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+}
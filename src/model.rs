@@ -0,0 +1,581 @@
+//! Regions, nations, and the dumps that hold them.
+
+use crate::estimate::Update;
+use crate::parser::{parse_dump, ParseOptions};
+use crate::{Result, SrsglassError};
+use chrono::naive::Days;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::US::Eastern;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::Read,
+};
+
+#[derive(Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub name: Option<String>,
+    pub factbook: Option<String>,
+    pub population: Option<i32>,
+    pub delegate: Option<String>,
+    pub delegate_votes: Option<i32>,
+    pub delegate_endorsements: Option<i32>,
+    pub delegate_exec: Option<bool>,
+    /// The authorities the region's delegate holds, from `DELEGATEAUTH`
+    pub delegate_authority: Option<DelegateAuthority>,
+    pub governor: Option<String>,
+    pub last_major: Option<i64>,
+    pub last_minor: Option<i64>,
+    /// Unix timestamp at which the region was founded or last refounded, or
+    /// `None` if it was founded before NS recorded founding times
+    pub founded: Option<i64>,
+    pub nations_before: Option<i32>,
+    /// Number of nations in the region that are in the World Assembly, as
+    /// counted by [`Dump::add_wa_members`]
+    pub wa_members: Option<i32>,
+    pub embassies: Vec<Embassy>,
+    /// URL of the region's flag, if it has one
+    pub flag: Option<String>,
+    /// URL of the region's banner, which is one of the default banners if
+    /// none was uploaded
+    pub banner: Option<String>,
+}
+
+impl Region {
+    /// Get the number of endorsements held by the region's delegate.
+    ///
+    /// Uses the count from the nations dump if one was loaded with
+    /// [`Dump::add_endorsements`], and otherwise approximates it as one less
+    /// than the delegate's votes.
+    pub fn endorsement_count(&self) -> Option<i32> {
+        self.delegate_endorsements
+            .or_else(|| self.delegate_votes.map(|votes| (votes - 1).max(0)))
+    }
+
+    /// Get the region's name in the canonical form used in URLs and API
+    /// responses. See [`canonical_name`].
+    pub fn canonical_name(&self) -> Option<String> {
+        self.name.as_deref().map(canonical_name)
+    }
+
+    /// Get the region's name in the form NS displays it in. See
+    /// [`display_name`].
+    pub fn display_name(&self) -> Option<String> {
+        self.name.as_deref().map(display_name)
+    }
+
+    /// Check whether the region was founded or refounded since the last major
+    /// update, so it has not updated yet.
+    pub fn is_new(&self) -> bool {
+        self.last_major == Some(0)
+    }
+}
+
+/// The regional authorities held by a region's delegate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DelegateAuthority {
+    pub executive: bool,
+    pub world_assembly: bool,
+    pub appearance: bool,
+    /// Border control, which lets the delegate eject and ban nations
+    pub border_control: bool,
+    pub communications: bool,
+    pub embassies: bool,
+    pub polls: bool,
+}
+
+impl DelegateAuthority {
+    /// Get the authorities given by the codes NS lists them as in
+    /// `DELEGATEAUTH`, such as `XWABCEP` for every authority. Unknown codes
+    /// are ignored.
+    pub fn from_codes(codes: &[u8]) -> Self {
+        let has = |code| codes.contains(&code);
+
+        Self {
+            executive: has(b'X'),
+            world_assembly: has(b'W'),
+            appearance: has(b'A'),
+            border_control: has(b'B'),
+            communications: has(b'C'),
+            embassies: has(b'E'),
+            polls: has(b'P'),
+        }
+    }
+}
+
+/// The state of an embassy between two regions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EmbassyStatus {
+    #[default]
+    Established,
+    /// Requested by the other region, awaiting this region's response
+    Invited,
+    /// Requested by this region, awaiting the other region's response
+    Requested,
+    /// Accepted by both regions, but not yet built
+    Pending,
+    /// Declined by the other region
+    Denied,
+    /// Declined by this region
+    Rejected,
+    /// Being withdrawn, but still standing until it closes
+    Closing,
+}
+
+impl EmbassyStatus {
+    /// Get the status given by an `EMBASSY` element's `type` attribute, which
+    /// is absent for established embassies.
+    pub(crate) fn from_type(embassy_type: Option<&str>) -> Self {
+        match embassy_type {
+            None => EmbassyStatus::Established,
+            Some("invited") => EmbassyStatus::Invited,
+            Some("requested") => EmbassyStatus::Requested,
+            Some("denied") => EmbassyStatus::Denied,
+            Some("rejected") => EmbassyStatus::Rejected,
+            Some("closing") => EmbassyStatus::Closing,
+            // treat anything unrecognized as not yet built
+            Some(_) => EmbassyStatus::Pending,
+        }
+    }
+}
+
+/// An embassy a region has, or is negotiating, with another region.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Embassy {
+    pub region: String,
+    pub status: EmbassyStatus,
+}
+
+impl Embassy {
+    pub fn is_established(&self) -> bool {
+        self.status == EmbassyStatus::Established
+    }
+}
+
+#[derive(Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Nation {
+    pub name: Option<String>,
+    pub region: Option<String>,
+    pub endorsements: Vec<String>,
+    /// Whether the nation is a member or delegate of the World Assembly
+    pub wa_member: bool,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dump {
+    // Date that NS will consider this dump to be generated on
+    pub dump_date: NaiveDate,
+    pub regions: Vec<Region>,
+    pub governorless: Vec<String>,
+    pub passwordless: Vec<String>,
+    /// Regions that are frontiers rather than strongholds
+    pub frontiers: Vec<String>,
+    /// Regions tagged Invader, which raiders tag the regions they hold with
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub invaders: Vec<String>,
+    /// Regions tagged Defender
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub defenders: Vec<String>,
+    /// Whether the dump ended early, so that regions after the last one read
+    /// are missing
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub incomplete: bool,
+    /// SHA-256 of the regions dump as it was read, gzipped or not, as a hex
+    /// string, or `None` if the dump was built from regions parsed elsewhere
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sha256: Option<String>,
+}
+
+/// The parts of a [`Dump`] that come from the API rather than the regions
+/// dump, for building a dump with [`Dump::from_reader`] or
+/// [`Dump::from_regions`]. Anything left unset is filled in from the
+/// regions dump as [`Client::get_dump_from_file_offline`] does.
+///
+/// [`Client::get_dump_from_file_offline`]: crate::Client::get_dump_from_file_offline
+#[derive(Clone, Debug, Default)]
+pub struct DumpExtras {
+    /// Date NS lists the dump as in the archive, or `None` to work it out
+    /// from when the first region updated
+    pub dump_date: Option<NaiveDate>,
+    /// Governorless regions, or `None` to use each region's governor in the
+    /// dump
+    pub governorless: Option<Vec<String>>,
+    /// Passwordless regions, or `None` to treat every region as passwordless
+    pub passwordless: Option<Vec<String>>,
+    /// Regions that are frontiers rather than strongholds
+    pub frontiers: Vec<String>,
+    /// Regions tagged Invader
+    pub invaders: Vec<String>,
+    /// Regions tagged Defender
+    pub defenders: Vec<String>,
+}
+
+/// How far the nations counted in a dump can be from NS's count of nations in
+/// the world, as a fraction of NS's count, before the dump is suspect.
+const NATION_COUNT_TOLERANCE: f64 = 0.05;
+
+/// A sign that the nation counts in a dump are wrong, which would throw off
+/// every estimate in a timesheet. See [`Dump::check_nation_counts`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NationCountWarning {
+    /// The nations counted in the dump are far from NS's count of nations in
+    /// the world
+    TotalMismatch { dump: i32, world: i32 },
+    /// A region has fewer nations before it than the region before it in the
+    /// dump
+    CountDecreased {
+        region: String,
+        nations_before: i32,
+        previous_region: String,
+        previous_nations_before: i32,
+    },
+}
+
+impl fmt::Display for NationCountWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NationCountWarning::TotalMismatch { dump, world } => write!(
+                f,
+                "The dump counts {} nations, but NS counts {} in the world",
+                dump, world
+            ),
+            NationCountWarning::CountDecreased {
+                region,
+                nations_before,
+                previous_region,
+                previous_nations_before,
+            } => write!(
+                f,
+                "{} has {} nations before it, fewer than the {} before {}",
+                region, nations_before, previous_nations_before, previous_region
+            ),
+        }
+    }
+}
+
+impl Dump {
+    /// Read a dump from a reader, whether it is gzipped as NS publishes it
+    /// or has already been decompressed, without a [`Client`], for
+    /// applications that download dumps themselves and for tests. No
+    /// requests are made, so the rest of the dump comes from `extras`.
+    ///
+    /// A dump that ends early is an error; use
+    /// [`Client::with_partial_dumps`] to keep partial dumps.
+    ///
+    /// [`Client`]: crate::Client
+    /// [`Client::with_partial_dumps`]: crate::Client::with_partial_dumps
+    pub fn from_reader(dump: impl Read + Send, extras: DumpExtras) -> Result<Self> {
+        let parsed = parse_dump(dump, &ParseOptions::default())?;
+
+        Ok(Self {
+            sha256: Some(parsed.sha256),
+            ..Self::from_regions(parsed.regions, extras)?
+        })
+    }
+
+    /// Build a dump from regions in the order they appear in the regions
+    /// dump, such as regions parsed elsewhere or built for tests. The number
+    /// of nations before each region is counted from their populations, and
+    /// the rest of the dump comes from `extras`.
+    pub fn from_regions(
+        regions: impl IntoIterator<Item = Region>,
+        extras: DumpExtras,
+    ) -> Result<Self> {
+        let mut nations_before = 0;
+        let regions: Vec<Region> = regions
+            .into_iter()
+            .map(|region| {
+                let region = Region {
+                    nations_before: Some(nations_before),
+                    ..region
+                };
+                nations_before += region.population.unwrap_or(0);
+                region
+            })
+            .collect();
+
+        let dump_date = match extras.dump_date {
+            Some(dump_date) => dump_date,
+            None => compute_dump_date(&regions)?,
+        };
+        let governorless = extras
+            .governorless
+            .unwrap_or_else(|| governorless_in(&regions));
+        let passwordless = extras.passwordless.unwrap_or_else(|| {
+            regions
+                .iter()
+                .filter_map(|region| region.name.clone())
+                .collect()
+        });
+
+        Ok(Self {
+            dump_date,
+            regions,
+            governorless,
+            passwordless,
+            frontiers: extras.frontiers,
+            invaders: extras.invaders,
+            defenders: extras.defenders,
+            incomplete: false,
+            sha256: None,
+        })
+    }
+
+    /// Check that the dump's SHA-256 is `expected`, such as the checksum in
+    /// the metadata of a timesheet someone else made, to be sure that both
+    /// were made from the same dump.
+    pub fn verify_sha256(&self, expected: &str) -> Result<()> {
+        match &self.sha256 {
+            Some(sha256) if sha256.eq_ignore_ascii_case(expected.trim()) => Ok(()),
+            Some(sha256) => Err(SrsglassError::InvalidDump(format!(
+                "Dump has SHA-256 {}, not {}",
+                sha256, expected
+            ))),
+            None => Err(SrsglassError::InvalidDump(
+                "Dump has no SHA-256 to check, as it was not read by srsglass".to_string(),
+            )),
+        }
+    }
+
+    /// Check whether a newer dump than this one should be available.
+    pub fn is_stale(&self) -> bool {
+        self.dump_date < expected_dump_date()
+    }
+
+    /// Get the time of the last major update of any region in the dump,
+    /// which NS generates the dump shortly after. Together with
+    /// [`Dump::dump_date`], this identifies which data a timesheet is from.
+    pub fn last_update(&self) -> Option<DateTime<Utc>> {
+        let timestamp = self
+            .regions
+            .iter()
+            .filter_map(|region| region.last_major)
+            .max()?;
+        DateTime::from_timestamp(timestamp, 0)
+    }
+
+    /// Check the dump's nation counts for signs that it was read incorrectly:
+    /// a total far from `world_nations`, NS's count of nations in the world if
+    /// known, or a cumulative count that goes down from one region to the
+    /// next.
+    pub fn check_nation_counts(&self, world_nations: Option<i32>) -> Vec<NationCountWarning> {
+        let mut warnings = Vec::new();
+
+        if let (Ok(dump), Some(world)) = (self.total_population(), world_nations) {
+            if (dump - world).abs() as f64 > world as f64 * NATION_COUNT_TOLERANCE {
+                warnings.push(NationCountWarning::TotalMismatch { dump, world });
+            }
+        }
+
+        let counted = self
+            .regions
+            .iter()
+            .filter_map(|region| Some((region.name.as_deref()?, region.nations_before?)));
+
+        for ((previous_region, previous_nations_before), (region, nations_before)) in
+            counted.clone().zip(counted.skip(1))
+        {
+            if nations_before < previous_nations_before {
+                warnings.push(NationCountWarning::CountDecreased {
+                    region: region.to_string(),
+                    nations_before,
+                    previous_region: previous_region.to_string(),
+                    previous_nations_before,
+                });
+            }
+        }
+
+        warnings
+    }
+
+    /// Populate each region's delegate endorsement count from the nations
+    /// data dump.
+    pub fn add_endorsements(&mut self, nations: &[Nation]) {
+        let endorsements: HashMap<String, i32> = nations
+            .iter()
+            .filter_map(|nation| {
+                let name = nation.name.as_ref()?;
+                Some((canonical_name(name), nation.endorsements.len() as i32))
+            })
+            .collect();
+
+        for region in &mut self.regions {
+            region.delegate_endorsements = match &region.delegate {
+                Some(delegate) => endorsements.get(delegate).copied(),
+                None => Some(0),
+            };
+        }
+    }
+
+    /// Add regions founded or refounded since the dump, as from
+    /// [`Client::get_new_regions`], to the end of the dump, so that they are
+    /// estimated to update after every existing region as newly founded
+    /// regions do. Each is given one nation, its founder, and is treated as
+    /// having a governor and a password, since the happenings don't say
+    /// otherwise. Regions already in the dump are skipped. Returns the number
+    /// of regions added.
+    ///
+    /// [`Client::get_new_regions`]: crate::Client::get_new_regions
+    pub fn add_new_regions(&mut self, names: &[String]) -> usize {
+        let mut existing: HashSet<String> = self
+            .regions
+            .iter()
+            .filter_map(Region::canonical_name)
+            .collect();
+
+        let mut nations_before = self
+            .regions
+            .iter()
+            .filter_map(|region| region.population)
+            .sum();
+        let mut added = 0;
+
+        for name in names {
+            if !existing.insert(canonical_name(name)) {
+                continue;
+            }
+
+            self.regions.push(Region {
+                name: Some(display_name(name)),
+                population: Some(1),
+                delegate_votes: Some(0),
+                delegate_exec: Some(false),
+                delegate_authority: Some(DelegateAuthority::default()),
+                last_major: Some(0),
+                last_minor: Some(0),
+                nations_before: Some(nations_before),
+                ..Region::default()
+            });
+
+            nations_before += 1;
+            added += 1;
+        }
+
+        added
+    }
+
+    /// Count the World Assembly members in each region from the nations data
+    /// dump.
+    pub fn add_wa_members(&mut self, nations: &[Nation]) {
+        let mut wa_members: HashMap<String, i32> = HashMap::new();
+
+        for nation in nations.iter().filter(|nation| nation.wa_member) {
+            if let Some(region) = &nation.region {
+                *wa_members.entry(canonical_name(region)).or_default() += 1;
+            }
+        }
+
+        for region in &mut self.regions {
+            region.wa_members = region
+                .canonical_name()
+                .map(|name| wa_members.get(&name).copied().unwrap_or(0));
+        }
+    }
+
+    /// Remove regions without any nations, which NS skips during update.
+    ///
+    /// Regions with nations are always kept, including those that have never
+    /// updated: newly founded regions are estimated to update after every
+    /// existing region (see [`estimate::update_order`]), and regions without
+    /// a minor update timestamp are placed at the end of minor update (see
+    /// [`estimate::minor_order`]). Empty regions add no nations to the
+    /// cumulative counts, but they still count as regions for
+    /// [`Model::Nonlinear`] and would otherwise appear in timesheets.
+    ///
+    /// [`estimate::update_order`]: crate::estimate::update_order
+    /// [`estimate::minor_order`]: crate::estimate::minor_order
+    /// [`Model::Nonlinear`]: crate::estimate::Model::Nonlinear
+    pub fn remove_empty_regions(&mut self) {
+        self.regions.retain(|region| region.population != Some(0));
+    }
+
+    /// Get the total number of nations across all regions in the dump.
+    pub(crate) fn total_population(&self) -> Result<i32> {
+        self.regions
+            .last()
+            .and_then(|region| {
+                region
+                    .population
+                    .zip(region.nations_before)
+                    .map(|(population, nations_before)| population + nations_before)
+            })
+            .ok_or_else(|| {
+                SrsglassError::InvalidDump("Could not find total world population".to_string())
+            })
+    }
+}
+
+/// Get the date of the most recent dump NS should have published, assuming
+/// dumps are available within two hours of major update starting at midnight
+/// Eastern time.
+pub fn expected_dump_date() -> NaiveDate {
+    let now = chrono::Utc::now().with_timezone(&Eastern) - chrono::Duration::hours(2);
+    now.date_naive() - Days::new(1)
+}
+
+/// Convert a region or nation name to the lowercase, underscored form NS uses
+/// in URLs and API responses. Names should be compared in this form, since
+/// NS is inconsistent about which form it returns.
+pub fn canonical_name(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "_")
+}
+
+/// Convert a region or nation name to the form NS displays it in, with spaces
+/// instead of underscores. Capitalization lost in the canonical form cannot be
+/// recovered.
+pub fn display_name(name: &str) -> String {
+    name.trim().replace('_', " ")
+}
+
+/// Get the names of the regions without a governor in a dump.
+pub(crate) fn governorless_in(regions: &[Region]) -> Vec<String> {
+    regions
+        .iter()
+        .filter(|region| region.governor.is_none())
+        .filter_map(|region| region.name.clone())
+        .collect()
+}
+
+/// Get the date NS will list a dump as in the archive, from when its first
+/// region updated. Regions founded since the last major update, which have
+/// not updated yet, are left out.
+pub(crate) fn compute_dump_date(regions: &[Region]) -> Result<NaiveDate> {
+    if regions.is_empty() {
+        return Err(SrsglassError::InvalidDump(
+            "Regions not populated!".to_string(),
+        ));
+    }
+
+    // Extract datetime of the first region's last major update
+    let Some(first_update) = regions
+        .iter()
+        .filter_map(|region| Update::Major.observed(region))
+        .min()
+    else {
+        return Err(SrsglassError::InvalidDump(
+            "Could not find major update timestamp!".to_string(),
+        ));
+    };
+
+    let Some(datetime) = chrono::DateTime::from_timestamp(first_update, 0) else {
+        return Err(SrsglassError::InvalidDump(
+            "Invalid date found in dump!".to_string(),
+        ));
+    };
+
+    // Rebase the timestamp in EST
+    let datetime = datetime.with_timezone(&Eastern);
+    let Some(datetime) = datetime.checked_sub_days(Days::new(1)) else {
+        return Err(SrsglassError::InvalidDump(
+            "Could not roll back one day!".to_string(),
+        ));
+    };
+
+    // After all that processing, return the naive date
+    Ok(datetime.date_naive())
+}
@@ -0,0 +1,242 @@
+//! OpenDocument spreadsheet timesheets, for LibreOffice and other office
+//! suites that read ODS natively. The spreadsheet is written by hand, as it
+//! only needs a single table of typed cells.
+
+use crate::html::{css_color, escape_html};
+use crate::rows::Row;
+use crate::{Column, Dump, LinkStyle, Result, TimesheetOptions};
+use chrono::DateTime;
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, Write},
+    path::Path,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+<manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+const CONTENT_START: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:xlink="http://www.w3.org/1999/xlink" office:version="1.2">
+"#;
+
+/// A cell's value, typed so that numbers, times, and booleans can be sorted
+/// and filtered as such.
+enum Value {
+    Empty,
+    Text,
+    Link(String),
+    Number(f64),
+    /// Seconds from the start of an update
+    Duration(f64),
+    Boolean(bool),
+    /// Unix timestamp
+    Date(i64),
+}
+
+/// Get the typed value of a column in a row, given the text shown for it.
+fn value(row: &Row, column: Column, text: &str) -> Value {
+    let number = |value: Option<f64>| value.map_or(Value::Empty, Value::Number);
+    let duration = |value: Option<f64>| value.map_or(Value::Empty, Value::Duration);
+    let link = |url: Option<&str>| url.map_or(Value::Empty, |url| Value::Link(url.to_string()));
+
+    match column {
+        Column::Link => Value::Link(row.link.clone()),
+        Column::Population => Value::Number(row.population.into()),
+        Column::WaMembers => number(row.wa_members.map(f64::from)),
+        Column::TotalNations => Value::Number(row.nations_before.into()),
+        Column::Progress => Value::Number(row.progress),
+        Column::Minor => Value::Duration(row.minor),
+        Column::Major => Value::Duration(row.major),
+        Column::MinorTrigger => Value::Duration(row.minor_trigger),
+        Column::MajorTrigger => Value::Duration(row.major_trigger),
+        Column::MinorRelative => number(row.minor_relative),
+        Column::MajorRelative => number(row.major_relative),
+        Column::MinorObserved => duration(row.minor_observed),
+        Column::MajorObserved => duration(row.major_observed),
+        Column::MinorResidual => number(row.minor_residual()),
+        Column::MajorResidual => number(row.major_residual()),
+        Column::MinorUncertainty => number(row.minor_uncertainty),
+        Column::MajorUncertainty => number(row.major_uncertainty),
+        Column::Updated => Value::Boolean(row.already_updated),
+        Column::DelegateVotes => Value::Number(row.delegate_votes.into()),
+        Column::DelegateEndorsements => Value::Number(row.delegate_endorsements.into()),
+        Column::EndorsementsNeeded => Value::Number(row.endorsements_needed.into()),
+        Column::HasGovernor => Value::Boolean(row.has_governor),
+        Column::Executive => Value::Boolean(row.delegate_exec),
+        Column::DelegateAppearance => Value::Boolean(row.delegate_authority.appearance),
+        Column::DelegateBorderControl => Value::Boolean(row.delegate_authority.border_control),
+        Column::DelegateCommunications => Value::Boolean(row.delegate_authority.communications),
+        Column::DelegateEmbassies => Value::Boolean(row.delegate_authority.embassies),
+        Column::DelegatePolls => Value::Boolean(row.delegate_authority.polls),
+        Column::Password => Value::Boolean(row.has_password),
+        Column::Occupied => Value::Boolean(row.is_occupied),
+        Column::New => Value::Boolean(row.is_new),
+        Column::Founded => row.founded.map_or(Value::Empty, Value::Date),
+        Column::EmbassyCount => Value::Number(row.embassies.len() as f64),
+        Column::HasFriendlyEmbassy => Value::Boolean(!row.friendly_embassies.is_empty()),
+        Column::Flag => link(row.flag),
+        Column::Banner => link(row.banner),
+        Column::TagSet(index) => Value::Boolean(row.in_tag_set(index)),
+        _ if text.is_empty() => Value::Empty,
+        _ => Value::Text,
+    }
+}
+
+/// Write a cell with its value and the text shown for it, styled with the
+/// given cell style if any.
+fn write_cell(content: &mut String, value: Value, text: &str, style: Option<&str>) {
+    let style = style
+        .map(|style| format!(" table:style-name=\"{}\"", style))
+        .unwrap_or_default();
+    let text = escape_html(text);
+
+    let (attributes, paragraph) = match value {
+        Value::Text => ("office:value-type=\"string\"".to_string(), text),
+        Value::Link(url) => (
+            "office:value-type=\"string\"".to_string(),
+            format!(
+                "<text:a xlink:type=\"simple\" xlink:href=\"{}\">{}</text:a>",
+                escape_html(&url),
+                text
+            ),
+        ),
+        Value::Number(number) => (
+            format!("office:value-type=\"float\" office:value=\"{}\"", number),
+            text,
+        ),
+        Value::Duration(seconds) => (
+            format!(
+                "office:value-type=\"time\" office:time-value=\"PT{}H{}M{:.3}S\"",
+                (seconds / 3600.0).floor(),
+                ((seconds / 60.0) % 60.0).floor(),
+                seconds % 60.0
+            ),
+            text,
+        ),
+        Value::Boolean(boolean) => (
+            format!(
+                "office:value-type=\"boolean\" office:boolean-value=\"{}\"",
+                boolean
+            ),
+            text,
+        ),
+        Value::Date(timestamp) => match DateTime::from_timestamp(timestamp, 0) {
+            Some(date) => (
+                format!(
+                    "office:value-type=\"date\" office:date-value=\"{}\"",
+                    date.format("%Y-%m-%dT%H:%M:%S")
+                ),
+                text,
+            ),
+            None => return write_cell(content, Value::Empty, "", None),
+        },
+        Value::Empty => {
+            content.push_str(&format!("<table:table-cell{}/>", style));
+            return;
+        }
+    };
+
+    content.push_str(&format!(
+        "<table:table-cell {}{}><text:p>{}</text:p></table:table-cell>",
+        attributes, style, paragraph
+    ));
+}
+
+impl Dump {
+    /// Write the timesheet to an OpenDocument spreadsheet, for LibreOffice.
+    /// Region names are colored by the color rules as in Excel timesheets.
+    pub fn to_ods(&self, output_file: impl AsRef<Path>, options: &TimesheetOptions) -> Result<()> {
+        options.check_precision()?;
+
+        write(self, BufWriter::new(File::create(output_file)?), options)
+    }
+}
+
+/// Write the timesheet as an OpenDocument spreadsheet.
+fn write(dump: &Dump, writer: impl Write + Seek, options: &TimesheetOptions) -> Result<()> {
+    let mut content = CONTENT_START.to_string();
+
+    // region names are colored by the first color rule they match, as in
+    // Excel timesheets
+    content.push_str("<office:automatic-styles>\n");
+    for (index, rule) in options.color_rules.iter().enumerate() {
+        content.push_str(&format!(
+            "<style:style style:name=\"rule{}\" style:family=\"table-cell\"><style:table-cell-properties fo:background-color=\"{}\"/></style:style>\n",
+            index,
+            css_color(rule.color)
+        ));
+    }
+    content.push_str("</office:automatic-styles>\n");
+
+    let name = if dump.incomplete {
+        "Timesheet (Incomplete)"
+    } else {
+        "Timesheet"
+    };
+    content.push_str(&format!(
+        "<office:body><office:spreadsheet><table:table table:name=\"{}\">\n",
+        name
+    ));
+
+    let columns = options.linked_columns();
+
+    content.push_str("<table:table-row>");
+    for &column in &columns {
+        write_cell(&mut content, Value::Text, options.header(column), None);
+    }
+    content.push_str("</table:table-row>\n");
+
+    for row in dump.rows(options)? {
+        let color = options
+            .color_rules
+            .iter()
+            .position(|rule| rule.matches(&row))
+            .map(|index| format!("rule{}", index));
+
+        content.push_str("<table:table-row>");
+        for &column in &columns {
+            let text = row.text(column, options.timestamp_precision);
+            let (value, style) = match column {
+                Column::Region if options.link_style == LinkStyle::Name => {
+                    (Value::Link(row.link.clone()), color.as_deref())
+                }
+                Column::Region => (value(&row, column, &text), color.as_deref()),
+                _ => (value(&row, column, &text), None),
+            };
+
+            write_cell(&mut content, value, &text, style);
+        }
+        content.push_str("</table:table-row>\n");
+    }
+
+    content.push_str(
+        "</table:table></office:spreadsheet></office:body>\n</office:document-content>\n",
+    );
+
+    let mut zip = ZipWriter::new(writer);
+
+    // the mimetype must come first and be stored uncompressed, so that the
+    // format can be recognized from the start of the file
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+
+    zip.start_file("META-INF/manifest.xml", SimpleFileOptions::default())?;
+    zip.write_all(MANIFEST.as_bytes())?;
+
+    zip.start_file("content.xml", SimpleFileOptions::default())?;
+    zip.write_all(content.as_bytes())?;
+
+    zip.finish()?;
+
+    Ok(())
+}
@@ -0,0 +1,526 @@
+//! Settings for timesheets and the columns of their region table.
+
+use crate::estimate::{Anchor, Model, Update};
+use crate::{ColorRule, Result, SrsglassError, Styling};
+use chrono::{DateTime, Utc};
+use chrono_tz::{Tz, US::Eastern};
+use std::str::FromStr;
+
+/// A column of the region table in CSV, HTML, ODS, and Excel timesheets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    Region,
+    Link,
+    Population,
+    /// Number of nations in the World Assembly, which needs the nations dump
+    WaMembers,
+    TotalNations,
+    Progress,
+    Minor,
+    Major,
+    /// Time to be in position for minor update,
+    /// [`TimesheetOptions::trigger_offset`] before the estimate
+    MinorTrigger,
+    /// Time to be in position for major update,
+    /// [`TimesheetOptions::trigger_offset`] before the estimate
+    MajorTrigger,
+    /// Estimated wall-clock time of minor update, in
+    /// [`TimesheetOptions::timezone`]
+    MinorTime,
+    /// Estimated wall-clock time of major update, in
+    /// [`TimesheetOptions::timezone`]
+    MajorTime,
+    /// Whether the region updates early, in the middle of, or late in minor
+    /// update, by [`TimesheetOptions::early_window`] and
+    /// [`TimesheetOptions::late_window`]
+    MinorWindow,
+    /// Whether the region updates early, in the middle of, or late in major
+    /// update, by [`TimesheetOptions::early_window`] and
+    /// [`TimesheetOptions::late_window`]
+    MajorWindow,
+    /// Seconds between minor update reaching
+    /// [`TimesheetOptions::relative_to`] and the region
+    MinorRelative,
+    /// Seconds between major update reaching
+    /// [`TimesheetOptions::relative_to`] and the region
+    MajorRelative,
+    /// When the region updated in the last minor update, in seconds from
+    /// the start of the update
+    MinorObserved,
+    /// When the region updated in the last major update, in seconds from
+    /// the start of the update
+    MajorObserved,
+    /// Which of the last major and minor update the region has no observed
+    /// update time from, in which case its estimate for that update is only
+    /// assumed
+    Missing,
+    /// Seconds the region updated after its estimated minor update time in
+    /// the last minor update, or before if negative
+    MinorResidual,
+    /// Seconds the region updated after its estimated major update time in
+    /// the last major update, or before if negative
+    MajorResidual,
+    /// Typical error of the minor update estimate, in seconds, from how far
+    /// regions strayed from their estimates around the same point in the
+    /// last minor update
+    MinorUncertainty,
+    /// Typical error of the major update estimate, in seconds, from how far
+    /// regions strayed from their estimates around the same point in the
+    /// last major update
+    MajorUncertainty,
+    /// Whether the region has presumably updated already in the update in
+    /// progress, given [`TimesheetOptions::update_started`]
+    Updated,
+    Delegate,
+    DelegateVotes,
+    DelegateEndorsements,
+    /// Endorsements a nation needs to have more than the delegate, such as
+    /// to take the delegacy in a liberation
+    EndorsementsNeeded,
+    HasGovernor,
+    Governor,
+    Executive,
+    /// Whether the delegate has appearance authority
+    DelegateAppearance,
+    /// Whether the delegate has border control authority, and so can eject
+    /// and ban nations
+    DelegateBorderControl,
+    /// Whether the delegate has communications authority
+    DelegateCommunications,
+    /// Whether the delegate has embassies authority
+    DelegateEmbassies,
+    /// Whether the delegate has polls authority
+    DelegatePolls,
+    Password,
+    /// Whether the region is presumably held by raiders: it has a password
+    /// and is tagged Invader but not Defender
+    Occupied,
+    Type,
+    New,
+    /// When the region was founded or last refounded, to tell refounds and
+    /// fresh frontiers apart from long-standing regions
+    Founded,
+    /// Number of established embassies
+    EmbassyCount,
+    Embassies,
+    /// Whether the region has an embassy with any region in
+    /// [`TimesheetOptions::friendly`]
+    HasFriendlyEmbassy,
+    FriendlyEmbassies,
+    EnemyEmbassies,
+    Factbook,
+    /// URL of the region's flag
+    Flag,
+    /// URL of the region's banner
+    Banner,
+    /// Whether the region is in the set at this index of
+    /// [`TimesheetOptions::tag_sets`], headed by the set's name. Not selected
+    /// by [`Column::from_str`]; add one for each tag set instead.
+    TagSet(usize),
+}
+
+impl Column {
+    /// Every column, in the default order.
+    pub const ALL: [Column; 49] = [
+        Column::Region,
+        Column::Link,
+        Column::Population,
+        Column::WaMembers,
+        Column::TotalNations,
+        Column::Progress,
+        Column::Minor,
+        Column::Major,
+        Column::MinorTrigger,
+        Column::MajorTrigger,
+        Column::MinorTime,
+        Column::MajorTime,
+        Column::MinorWindow,
+        Column::MajorWindow,
+        Column::MinorRelative,
+        Column::MajorRelative,
+        Column::MinorObserved,
+        Column::MajorObserved,
+        Column::Missing,
+        Column::MinorResidual,
+        Column::MajorResidual,
+        Column::MinorUncertainty,
+        Column::MajorUncertainty,
+        Column::Updated,
+        Column::Delegate,
+        Column::DelegateVotes,
+        Column::DelegateEndorsements,
+        Column::EndorsementsNeeded,
+        Column::HasGovernor,
+        Column::Governor,
+        Column::Executive,
+        Column::DelegateAppearance,
+        Column::DelegateBorderControl,
+        Column::DelegateCommunications,
+        Column::DelegateEmbassies,
+        Column::DelegatePolls,
+        Column::Password,
+        Column::Occupied,
+        Column::Type,
+        Column::New,
+        Column::Founded,
+        Column::EmbassyCount,
+        Column::Embassies,
+        Column::HasFriendlyEmbassy,
+        Column::FriendlyEmbassies,
+        Column::EnemyEmbassies,
+        Column::Factbook,
+        Column::Flag,
+        Column::Banner,
+    ];
+
+    /// The columns included in timesheets by default. The rest need the
+    /// nations dump or are only wanted for some sheets, such as trigger times.
+    pub const DEFAULT: [Column; 21] = [
+        Column::Region,
+        Column::Link,
+        Column::Population,
+        Column::TotalNations,
+        Column::Progress,
+        Column::Minor,
+        Column::Major,
+        Column::Delegate,
+        Column::DelegateVotes,
+        Column::DelegateEndorsements,
+        Column::HasGovernor,
+        Column::Governor,
+        Column::Executive,
+        Column::Password,
+        Column::Type,
+        Column::New,
+        Column::EmbassyCount,
+        Column::Embassies,
+        Column::FriendlyEmbassies,
+        Column::EnemyEmbassies,
+        Column::Factbook,
+    ];
+
+    /// Get the column's header in timesheets.
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Region => "Region",
+            Column::Link => "Link",
+            Column::Population => "Population",
+            Column::WaMembers => "WA Members",
+            Column::TotalNations => "Total Nations",
+            Column::Progress => "Progress",
+            Column::Minor => "Minor",
+            Column::Major => "Major",
+            Column::MinorTrigger => "Minor Trigger At",
+            Column::MajorTrigger => "Major Trigger At",
+            Column::MinorTime => "Minor Time",
+            Column::MajorTime => "Major Time",
+            Column::MinorWindow => "Minor Window",
+            Column::MajorWindow => "Major Window",
+            Column::MinorRelative => "Minor Relative",
+            Column::MajorRelative => "Major Relative",
+            Column::MinorObserved => "Minor Observed",
+            Column::MajorObserved => "Major Observed",
+            Column::Missing => "Missing Timestamps",
+            Column::MinorResidual => "Minor Residual",
+            Column::MajorResidual => "Major Residual",
+            Column::MinorUncertainty => "Minor ±",
+            Column::MajorUncertainty => "Major ±",
+            Column::Updated => "Updated",
+            Column::Delegate => "Delegate",
+            Column::DelegateVotes => "Del. Votes",
+            Column::DelegateEndorsements => "Del. Endos",
+            Column::EndorsementsNeeded => "Endos to Beat",
+            Column::HasGovernor => "Has Governor",
+            Column::Governor => "Governor",
+            Column::Executive => "Exec. Delegate",
+            Column::DelegateAppearance => "Del. Appearance",
+            Column::DelegateBorderControl => "Del. Border Control",
+            Column::DelegateCommunications => "Del. Communications",
+            Column::DelegateEmbassies => "Del. Embassies",
+            Column::DelegatePolls => "Del. Polls",
+            Column::Password => "Password",
+            Column::Occupied => "Occupied",
+            Column::Type => "Type",
+            Column::New => "New",
+            Column::Founded => "Founded",
+            Column::EmbassyCount => "Embassy Count",
+            Column::Embassies => "Embassies",
+            Column::HasFriendlyEmbassy => "Has Friendly Embassy",
+            Column::FriendlyEmbassies => "Friendly Embassies",
+            Column::EnemyEmbassies => "Enemy Embassies",
+            Column::Factbook => "WFE",
+            Column::Flag => "Flag",
+            Column::Banner => "Banner",
+            Column::TagSet(_) => "Tag Set",
+        }
+    }
+
+    /// Get the name used to select the column, as accepted by
+    /// [`Column::from_str`].
+    pub fn key(self) -> &'static str {
+        match self {
+            Column::Region => "region",
+            Column::Link => "link",
+            Column::Population => "population",
+            Column::WaMembers => "wa-members",
+            Column::TotalNations => "nations",
+            Column::Progress => "progress",
+            Column::Minor => "minor",
+            Column::Major => "major",
+            Column::MinorTrigger => "minor-trigger",
+            Column::MajorTrigger => "major-trigger",
+            Column::MinorTime => "minor-time",
+            Column::MajorTime => "major-time",
+            Column::MinorWindow => "minor-window",
+            Column::MajorWindow => "major-window",
+            Column::MinorRelative => "minor-relative",
+            Column::MajorRelative => "major-relative",
+            Column::MinorObserved => "minor-observed",
+            Column::MajorObserved => "major-observed",
+            Column::Missing => "missing",
+            Column::MinorResidual => "minor-residual",
+            Column::MajorResidual => "major-residual",
+            Column::MinorUncertainty => "minor-uncertainty",
+            Column::MajorUncertainty => "major-uncertainty",
+            Column::Updated => "updated",
+            Column::Delegate => "delegate",
+            Column::DelegateVotes => "votes",
+            Column::DelegateEndorsements => "endos",
+            Column::EndorsementsNeeded => "endos-needed",
+            Column::HasGovernor => "has-governor",
+            Column::Governor => "governor",
+            Column::Executive => "exec",
+            Column::DelegateAppearance => "appearance",
+            Column::DelegateBorderControl => "border-control",
+            Column::DelegateCommunications => "communications",
+            Column::DelegateEmbassies => "embassy-authority",
+            Column::DelegatePolls => "polls",
+            Column::Password => "password",
+            Column::Occupied => "occupied",
+            Column::Type => "type",
+            Column::New => "new",
+            Column::Founded => "founded",
+            Column::EmbassyCount => "embassy-count",
+            Column::Embassies => "embassies",
+            Column::HasFriendlyEmbassy => "has-friendly",
+            Column::FriendlyEmbassies => "friendly",
+            Column::EnemyEmbassies => "enemy",
+            Column::Factbook => "wfe",
+            Column::Flag => "flag",
+            Column::Banner => "banner",
+            Column::TagSet(_) => "tag-set",
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = SrsglassError;
+
+    /// Parse a column from its [`Column::key`], such as `minor` or `wfe`.
+    fn from_str(s: &str) -> Result<Self> {
+        let key = s.trim().to_lowercase();
+
+        Column::ALL
+            .into_iter()
+            .find(|column| column.key() == key)
+            .ok_or_else(|| {
+                let keys: Vec<_> = Column::ALL.into_iter().map(Column::key).collect();
+                SrsglassError::InvalidOption(format!(
+                    "Unknown column {}; expected one of {}",
+                    s,
+                    keys.join(", ")
+                ))
+            })
+    }
+}
+
+/// How timesheets link to each region's page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LinkStyle {
+    /// Link to each region in a separate Link column.
+    #[default]
+    Column,
+    /// Link each region's name instead of adding a Link column, in HTML, ODS,
+    /// and Excel timesheets. CSV timesheets, which can't hold links, keep the
+    /// column.
+    Name,
+    /// Link to each region in a separate Link column, with the site's
+    /// styling turned off so that pages load faster.
+    Template,
+}
+
+impl LinkStyle {
+    /// Get the link to a region's page, given its canonical name.
+    pub(crate) fn url(self, canonical: &str) -> String {
+        match self {
+            LinkStyle::Template => format!(
+                "https://www.nationstates.net/template-overall=none/region={}",
+                canonical
+            ),
+            _ => format!("https://www.nationstates.net/region={}", canonical),
+        }
+    }
+}
+
+/// A named set of regions, such as the regions matching a tag query, shown
+/// as a [`Column::TagSet`] column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagSet {
+    pub name: String,
+    pub regions: Vec<String>,
+}
+
+/// Settings for estimating update times and writing timesheets.
+#[derive(Clone, Debug)]
+pub struct TimesheetOptions {
+    /// Length of major update, in seconds
+    pub major_length: i32,
+    /// Length of minor update, in seconds
+    pub minor_length: i32,
+    /// The number of milliseconds to use in timestamps, from 0 to 3
+    pub timestamp_precision: i32,
+    /// Model used to estimate update times
+    pub model: Model,
+    /// Observed update times of regions early in the update in progress, to
+    /// rescale the estimates for the rest of that update to its actual speed
+    pub anchors: Vec<Anchor>,
+    /// The update in progress, which the anchors and
+    /// [`TimesheetOptions::update_started`] were observed in
+    pub anchor_update: Update,
+    /// When the update in progress was seen to start. Regions estimated to
+    /// update in it before [`TimesheetOptions::now`] are marked as already
+    /// updated, for timesheets generated mid-update.
+    pub update_started: Option<DateTime<Utc>>,
+    /// Time to mark regions as already updated by, or the current time if not
+    /// given
+    pub now: Option<DateTime<Utc>>,
+    /// Seconds before each estimated update time to show in the
+    /// [`Column::MinorTrigger`] and [`Column::MajorTrigger`] columns, as a
+    /// cushion for being in position before the region updates
+    pub trigger_offset: f64,
+    /// Region to measure the [`Column::MinorRelative`] and
+    /// [`Column::MajorRelative`] columns from, such as a target
+    pub relative_to: Option<String>,
+    /// Seconds from the start of each update that count as early in the
+    /// [`Column::MinorWindow`] and [`Column::MajorWindow`] columns
+    pub early_window: f64,
+    /// Seconds before the end of each update that count as late in the
+    /// [`Column::MinorWindow`] and [`Column::MajorWindow`] columns
+    pub late_window: f64,
+    /// Time zone of the wall-clock update times in the
+    /// [`Column::MinorTime`] and [`Column::MajorTime`] columns
+    pub timezone: Tz,
+    /// Write update times as `H:MM:SS` strings, as Spyglass does, rather than
+    /// as Excel datetimes
+    pub spyglass_times: bool,
+    /// Rules for coloring region names in Excel timesheets
+    pub color_rules: Vec<ColorRule>,
+    /// Colors for highlighting cells other than region names
+    pub styling: Styling,
+    /// Keep the header row of Excel timesheets visible when scrolling down
+    pub freeze_header_row: bool,
+    /// Keep the first column of Excel timesheets, the region names by
+    /// default, visible when scrolling across
+    pub freeze_first_column: bool,
+    /// Add a worksheet to Excel timesheets listing only passworded regions,
+    /// for watching passworded occupations
+    pub passworded_sheet: bool,
+    /// Add a worksheet to Excel timesheets charting how many nations had
+    /// updated over the course of the last major and minor updates
+    pub update_chart: bool,
+    /// Add a worksheet to Excel timesheets with the rest of any factbook or
+    /// embassy list too long to fit in a cell, by region
+    pub overflow_sheet: bool,
+    /// Only include these regions in the timesheet, or every region if
+    /// `None`. A filter that matches no regions leaves the timesheet empty.
+    pub filter: Option<Vec<String>>,
+    /// Number of regions updating before and after each filtered region to
+    /// include as well
+    pub filter_context: usize,
+    /// Regions whose embassies are highlighted as friendly, such as jump
+    /// points
+    pub friendly: Vec<String>,
+    /// Regions whose embassies are highlighted as hostile, such as known
+    /// raider headquarters
+    pub enemy: Vec<String>,
+    /// Columns of the region table, in order, in CSV, HTML, ODS, and Excel
+    /// timesheets. Other formats always include every column, and Excel
+    /// timesheets add any columns the color rules depend on.
+    pub columns: Vec<Column>,
+    /// Sets of regions that [`Column::TagSet`] columns check regions against
+    pub tag_sets: Vec<TagSet>,
+    /// How timesheets link to each region's page
+    pub link_style: LinkStyle,
+}
+
+impl Default for TimesheetOptions {
+    fn default() -> Self {
+        Self {
+            major_length: 5350,
+            minor_length: 3550,
+            timestamp_precision: 0,
+            model: Model::default(),
+            anchors: Vec::new(),
+            anchor_update: Update::Major,
+            update_started: None,
+            now: None,
+            trigger_offset: 0.0,
+            relative_to: None,
+            early_window: 600.0,
+            late_window: 600.0,
+            timezone: Eastern,
+            spyglass_times: false,
+            color_rules: ColorRule::defaults(),
+            styling: Styling::default(),
+            freeze_header_row: true,
+            freeze_first_column: true,
+            passworded_sheet: false,
+            update_chart: false,
+            overflow_sheet: false,
+            filter: None,
+            filter_context: 0,
+            friendly: Vec::new(),
+            enemy: Vec::new(),
+            columns: Column::DEFAULT.to_vec(),
+            tag_sets: Vec::new(),
+            link_style: LinkStyle::default(),
+        }
+    }
+}
+
+impl TimesheetOptions {
+    /// Get the header of a column, which for [`Column::TagSet`] columns is
+    /// the name of their tag set.
+    pub fn header(&self, column: Column) -> &str {
+        match column {
+            Column::TagSet(index) => self
+                .tag_sets
+                .get(index)
+                .map_or(column.header(), |tag_set| tag_set.name.as_str()),
+            _ => column.header(),
+        }
+    }
+
+    /// Get the columns of the region table in timesheets that can link region
+    /// names, which leave out [`Column::Link`] with [`LinkStyle::Name`].
+    pub(crate) fn linked_columns(&self) -> Vec<Column> {
+        self.columns
+            .iter()
+            .copied()
+            .filter(|&column| !(self.link_style == LinkStyle::Name && column == Column::Link))
+            .collect()
+    }
+
+    pub(crate) fn check_precision(&self) -> Result<()> {
+        // excel only supports up to 3 milliseconds of precision
+        if !(0..=3).contains(&self.timestamp_precision) {
+            return Err(SrsglassError::InvalidOption(
+                "timestamp_precision must be between 0 and 3".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
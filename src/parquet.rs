@@ -0,0 +1,108 @@
+//! Parquet timesheets.
+
+use crate::rows::Row;
+use crate::{Dump, Result, TimesheetOptions};
+use arrow_array::{
+    builder::{ListBuilder, StringBuilder},
+    ArrayRef, BooleanArray, Float64Array, Int32Array, RecordBatch, StringArray,
+};
+use parquet::arrow::ArrowWriter;
+use std::{collections::HashMap, fs::File, path::Path, sync::Arc};
+
+impl Dump {
+    /// Write the timesheet to a Parquet file, with embassies as a list column
+    /// and the world data in the file's metadata. Update times are given as
+    /// seconds from the start of the update.
+    pub fn to_parquet(
+        &self,
+        output_file: impl AsRef<Path>,
+        options: &TimesheetOptions,
+    ) -> Result<()> {
+        let total_population = self.total_population()?;
+        let rows = self.rows(options)?;
+
+        let strings = |value: for<'r> fn(&'r Row<'r>) -> &'r str| -> ArrayRef {
+            Arc::new(StringArray::from_iter_values(rows.iter().map(value)))
+        };
+        let integers = |value: fn(&Row) -> i32| -> ArrayRef {
+            Arc::new(Int32Array::from_iter_values(rows.iter().map(value)))
+        };
+        let floats = |value: fn(&Row) -> f64| -> ArrayRef {
+            Arc::new(Float64Array::from_iter_values(rows.iter().map(value)))
+        };
+        let booleans = |value: fn(&Row) -> bool| -> ArrayRef {
+            Arc::new(BooleanArray::from_iter(
+                rows.iter().map(|row| Some(value(row))),
+            ))
+        };
+
+        let mut embassies = ListBuilder::new(StringBuilder::new());
+        for row in &rows {
+            for embassy in &row.embassies {
+                embassies.values().append_value(embassy);
+            }
+            embassies.append(true);
+        }
+
+        let batch = RecordBatch::try_from_iter([
+            ("name", strings(|row| row.name)),
+            ("link", strings(|row| &row.link)),
+            ("population", integers(|row| row.population)),
+            ("nations_before", integers(|row| row.nations_before)),
+            ("progress", floats(|row| row.progress)),
+            ("minor", floats(|row| row.minor)),
+            ("major", floats(|row| row.major)),
+            (
+                "delegate",
+                Arc::new(StringArray::from_iter(rows.iter().map(|row| row.delegate))),
+            ),
+            ("delegate_votes", integers(|row| row.delegate_votes)),
+            (
+                "delegate_endorsements",
+                integers(|row| row.delegate_endorsements),
+            ),
+            ("has_governor", booleans(|row| row.has_governor)),
+            (
+                "governor",
+                Arc::new(StringArray::from_iter(rows.iter().map(|row| row.governor))),
+            ),
+            ("delegate_exec", booleans(|row| row.delegate_exec)),
+            ("has_password", booleans(|row| row.has_password)),
+            ("frontier", booleans(|row| row.is_frontier)),
+            ("new", booleans(|row| row.is_new)),
+            ("embassies", Arc::new(embassies.finish()) as ArrayRef),
+            ("factbook", strings(|row| row.factbook)),
+        ])?;
+
+        let metadata = HashMap::from([
+            ("nations".to_string(), total_population.to_string()),
+            ("major_length".to_string(), options.major_length.to_string()),
+            ("minor_length".to_string(), options.minor_length.to_string()),
+            ("version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+            (
+                "date_generated".to_string(),
+                chrono::Utc::now().date_naive().to_string(),
+            ),
+            ("dump_date".to_string(), self.dump_date.to_string()),
+            (
+                "last_update".to_string(),
+                self.last_update()
+                    .map(|time| time.to_rfc3339())
+                    .unwrap_or_default(),
+            ),
+            ("incomplete".to_string(), self.incomplete.to_string()),
+            (
+                "dump_sha256".to_string(),
+                self.sha256.clone().unwrap_or_default(),
+            ),
+        ]);
+        let schema = Arc::new(batch.schema().as_ref().clone().with_metadata(metadata));
+        let batch = batch.with_schema(schema.clone())?;
+
+        let mut writer = ArrowWriter::try_new(File::create(output_file)?, schema, None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,606 @@
+//! Streaming parsers for region and nation data dumps and API responses.
+
+use crate::client::ProgressHook;
+use crate::{
+    DelegateAuthority, Embassy, EmbassyStatus, Nation, Progress, Region, Result, SrsglassError,
+};
+use flate2::read::GzDecoder;
+use log::warn;
+use quick_xml::{events::Event, Reader};
+use sha2::{Digest, Sha256};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    thread,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver};
+
+/// An element of a region in the regions dump whose text is read.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RegionField {
+    Name,
+    Factbook,
+    NumNations,
+    Delegate,
+    DelegateVotes,
+    DelegateAuth,
+    Governor,
+    LastMajorUpdate,
+    LastMinorUpdate,
+    FoundedTime,
+    Embassy,
+    Flag,
+    Banner,
+}
+
+impl RegionField {
+    fn from_tag(tag: &[u8]) -> Option<Self> {
+        match tag {
+            b"NAME" => Some(Self::Name),
+            b"FACTBOOK" => Some(Self::Factbook),
+            b"NUMNATIONS" => Some(Self::NumNations),
+            b"DELEGATE" => Some(Self::Delegate),
+            b"DELEGATEVOTES" => Some(Self::DelegateVotes),
+            b"DELEGATEAUTH" => Some(Self::DelegateAuth),
+            b"GOVERNOR" => Some(Self::Governor),
+            b"LASTMAJORUPDATE" => Some(Self::LastMajorUpdate),
+            b"LASTMINORUPDATE" => Some(Self::LastMinorUpdate),
+            b"FOUNDEDTIME" => Some(Self::FoundedTime),
+            b"EMBASSY" => Some(Self::Embassy),
+            b"FLAG" => Some(Self::Flag),
+            b"BANNERURL" => Some(Self::Banner),
+            _ => None,
+        }
+    }
+}
+
+/// Reader that reports how many bytes have been read from the inner reader.
+pub(crate) struct ProgressReader<'a, R> {
+    pub(crate) inner: R,
+    pub(crate) bytes: u64,
+    pub(crate) total: Option<u64>,
+    pub(crate) progress: Option<&'a ProgressHook>,
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+
+        if let Some(progress) = self.progress {
+            progress(Progress::Read {
+                bytes: self.bytes,
+                total: self.total,
+            });
+        }
+
+        Ok(read)
+    }
+}
+
+/// Reader that copies everything read from the inner reader to a writer.
+pub(crate) struct TeeReader<R, W: Write> {
+    pub(crate) inner: R,
+    pub(crate) writer: W,
+}
+
+impl<R: Read, W: Write> Read for TeeReader<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+
+        if read == 0 {
+            self.writer.flush()?;
+        } else {
+            self.writer.write_all(&buf[..read])?;
+        }
+
+        Ok(read)
+    }
+}
+
+/// Reader that passes everything read from the inner reader to a SHA-256
+/// hasher, so that a dump's checksum is taken as it is read.
+pub(crate) struct HashReader<'h, R> {
+    pub(crate) inner: R,
+    pub(crate) hasher: &'h mut Sha256,
+}
+
+impl<R: Read> Read for HashReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+
+        Ok(read)
+    }
+}
+
+/// Regions read from a regions dump, before the rest of a [`Dump`] is filled
+/// in.
+///
+/// [`Dump`]: crate::Dump
+pub(crate) struct ParsedDump {
+    pub(crate) regions: Vec<Region>,
+    /// Whether the dump ended early
+    pub(crate) incomplete: bool,
+    /// SHA-256 of the dump as it was read, as a hex string
+    pub(crate) sha256: String,
+}
+
+/// Reader over chunks of data sent from another thread.
+#[cfg(not(target_arch = "wasm32"))]
+struct ChannelReader {
+    receiver: Receiver<std::io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk?;
+                    self.position = 0;
+                }
+                // the sender is dropped once everything has been sent
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let read = (&self.chunk[self.position..]).read(buf)?;
+        self.position += read;
+
+        Ok(read)
+    }
+}
+
+/// Decompress a dump if it is gzipped, passing through dumps that have
+/// already been decompressed, such as a `regions.xml` extracted by hand.
+fn decompressed<'a>(dump: impl Read + Send + 'a) -> Result<Box<dyn Read + Send + 'a>> {
+    let mut dump = BufReader::new(dump);
+
+    // gzip streams always start with these magic bytes, and XML never does
+    if dump.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(GzDecoder::new(dump)))
+    } else {
+        Ok(Box::new(dump))
+    }
+}
+
+/// Decompress a dump on a separate thread while `parse` reads the
+/// decompressed data, so that decompressing and parsing the dump overlap.
+#[cfg(not(target_arch = "wasm32"))]
+fn with_decompressed<T>(
+    dump: impl Read + Send,
+    parse: impl FnOnce(BufReader<ChannelReader>) -> Result<T>,
+) -> Result<T> {
+    // size of each chunk of decompressed data, and number of chunks to buffer
+    const CHUNK_SIZE: usize = 256 * 1024;
+    const CHUNKS: usize = 16;
+
+    let mut decoder = decompressed(dump)?;
+    let (sender, receiver) = mpsc::sync_channel(CHUNKS);
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            loop {
+                let mut chunk = vec![0; CHUNK_SIZE];
+
+                let message = match decoder.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(read) => {
+                        chunk.truncate(read);
+                        Ok(chunk)
+                    }
+                    Err(error) => Err(error),
+                };
+
+                let failed = message.is_err();
+
+                // stop if the parser has stopped reading, or after an error
+                if sender.send(message).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        parse(BufReader::new(ChannelReader {
+            receiver,
+            chunk: Vec::new(),
+            position: 0,
+        }))
+    })
+}
+
+/// Decompress a dump as `parse` reads it, since wasm has no threads to
+/// decompress on.
+#[cfg(target_arch = "wasm32")]
+fn with_decompressed<'a, T>(
+    dump: impl Read + Send + 'a,
+    parse: impl FnOnce(BufReader<Box<dyn Read + Send + 'a>>) -> Result<T>,
+) -> Result<T> {
+    parse(BufReader::new(decompressed(dump)?))
+}
+
+/// How a regions dump is read, as set on a [`Client`](crate::Client).
+#[derive(Clone)]
+pub(crate) struct ParseOptions {
+    /// Whether to read each region's factbook
+    pub(crate) factbooks: bool,
+    /// Whether to read each region's embassies
+    pub(crate) embassies: bool,
+    /// Whether to keep the regions read from a dump that ends early
+    pub(crate) allow_partial: bool,
+    /// Hook to report the regions parsed so far to, if any
+    pub(crate) progress: Option<ProgressHook>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            factbooks: true,
+            embassies: true,
+            allow_partial: false,
+            progress: None,
+        }
+    }
+}
+
+/// Parse a regions dump, returning the regions along with whether the
+/// dump ended early and its checksum.
+pub(crate) fn parse_dump(dump: impl Read + Send, options: &ParseOptions) -> Result<ParsedDump> {
+    let mut regions = Vec::new();
+    let mut hasher = Sha256::new();
+
+    let dump = HashReader {
+        inner: dump,
+        hasher: &mut hasher,
+    };
+    let result = stream_dump(dump, options, |region| {
+        regions.push(region);
+        Ok(())
+    });
+    let incomplete = keep_partial(result, options.allow_partial)?;
+
+    Ok(ParsedDump {
+        regions,
+        incomplete,
+        sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+/// Check the result of reading a dump, keeping what was read of a dump
+/// that ended early if partial dumps are allowed. Returns whether the
+/// dump ended early.
+pub(crate) fn keep_partial(result: Result<()>, allow_partial: bool) -> Result<bool> {
+    match result {
+        Ok(()) => Ok(false),
+        Err(error @ SrsglassError::TruncatedDump { .. }) if allow_partial => {
+            warn!("{}; keeping the regions read before then", error);
+            Ok(true)
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Parse a regions dump, passing each region to `on_region` as soon as it
+/// has been read.
+pub(crate) fn stream_dump(
+    dump: impl Read + Send,
+    options: &ParseOptions,
+    on_region: impl FnMut(Region) -> Result<()>,
+) -> Result<()> {
+    with_decompressed(dump, |dump| parse_regions(dump, options, on_region))
+}
+
+/// Parse decompressed regions dump XML, passing each region to
+/// `on_region` as soon as it has been read.
+fn parse_regions(
+    dump: impl BufRead,
+    options: &ParseOptions,
+    mut on_region: impl FnMut(Region) -> Result<()>,
+) -> Result<()> {
+    let mut reader = Reader::from_reader(dump);
+
+    let mut buf = Vec::new();
+    let mut skip_buf = Vec::new();
+
+    // the field whose text is being read, if any; every field is a leaf
+    // element, so it ends at the next end tag
+    let mut current_field = None;
+    let mut current_region = Region::default();
+    let mut embassy_status = EmbassyStatus::Established;
+
+    let mut current_population = 0;
+    let mut region_count = 0;
+    let mut finished = false;
+
+    // errors reading the XML itself mean the dump is truncated or corrupt,
+    // so report how far it got
+    let truncated = |region_count, error: quick_xml::Error| SrsglassError::TruncatedDump {
+        regions: region_count,
+        source: Some(Box::new(error)),
+    };
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|error| truncated(region_count, error))?;
+
+        match event {
+            Event::Start(e) => {
+                let skip = match e.name().as_ref() {
+                    b"FACTBOOK" => !options.factbooks,
+                    b"EMBASSIES" => !options.embassies,
+                    _ => false,
+                };
+
+                if skip {
+                    reader
+                        .read_to_end_into(e.name(), &mut skip_buf)
+                        .map_err(|error| truncated(region_count, error))?;
+                    skip_buf.clear();
+                } else {
+                    current_field = RegionField::from_tag(e.name().as_ref());
+
+                    if current_field == Some(RegionField::Embassy) {
+                        let embassy_type = e
+                            .try_get_attribute("type")?
+                            .map(|attribute| attribute.unescape_value())
+                            .transpose()?;
+                        embassy_status = EmbassyStatus::from_type(embassy_type.as_deref());
+                    }
+                }
+            }
+            Event::End(e) => {
+                current_field = None;
+
+                if e.name().as_ref() == b"REGIONS" {
+                    finished = true;
+                } else if e.name().as_ref() == b"REGION" {
+                    current_region.nations_before = Some(current_population);
+
+                    if let Some(population) = current_region.population {
+                        current_population += population;
+                    }
+
+                    on_region(std::mem::take(&mut current_region))?;
+
+                    region_count += 1;
+                    if let Some(progress) = &options.progress {
+                        progress(Progress::Parsed {
+                            regions: region_count,
+                        });
+                    }
+                }
+            }
+            Event::Text(e) => {
+                // text is only copied into an owned string for fields
+                // kept as strings
+                match current_field {
+                    Some(RegionField::Name) => {
+                        current_region.name = Some(e.unescape()?.into_owned())
+                    }
+                    Some(RegionField::NumNations) => {
+                        current_region.population = Some(e.unescape()?.parse()?)
+                    }
+                    Some(RegionField::Delegate) => {
+                        let delegate = e.unescape()?;
+                        if delegate != "0" {
+                            current_region.delegate = Some(delegate.into_owned());
+                        }
+                    }
+                    Some(RegionField::DelegateVotes) => {
+                        current_region.delegate_votes = Some(e.unescape()?.parse()?);
+                    }
+                    Some(RegionField::DelegateAuth) => {
+                        let authority = DelegateAuthority::from_codes(&e);
+                        current_region.delegate_exec = Some(authority.executive);
+                        current_region.delegate_authority = Some(authority);
+                    }
+                    Some(RegionField::Governor) => {
+                        let governor = e.unescape()?;
+                        if governor != "0" {
+                            current_region.governor = Some(governor.into_owned());
+                        }
+                    }
+                    Some(RegionField::LastMajorUpdate) => {
+                        current_region.last_major = Some(e.unescape()?.parse()?);
+                    }
+                    Some(RegionField::LastMinorUpdate) => {
+                        current_region.last_minor = Some(e.unescape()?.parse()?);
+                    }
+                    Some(RegionField::FoundedTime) => {
+                        // regions founded before founding times were
+                        // recorded are given as founded at 0
+                        let founded: i64 = e.unescape()?.parse()?;
+                        current_region.founded = (founded > 0).then_some(founded);
+                    }
+                    Some(RegionField::Embassy) => current_region.embassies.push(Embassy {
+                        region: e.unescape()?.into_owned(),
+                        status: embassy_status,
+                    }),
+                    Some(RegionField::Flag) => {
+                        let flag = e.unescape()?;
+                        if !flag.trim().is_empty() {
+                            current_region.flag = Some(absolute_url(flag.trim()));
+                        }
+                    }
+                    Some(RegionField::Banner) => {
+                        let banner = e.unescape()?;
+                        if !banner.trim().is_empty() {
+                            current_region.banner = Some(absolute_url(banner.trim()));
+                        }
+                    }
+                    Some(RegionField::Factbook) | None => (),
+                }
+            }
+            // CDATA is not escaped, so it only needs decoding
+            Event::CData(e) if current_field == Some(RegionField::Factbook) => {
+                let factbook = reader.decoder().decode(&e)?;
+                current_region.factbook = Some(factbook.trim().to_string());
+            }
+            Event::Eof if !finished => {
+                return Err(SrsglassError::TruncatedDump {
+                    regions: region_count,
+                    source: None,
+                })
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+
+        buf.clear();
+    }
+
+    Ok(())
+}
+
+pub(crate) fn parse_nations_dump(dump: impl Read + Send) -> Result<Vec<Nation>> {
+    with_decompressed(dump, parse_nations)
+}
+
+/// Parse decompressed nations dump XML.
+fn parse_nations(dump: impl BufRead) -> Result<Vec<Nation>> {
+    let mut reader = Reader::from_reader(dump);
+
+    let mut buf = Vec::new();
+
+    let mut current_tag = None;
+    let mut current_nation = Nation::default();
+
+    let mut nations: Vec<Nation> = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                current_tag = Some(e.to_owned());
+            }
+            Event::End(e) => {
+                if let Some(tag) = current_tag.as_ref() {
+                    if e.name() == tag.name() {
+                        current_tag = None;
+                    }
+                }
+
+                if e.name().as_ref() == b"NATION" {
+                    nations.push(current_nation);
+                    current_nation = Nation::default();
+                }
+            }
+            Event::Text(e) => {
+                if let Some(tag) = current_tag.as_ref() {
+                    match tag.name().as_ref() {
+                        b"NAME" => current_nation.name = Some(e.unescape()?.to_string()),
+                        b"REGION" => current_nation.region = Some(e.unescape()?.to_string()),
+                        b"UNSTATUS" => {
+                            current_nation.wa_member = e.unescape()? != "Non-member";
+                        }
+                        b"ENDORSEMENTS" => {
+                            current_nation.endorsements = e
+                                .unescape()?
+                                .split(',')
+                                .filter(|s| !s.is_empty())
+                                .map(|s| s.to_string())
+                                .collect();
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+
+        buf.clear();
+    }
+
+    Ok(nations)
+}
+
+/// Parse the list of regions from a `regionsbytag` API response.
+pub(crate) fn parse_region_list(body: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(body);
+
+    let mut collecting = false;
+    let mut regions: Vec<String> = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.name().as_ref() == b"REGIONS" => {
+                collecting = true;
+            }
+            Event::End(e) if e.name().as_ref() == b"REGIONS" => {
+                collecting = false;
+            }
+            Event::Text(e) if collecting => {
+                regions = e.unescape()?.split(',').map(|s| s.to_string()).collect();
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    Ok(regions)
+}
+
+/// Parse the number of nations from a `numnations` API response.
+pub(crate) fn parse_nation_count(body: &str) -> Result<i32> {
+    let mut reader = Reader::from_str(body);
+    let mut in_count = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.name().as_ref() == b"NUMNATIONS" => in_count = true,
+            Event::Text(e) if in_count => return Ok(e.unescape()?.trim().parse()?),
+            Event::Eof => return Err(SrsglassError::Parse("No NUMNATIONS in API response".into())),
+            _ => (),
+        }
+    }
+}
+
+/// Parse the regions founded or refounded in a happenings API response,
+/// oldest first.
+pub(crate) fn parse_foundings(body: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(body);
+    let mut in_text = false;
+    let mut regions = Vec::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => in_text = e.name().as_ref() == b"TEXT",
+            Event::End(_) => in_text = false,
+            Event::Text(e) if in_text => regions.extend(founded_region(&e.unescape()?)),
+            Event::CData(e) if in_text => {
+                regions.extend(founded_region(&reader.decoder().decode(&e)?));
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    // happenings are listed newest first
+    regions.reverse();
+    Ok(regions)
+}
+
+/// Get the region a happening says was founded or refounded, such as
+/// `region` in "@@nation@@ founded the region %%region%%.". Nations being
+/// founded or refounded in a region are not region foundings.
+fn founded_region(text: &str) -> Option<String> {
+    let (_, rest) = text.split_once("founded the region %%")?;
+    let (region, _) = rest.split_once("%%")?;
+    Some(region.to_string())
+}
+
+/// Make a URL from the dump absolute, since banners are given as paths on
+/// the NS site.
+fn absolute_url(url: &str) -> String {
+    if url.starts_with('/') {
+        format!("https://www.nationstates.net{}", url)
+    } else {
+        url.to_string()
+    }
+}
@@ -0,0 +1,196 @@
+//! Python bindings to the client, dumps, and update estimates, enabled with
+//! the `python` feature, so that Python tools can use srsglass's parser.
+//! Build it as an extension module with
+//! `cargo rustc --release --lib --features python --crate-type cdylib`, and
+//! copy the library onto the Python path as `srsglass.so` (`srsglass.pyd` on
+//! Windows) to `import srsglass`.
+//!
+//! Failures are raised as `srsglass.SrsglassException`, and requests and
+//! parsing release the GIL so that other Python threads keep running.
+
+use crate::{Client, Dump, DumpExtras, SrsglassError, TimesheetOptions};
+use chrono::NaiveDate;
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+
+create_exception!(srsglass, SrsglassException, PyException);
+
+impl From<SrsglassError> for PyErr {
+    fn from(error: SrsglassError) -> Self {
+        SrsglassException::new_err(error.to_string())
+    }
+}
+
+/// A client for downloading dumps and making API requests, identified to
+/// NationStates by the user's nation.
+#[pyclass(name = "Client", module = "srsglass", frozen)]
+pub struct PyClient {
+    client: Client,
+}
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    pub fn new(user_nation: &str) -> Self {
+        Self {
+            client: Client::new(user_nation),
+        }
+    }
+
+    /// Download today's regions dump, along with the API data about regions
+    /// that the dump leaves out.
+    pub fn get_dump(&self, py: Python<'_>) -> PyResult<PyDump> {
+        let dump = py.allow_threads(|| self.client.get_dump())?;
+        Ok(PyDump { dump })
+    }
+
+    /// Download the archived regions dump from a date.
+    pub fn get_dump_for_date(&self, py: Python<'_>, date: NaiveDate) -> PyResult<PyDump> {
+        let dump = py.allow_threads(|| self.client.get_dump_for_date(date))?;
+        Ok(PyDump { dump })
+    }
+
+    /// Read a regions dump from a file, with the API data about regions that
+    /// the dump leaves out.
+    pub fn get_dump_from_file(&self, py: Python<'_>, path: &str) -> PyResult<PyDump> {
+        let dump = py.allow_threads(|| self.client.get_dump_from_file(path))?;
+        Ok(PyDump { dump })
+    }
+
+    /// Read a regions dump from a file without making any requests.
+    pub fn get_dump_from_file_offline(&self, py: Python<'_>, path: &str) -> PyResult<PyDump> {
+        let dump = py.allow_threads(|| self.client.get_dump_from_file_offline(path))?;
+        Ok(PyDump { dump })
+    }
+}
+
+/// A regions dump and the API data about its regions.
+#[pyclass(name = "Dump", module = "srsglass", frozen)]
+pub struct PyDump {
+    dump: Dump,
+}
+
+#[pymethods]
+impl PyDump {
+    /// Read a gzipped or uncompressed regions dump from bytes, without
+    /// making any requests.
+    #[staticmethod]
+    pub fn from_bytes(py: Python<'_>, data: &[u8]) -> PyResult<Self> {
+        let dump = py.allow_threads(|| Dump::from_reader(data, DumpExtras::default()))?;
+        Ok(Self { dump })
+    }
+
+    /// Date that NS considers the dump to be generated on
+    #[getter]
+    pub fn dump_date(&self) -> NaiveDate {
+        self.dump.dump_date
+    }
+
+    /// Whether the dump ended early, so that later regions are missing
+    #[getter]
+    pub fn incomplete(&self) -> bool {
+        self.dump.incomplete
+    }
+
+    /// SHA-256 of the regions dump as it was read, as a hex string
+    #[getter]
+    pub fn sha256(&self) -> Option<String> {
+        self.dump.sha256.clone()
+    }
+
+    /// Names of the regions in the dump, in dump order
+    #[getter]
+    pub fn regions(&self) -> Vec<String> {
+        self.dump
+            .regions
+            .iter()
+            .filter_map(|region| region.name.clone())
+            .collect()
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.dump.regions.len()
+    }
+
+    /// Estimate when each region updates, in seconds from the start of
+    /// major and minor update, in update order.
+    #[pyo3(signature = (major_length = 5350, minor_length = 3550))]
+    pub fn estimate_times(
+        &self,
+        major_length: i32,
+        minor_length: i32,
+    ) -> PyResult<Vec<PyEstimate>> {
+        Ok(self
+            .dump
+            .estimate_times(major_length, minor_length)?
+            .into_iter()
+            .map(|estimate| PyEstimate {
+                name: estimate.name.to_string(),
+                nations_before: estimate.nations_before,
+                nations_before_minor: estimate.nations_before_minor,
+                major: estimate.major,
+                minor: estimate.minor,
+                last_major: estimate.last_major,
+                last_minor: estimate.last_minor,
+            })
+            .collect())
+    }
+
+    /// Write an Excel timesheet with the default options and the given
+    /// update lengths.
+    #[pyo3(signature = (path, major_length = 5350, minor_length = 3550))]
+    pub fn to_excel(
+        &self,
+        py: Python<'_>,
+        path: &str,
+        major_length: i32,
+        minor_length: i32,
+    ) -> PyResult<()> {
+        let options = TimesheetOptions {
+            major_length,
+            minor_length,
+            ..TimesheetOptions::default()
+        };
+        py.allow_threads(|| self.dump.to_excel(path, &options))?;
+
+        Ok(())
+    }
+}
+
+/// A region's estimated update times, in seconds from the start of each
+/// update.
+#[pyclass(name = "Estimate", module = "srsglass", frozen, get_all)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PyEstimate {
+    pub name: String,
+    /// Number of nations updating before the region in major update
+    pub nations_before: i32,
+    /// Number of nations updating before the region in minor update
+    pub nations_before_minor: i32,
+    pub major: f64,
+    pub minor: f64,
+    /// When the region updated in the last major update, if it did
+    pub last_major: Option<i64>,
+    /// When the region updated in the last minor update, if it did
+    pub last_minor: Option<i64>,
+}
+
+#[pymethods]
+impl PyEstimate {
+    pub fn __repr__(&self) -> String {
+        format!(
+            "Estimate(name={:?}, major={}, minor={})",
+            self.name, self.major, self.minor
+        )
+    }
+}
+
+/// The `srsglass` Python module.
+#[pymodule]
+pub fn srsglass(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    m.add_class::<PyDump>()?;
+    m.add_class::<PyEstimate>()?;
+    m.add("SrsglassException", m.py().get_type::<SrsglassException>())?;
+
+    Ok(())
+}
@@ -0,0 +1,620 @@
+//! The per-region rows shared by every timesheet format.
+
+use crate::estimate::{self, Calibration, Position, Uncertainty, Update};
+use crate::{
+    canonical_name, Column, DelegateAuthority, Dump, Region, Result, SrsglassError,
+    TimesheetOptions,
+};
+use chrono::naive::Days;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use chrono_tz::{Tz, US::Eastern};
+use std::collections::HashSet;
+
+/// A region's values in the timesheet, shared by every output format.
+pub(crate) struct Row<'a> {
+    pub name: &'a str,
+    pub link: String,
+    pub population: i32,
+    /// Number of nations in the World Assembly, if counted
+    pub wa_members: Option<i32>,
+    pub nations_before: i32,
+    /// Fraction of the world's nations that update before the region
+    pub progress: f64,
+    /// Estimated minor update time, in seconds from the start of the update
+    pub minor: f64,
+    /// Estimated major update time, in seconds from the start of the update
+    pub major: f64,
+    /// Time to be in position for minor update, in seconds from the start of
+    /// the update
+    pub minor_trigger: f64,
+    /// Time to be in position for major update, in seconds from the start of
+    /// the update
+    pub major_trigger: f64,
+    /// Estimated wall-clock time of minor update
+    pub minor_time: String,
+    /// Estimated wall-clock time of major update
+    pub major_time: String,
+    /// Part of minor update the region updates in
+    pub minor_window: &'static str,
+    /// Part of major update the region updates in
+    pub major_window: &'static str,
+    /// Seconds after [`TimesheetOptions::relative_to`] the region updates in
+    /// minor, or before if negative
+    pub minor_relative: Option<f64>,
+    /// Seconds after [`TimesheetOptions::relative_to`] the region updates in
+    /// major, or before if negative
+    pub major_relative: Option<f64>,
+    /// Observed time of the last minor update, in seconds from its start, if
+    /// the region updated in it
+    pub minor_observed: Option<f64>,
+    /// Observed time of the last major update, in seconds from its start, if
+    /// the region updated in it
+    pub major_observed: Option<f64>,
+    /// Typical error of the minor update estimate, in seconds, if any region
+    /// updated near the region in the last minor update
+    pub minor_uncertainty: Option<f64>,
+    /// Typical error of the major update estimate, in seconds, if any region
+    /// updated near the region in the last major update
+    pub major_uncertainty: Option<f64>,
+    /// Whether the region is estimated to have updated already in the update
+    /// in progress
+    pub already_updated: bool,
+    /// Name of the WA Delegate, if the region has one
+    pub delegate: Option<&'a str>,
+    pub delegate_votes: i32,
+    pub delegate_endorsements: i32,
+    /// Endorsements needed to have more than the delegate, or 0 if the
+    /// region has no delegate
+    pub endorsements_needed: i32,
+    pub has_governor: bool,
+    /// Name of the governor, if the region has one
+    pub governor: Option<&'a str>,
+    pub delegate_exec: bool,
+    /// The authorities the delegate holds
+    pub delegate_authority: DelegateAuthority,
+    pub has_password: bool,
+    /// Whether the region is a frontier rather than a stronghold
+    pub is_frontier: bool,
+    /// Whether the region has a password and is tagged Invader but not
+    /// Defender
+    pub is_occupied: bool,
+    /// Whether the region was founded since the last major update
+    pub is_new: bool,
+    /// Unix timestamp at which the region was founded or last refounded
+    pub founded: Option<i64>,
+    /// Established embassies, leaving out those still being negotiated or
+    /// closing
+    pub embassies: Vec<&'a str>,
+    /// Embassies with regions in [`TimesheetOptions::friendly`]
+    pub friendly_embassies: Vec<&'a str>,
+    /// Embassies with regions in [`TimesheetOptions::enemy`]
+    pub enemy_embassies: Vec<&'a str>,
+    pub factbook: &'a str,
+    pub flag: Option<&'a str>,
+    pub banner: Option<&'a str>,
+    /// Whether the region is in each of [`TimesheetOptions::tag_sets`]
+    pub tag_sets: Vec<bool>,
+}
+
+impl Row<'_> {
+    /// Check whether the region is in the tag set at the given index of
+    /// [`TimesheetOptions::tag_sets`].
+    pub(crate) fn in_tag_set(&self, index: usize) -> bool {
+        self.tag_sets.get(index).copied().unwrap_or(false)
+    }
+
+    /// Seconds the region updated after its estimated time in the last minor
+    /// update, or before if negative.
+    pub(crate) fn minor_residual(&self) -> Option<f64> {
+        self.minor_observed.map(|observed| observed - self.minor)
+    }
+
+    /// Seconds the region updated after its estimated time in the last major
+    /// update, or before if negative.
+    pub(crate) fn major_residual(&self) -> Option<f64> {
+        self.major_observed.map(|observed| observed - self.major)
+    }
+
+    /// Get the text of a column in text-based timesheets.
+    pub(crate) fn text(&self, column: Column, timestamp_precision: i32) -> String {
+        match column {
+            Column::Region => self.name.to_string(),
+            Column::Link => self.link.clone(),
+            Column::Population => self.population.to_string(),
+            Column::WaMembers => self
+                .wa_members
+                .map(|wa_members| wa_members.to_string())
+                .unwrap_or_default(),
+            Column::TotalNations => self.nations_before.to_string(),
+            Column::Progress => format!("{:.2}%", self.progress * 100.0),
+            Column::Minor => format_duration(self.minor, timestamp_precision),
+            Column::Major => format_duration(self.major, timestamp_precision),
+            Column::MinorTrigger => format_duration(self.minor_trigger, timestamp_precision),
+            Column::MajorTrigger => format_duration(self.major_trigger, timestamp_precision),
+            Column::MinorTime => self.minor_time.clone(),
+            Column::MajorTime => self.major_time.clone(),
+            Column::MinorWindow => self.minor_window.to_string(),
+            Column::MajorWindow => self.major_window.to_string(),
+            Column::MinorRelative => format_relative(self.minor_relative, timestamp_precision),
+            Column::MajorRelative => format_relative(self.major_relative, timestamp_precision),
+            Column::MinorObserved => self
+                .minor_observed
+                .map(|observed| format_duration(observed, timestamp_precision))
+                .unwrap_or_default(),
+            Column::MajorObserved => self
+                .major_observed
+                .map(|observed| format_duration(observed, timestamp_precision))
+                .unwrap_or_default(),
+            Column::Missing => [
+                ("major", self.major_observed),
+                ("minor", self.minor_observed),
+            ]
+            .into_iter()
+            .filter(|(_, observed)| observed.is_none())
+            .map(|(update, _)| update)
+            .collect::<Vec<_>>()
+            .join(","),
+            Column::MinorResidual => format_relative(self.minor_residual(), timestamp_precision),
+            Column::MajorResidual => format_relative(self.major_residual(), timestamp_precision),
+            Column::MinorUncertainty => {
+                format_uncertainty(self.minor_uncertainty, timestamp_precision)
+            }
+            Column::MajorUncertainty => {
+                format_uncertainty(self.major_uncertainty, timestamp_precision)
+            }
+            Column::Updated => self.already_updated.to_string(),
+            Column::Delegate => self.delegate.unwrap_or_default().to_string(),
+            Column::DelegateVotes => self.delegate_votes.to_string(),
+            Column::DelegateEndorsements => self.delegate_endorsements.to_string(),
+            Column::EndorsementsNeeded => self.endorsements_needed.to_string(),
+            Column::HasGovernor => self.has_governor.to_string(),
+            Column::Governor => self.governor.unwrap_or_default().to_string(),
+            Column::Executive => self.delegate_exec.to_string(),
+            Column::DelegateAppearance => self.delegate_authority.appearance.to_string(),
+            Column::DelegateBorderControl => self.delegate_authority.border_control.to_string(),
+            Column::DelegateCommunications => self.delegate_authority.communications.to_string(),
+            Column::DelegateEmbassies => self.delegate_authority.embassies.to_string(),
+            Column::DelegatePolls => self.delegate_authority.polls.to_string(),
+            Column::Password => self.has_password.to_string(),
+            Column::Occupied => self.is_occupied.to_string(),
+            Column::Type => region_type(self.is_frontier).to_string(),
+            Column::New => self.is_new.to_string(),
+            Column::Founded => self
+                .founded
+                .and_then(|founded| DateTime::from_timestamp(founded, 0))
+                .map(|founded| founded.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            Column::EmbassyCount => self.embassies.len().to_string(),
+            Column::Embassies => self.embassies.join(","),
+            Column::HasFriendlyEmbassy => (!self.friendly_embassies.is_empty()).to_string(),
+            Column::FriendlyEmbassies => self.friendly_embassies.join(","),
+            Column::EnemyEmbassies => self.enemy_embassies.join(","),
+            Column::Factbook => self.factbook.to_string(),
+            Column::Flag => self.flag.unwrap_or_default().to_string(),
+            Column::Banner => self.banner.unwrap_or_default().to_string(),
+            Column::TagSet(index) => self.in_tag_set(index).to_string(),
+        }
+    }
+}
+
+/// Get the part of an update a region updates in, given its estimated update
+/// time and the length of the update, in seconds.
+fn update_window(time: f64, length: i32, options: &TimesheetOptions) -> &'static str {
+    if time < options.early_window {
+        "Early"
+    } else if time >= f64::from(length) - options.late_window {
+        "Late"
+    } else {
+        "Middle"
+    }
+}
+
+/// Get the name of a region's type, as NS labels it.
+pub(crate) fn region_type(is_frontier: bool) -> &'static str {
+    if is_frontier {
+        "Frontier"
+    } else {
+        "Stronghold"
+    }
+}
+
+/// Collect region names into a set of their canonical forms.
+fn canonical_set(names: &[String]) -> HashSet<String> {
+    names.iter().map(|name| canonical_name(name)).collect()
+}
+
+/// Split a duration in seconds into hours, minutes, seconds, and milliseconds.
+pub(crate) fn split_duration(duration: f64) -> (u16, u8, u8, u16) {
+    let h = (duration / 3600.0).floor() as u16;
+    let m = ((duration / 60.0) % 60.0).floor() as u8;
+    let s = (duration % 60.0).floor() as u8;
+    let ms = (duration.fract() * 1000.0).round().clamp(0.0, 999.0) as u16;
+
+    (h, m, s, ms)
+}
+
+/// Format a number of seconds relative to another region with a sign, such
+/// as `+12` or `-3.5`, or as empty if there is no region to compare to.
+fn format_relative(seconds: Option<f64>, precision: i32) -> String {
+    match seconds {
+        Some(seconds) => format!("{:+.*}", precision as usize, seconds),
+        None => String::new(),
+    }
+}
+
+/// Format the uncertainty of an estimate in seconds, or nothing if it is not
+/// known.
+fn format_uncertainty(seconds: Option<f64>, precision: i32) -> String {
+    match seconds {
+        Some(seconds) => format!("±{:.*}", precision as usize, seconds),
+        None => String::new(),
+    }
+}
+
+/// Get the time an update starting at `hour` Eastern on `date` begins.
+fn update_start(date: NaiveDate, hour: u32) -> Result<DateTime<Tz>> {
+    date.and_hms_opt(hour, 0, 0)
+        .and_then(|start| Eastern.from_local_datetime(&start).earliest())
+        .ok_or_else(|| SrsglassError::InvalidDump(format!("Invalid dump date {}", date)))
+}
+
+/// Format the time `offset` seconds after the start of an update as a
+/// wall-clock time such as `12:07:33 AM EST`, with `precision` digits of
+/// fractional seconds.
+fn format_clock_time(start: DateTime<Tz>, offset: f64, precision: i32, timezone: Tz) -> String {
+    // split the offset the same way as update times, so that both round alike
+    let (h, m, s, ms) = split_duration(offset);
+    let offset =
+        i64::from(h) * 3_600_000 + i64::from(m) * 60_000 + i64::from(s) * 1000 + i64::from(ms);
+    let time = (start + chrono::Duration::milliseconds(offset)).with_timezone(&timezone);
+
+    let fraction = match precision {
+        0 => String::new(),
+        _ => format!(".{:03}", time.timestamp_subsec_millis())[..=precision as usize].to_string(),
+    };
+
+    format!(
+        "{}{} {}",
+        time.format("%-I:%M:%S"),
+        fraction,
+        time.format("%p %Z")
+    )
+}
+
+/// Find the row for a region by name, in any case and with spaces or
+/// underscores.
+fn find_row<'r, 'a>(rows: &'r [Row<'a>], region: &str) -> Result<&'r Row<'a>> {
+    let name = canonical_name(region);
+
+    rows.iter()
+        .find(|row| canonical_name(row.name) == name)
+        .ok_or_else(|| SrsglassError::InvalidOption(format!("Could not find region {}", region)))
+}
+
+/// Format a duration in seconds as `H:MM:SS`, with `precision` digits of
+/// fractional seconds.
+pub(crate) fn format_duration(duration: f64, precision: i32) -> String {
+    let (h, m, s, ms) = split_duration(duration);
+
+    if precision == 0 {
+        return format!("{h}:{m:02}:{s:02}");
+    }
+
+    let fraction = format!("{ms:03}");
+    format!("{h}:{m:02}:{s:02}.{}", &fraction[..precision as usize])
+}
+
+impl Dump {
+    /// Build the timesheet row for each region that has the data needed for
+    /// one and passes the filter, in update order.
+    pub(crate) fn rows<'a>(&'a self, options: &TimesheetOptions) -> Result<Vec<Row<'a>>> {
+        let positions = estimate::positions(self, options.model)?;
+
+        let entries = estimate::update_order(self)
+            .into_iter()
+            .filter_map(|index| Some((&self.regions[index], positions[index]?)));
+
+        self.build_rows(entries, options)
+    }
+
+    /// Build the timesheet rows in the order regions last updated in minor
+    /// update, with nations counted cumulatively in that order.
+    pub(crate) fn minor_rows<'a>(&'a self, options: &TimesheetOptions) -> Result<Vec<Row<'a>>> {
+        let positions = estimate::positions(self, options.model)?;
+
+        let entries = estimate::minor_order(self).into_iter().filter_map(|index| {
+            let position = positions[index]?;
+
+            Some((
+                &self.regions[index],
+                Position {
+                    nations_before: position.nations_before_minor,
+                    ..position
+                },
+            ))
+        });
+
+        self.build_rows(entries, options)
+    }
+
+    /// Build rows from regions in update order, along with the estimated
+    /// position of each, keeping those that pass the filter.
+    fn build_rows<'a>(
+        &'a self,
+        entries: impl Iterator<Item = (&'a Region, Position)>,
+        options: &TimesheetOptions,
+    ) -> Result<Vec<Row<'a>>> {
+        let total_population = self.total_population()?;
+        let friendly = canonical_set(&options.friendly);
+        let enemy = canonical_set(&options.enemy);
+        let governorless = canonical_set(&self.governorless);
+        let passwordless = canonical_set(&self.passwordless);
+        let frontiers = canonical_set(&self.frontiers);
+        let invaders = canonical_set(&self.invaders);
+        let defenders = canonical_set(&self.defenders);
+        let tag_sets: Vec<HashSet<String>> = options
+            .tag_sets
+            .iter()
+            .map(|tag_set| canonical_set(&tag_set.regions))
+            .collect();
+
+        // the first updates after the dump was generated, which start at
+        // noon and midnight Eastern
+        let minor_start = update_start(self.dump_date + Days::new(1), 12)?;
+        let major_start = update_start(self.dump_date + Days::new(2), 0)?;
+        // observed times are measured from the first region to update, since
+        // the dump records when each region updated but not when updates began
+        let observed_minor_start = estimate::observed_start(self, Update::Minor);
+        let observed_major_start = estimate::observed_start(self, Update::Major);
+        let observed = |start: Option<i64>, time: Option<i64>| {
+            Some((time.filter(|&time| time > 0)? - start?) as f64)
+        };
+        let minor_uncertainty = Uncertainty::measure(self, options.model, Update::Minor)?;
+        let major_uncertainty = Uncertainty::measure(self, options.model, Update::Major)?;
+        let calibrate = |update, length| {
+            let anchors = if update == options.anchor_update {
+                options.anchors.as_slice()
+            } else {
+                &[]
+            };
+            Calibration::new(self, options.model, update, length, anchors)
+        };
+        let minor_calibration = calibrate(Update::Minor, options.minor_length)?;
+        let major_calibration = calibrate(Update::Major, options.major_length)?;
+
+        // how far the update in progress had got, if it is known to be underway
+        let elapsed = options.update_started.map(|started| {
+            let now = options.now.unwrap_or_else(Utc::now);
+            (now - started).num_milliseconds() as f64 / 1000.0
+        });
+
+        let clock_time = |start, offset| {
+            format_clock_time(start, offset, options.timestamp_precision, options.timezone)
+        };
+
+        // Pick out the embassies a region has with any of the given regions
+        let embassies_with = |embassies: &[&'a str], regions: &HashSet<String>| -> Vec<&'a str> {
+            embassies
+                .iter()
+                .filter(|embassy| regions.contains(&canonical_name(embassy)))
+                .copied()
+                .collect()
+        };
+
+        let mut rows: Vec<Row> = entries
+            .filter_map(|(region, position)| {
+                let Region {
+                    name: Some(name),
+                    population: Some(population),
+                    delegate_votes: Some(delegate_votes),
+                    factbook,
+                    delegate_exec: Some(delegate_exec),
+                    embassies,
+                    ..
+                } = region
+                else {
+                    return None;
+                };
+
+                let canonical = canonical_name(name);
+                let embassies: Vec<&str> = embassies
+                    .iter()
+                    .filter(|embassy| embassy.is_established())
+                    .map(|embassy| embassy.region.as_str())
+                    .collect();
+
+                let minor = minor_calibration.time(position.minor);
+                let major = major_calibration.time(position.major);
+                let estimate = match options.anchor_update {
+                    Update::Minor => minor,
+                    Update::Major => major,
+                };
+                let already_updated = elapsed.is_some_and(|elapsed| estimate <= elapsed);
+
+                Some(Row {
+                    name,
+                    link: options.link_style.url(&canonical),
+                    population: *population,
+                    wa_members: region.wa_members,
+                    nations_before: position.nations_before,
+                    progress: position.nations_before as f64 / total_population as f64,
+                    minor,
+                    major,
+                    // nothing can be in position before the update starts
+                    minor_trigger: (minor - options.trigger_offset).max(0.0),
+                    major_trigger: (major - options.trigger_offset).max(0.0),
+                    minor_time: clock_time(minor_start, minor),
+                    major_time: clock_time(major_start, major),
+                    minor_window: update_window(minor, options.minor_length, options),
+                    major_window: update_window(major, options.major_length, options),
+                    minor_relative: None,
+                    major_relative: None,
+                    minor_observed: observed(observed_minor_start, region.last_minor),
+                    major_observed: observed(observed_major_start, region.last_major),
+                    minor_uncertainty: minor_uncertainty
+                        .at(position.minor)
+                        .map(|uncertainty| uncertainty * options.minor_length as f64),
+                    major_uncertainty: major_uncertainty
+                        .at(position.major)
+                        .map(|uncertainty| uncertainty * options.major_length as f64),
+                    already_updated,
+                    delegate: region.delegate.as_deref(),
+                    delegate_votes: *delegate_votes,
+                    delegate_endorsements: region.endorsement_count().unwrap_or(0),
+                    endorsements_needed: match region.delegate {
+                        Some(_) => region.endorsement_count().unwrap_or(0) + 1,
+                        None => 0,
+                    },
+                    has_governor: !governorless.contains(&canonical),
+                    governor: region.governor.as_deref(),
+                    delegate_exec: *delegate_exec,
+                    // regions built without the full authority string still
+                    // know whether the delegate is executive
+                    delegate_authority: region.delegate_authority.unwrap_or(DelegateAuthority {
+                        executive: *delegate_exec,
+                        ..DelegateAuthority::default()
+                    }),
+                    has_password: !passwordless.contains(&canonical),
+                    is_frontier: frontiers.contains(&canonical),
+                    is_occupied: !passwordless.contains(&canonical)
+                        && invaders.contains(&canonical)
+                        && !defenders.contains(&canonical),
+                    is_new: region.is_new(),
+                    founded: region.founded,
+                    friendly_embassies: embassies_with(&embassies, &friendly),
+                    enemy_embassies: embassies_with(&embassies, &enemy),
+                    embassies,
+                    factbook: factbook.as_deref().unwrap_or_default(),
+                    flag: region.flag.as_deref(),
+                    banner: region.banner.as_deref(),
+                    tag_sets: tag_sets
+                        .iter()
+                        .map(|regions| regions.contains(&canonical))
+                        .collect(),
+                })
+            })
+            .collect();
+
+        if let Some(relative_to) = &options.relative_to {
+            let reference = canonical_name(relative_to);
+
+            let Some((minor, major)) = rows
+                .iter()
+                .find(|row| canonical_name(row.name) == reference)
+                .map(|row| (row.minor, row.major))
+            else {
+                return Err(SrsglassError::InvalidOption(format!(
+                    "Could not find region {}",
+                    relative_to
+                )));
+            };
+
+            for row in &mut rows {
+                row.minor_relative = Some(row.minor - minor);
+                row.major_relative = Some(row.major - major);
+            }
+        }
+
+        let Some(filter) = &options.filter else {
+            return Ok(rows);
+        };
+        let filter = canonical_set(filter);
+
+        // Keep each filtered region along with its neighbors in update order,
+        // which are useful as triggers
+        let mut keep = vec![false; rows.len()];
+        for (index, row) in rows.iter().enumerate() {
+            if filter.contains(&canonical_name(row.name)) {
+                let start = index.saturating_sub(options.filter_context);
+                let end = (index + options.filter_context).min(rows.len() - 1);
+                keep[start..=end].fill(true);
+            }
+        }
+
+        Ok(rows
+            .into_iter()
+            .zip(keep)
+            .filter_map(|(row, keep)| keep.then_some(row))
+            .collect())
+    }
+
+    /// Build the row for each region as [`Dump::rows`] does, ignoring the
+    /// filter, to look up regions the filter may have left out.
+    fn unfiltered_rows<'a>(&'a self, options: &TimesheetOptions) -> Result<Vec<Row<'a>>> {
+        self.rows(&TimesheetOptions {
+            filter: None,
+            ..options.clone()
+        })
+    }
+
+    /// Count the regions listed in the timesheet, after filtering.
+    pub fn row_count(&self, options: &TimesheetOptions) -> Result<usize> {
+        Ok(self.rows(options)?.len())
+    }
+
+    /// Get a region's estimated update time in the given update, in seconds
+    /// from the start of the update, as shown in the timesheet.
+    pub fn estimated_time(
+        &self,
+        region: &str,
+        update: Update,
+        options: &TimesheetOptions,
+    ) -> Result<f64> {
+        let rows = self.unfiltered_rows(options)?;
+        let row = find_row(&rows, region)?;
+
+        Ok(match update {
+            Update::Major => row.major,
+            Update::Minor => row.minor,
+        })
+    }
+
+    /// Describe a single region for a quick check in the terminal, with its
+    /// estimated update times, position in the update, and whether it can be
+    /// targeted, one line each. [`TimesheetOptions::columns`] and
+    /// [`TimesheetOptions::filter`] are ignored.
+    pub fn describe_region(&self, region: &str, options: &TimesheetOptions) -> Result<String> {
+        options.check_precision()?;
+
+        let columns = [
+            Column::Region,
+            Column::Link,
+            Column::Major,
+            Column::MajorTime,
+            Column::Minor,
+            Column::MinorTime,
+            Column::Population,
+            Column::TotalNations,
+            Column::Progress,
+            Column::Delegate,
+            Column::DelegateEndorsements,
+            Column::Executive,
+            Column::HasGovernor,
+            Column::Password,
+            Column::Occupied,
+            Column::Type,
+        ];
+
+        let rows = self.unfiltered_rows(options)?;
+        let row = find_row(&rows, region)?;
+
+        // line the values up after the longest header
+        let width = columns
+            .map(|column| column.header().len())
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        Ok(columns
+            .iter()
+            .map(|&column| {
+                format!(
+                    "{:<width$} {}\n",
+                    format!("{}:", column.header()),
+                    row.text(column, options.timestamp_precision)
+                )
+            })
+            .collect())
+    }
+}
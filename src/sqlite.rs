@@ -0,0 +1,122 @@
+//! SQLite timesheets.
+
+use crate::{Dump, Result, TimesheetOptions};
+use std::path::Path;
+
+impl Dump {
+    /// Write the timesheet to an SQLite database, with a `regions` table of
+    /// the per-region columns, an `embassies` table of each region's
+    /// embassies, and a `metadata` table of the world data. Update times are
+    /// given as seconds from the start of the update. An existing database at
+    /// the path is replaced.
+    pub fn to_sqlite(
+        &self,
+        output_file: impl AsRef<Path>,
+        options: &TimesheetOptions,
+    ) -> Result<()> {
+        let output_file = output_file.as_ref();
+        if output_file.exists() {
+            std::fs::remove_file(output_file)?;
+        }
+
+        let total_population = self.total_population()?;
+
+        let mut connection = rusqlite::Connection::open(output_file)?;
+        let transaction = connection.transaction()?;
+
+        transaction.execute_batch(
+            "CREATE TABLE regions (
+                name TEXT PRIMARY KEY,
+                link TEXT NOT NULL,
+                population INTEGER NOT NULL,
+                nations_before INTEGER NOT NULL,
+                progress REAL NOT NULL,
+                minor REAL NOT NULL,
+                major REAL NOT NULL,
+                delegate TEXT,
+                delegate_votes INTEGER NOT NULL,
+                delegate_endorsements INTEGER NOT NULL,
+                has_governor INTEGER NOT NULL,
+                governor TEXT,
+                delegate_exec INTEGER NOT NULL,
+                has_password INTEGER NOT NULL,
+                frontier INTEGER NOT NULL,
+                new INTEGER NOT NULL,
+                factbook TEXT NOT NULL
+            );
+            CREATE TABLE embassies (
+                region TEXT NOT NULL REFERENCES regions (name),
+                embassy TEXT NOT NULL
+            );
+            CREATE TABLE metadata (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );",
+        )?;
+
+        {
+            let mut insert_region = transaction.prepare(
+                "INSERT INTO regions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            )?;
+            let mut insert_embassy =
+                transaction.prepare("INSERT INTO embassies VALUES (?1, ?2)")?;
+
+            for row in self.rows(options)? {
+                insert_region.execute(rusqlite::params![
+                    row.name,
+                    row.link,
+                    row.population,
+                    row.nations_before,
+                    row.progress,
+                    row.minor,
+                    row.major,
+                    row.delegate,
+                    row.delegate_votes,
+                    row.delegate_endorsements,
+                    row.has_governor,
+                    row.governor,
+                    row.delegate_exec,
+                    row.has_password,
+                    row.is_frontier,
+                    row.is_new,
+                    row.factbook,
+                ])?;
+
+                for embassy in &row.embassies {
+                    insert_embassy.execute([row.name, embassy])?;
+                }
+            }
+
+            let mut insert_metadata =
+                transaction.prepare("INSERT INTO metadata VALUES (?1, ?2)")?;
+
+            let metadata = [
+                ("nations", total_population.to_string()),
+                ("major_length", options.major_length.to_string()),
+                ("minor_length", options.minor_length.to_string()),
+                ("version", env!("CARGO_PKG_VERSION").to_string()),
+                (
+                    "date_generated",
+                    chrono::Utc::now().date_naive().to_string(),
+                ),
+                ("dump_date", self.dump_date.to_string()),
+                (
+                    "last_update",
+                    self.last_update()
+                        .map(|time| time.to_rfc3339())
+                        .unwrap_or_default(),
+                ),
+                ("incomplete", self.incomplete.to_string()),
+                ("dump_sha256", self.sha256.clone().unwrap_or_default()),
+            ];
+
+            for (key, value) in metadata {
+                insert_metadata.execute([key, &value])?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
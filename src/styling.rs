@@ -0,0 +1,217 @@
+//! Colors for highlighting regions and cells in timesheets.
+
+use crate::rows::Row;
+use crate::{Column, Result, SrsglassError};
+use rust_xlsxwriter::Color;
+use std::str::FromStr;
+
+/// A region attribute that a [`ColorRule`] can check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attribute {
+    /// The region has a governor
+    Governor,
+    /// The region's delegate has executive authority
+    Executive,
+    /// The region has a password
+    Password,
+    /// The region is a frontier rather than a stronghold
+    Frontier,
+    /// The region is held by raiders, going by its password and tags
+    Occupied,
+}
+
+impl Attribute {
+    /// Get the timesheet column holding the attribute.
+    pub(crate) fn column(self) -> Column {
+        match self {
+            Attribute::Governor => Column::HasGovernor,
+            Attribute::Executive => Column::Executive,
+            Attribute::Password => Column::Password,
+            Attribute::Frontier => Column::Type,
+            Attribute::Occupied => Column::Occupied,
+        }
+    }
+}
+
+/// A check that a region does (or, if negated, does not) have an attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub attribute: Attribute,
+    pub negated: bool,
+}
+
+/// A rule for coloring regions in the timesheet. Each region is colored by
+/// the first rule whose conditions all hold for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorRule {
+    pub color: Color,
+    pub conditions: Vec<Condition>,
+}
+
+impl ColorRule {
+    /// Get the rules Spyglass uses: green for regions without a governor or
+    /// password, yellow for regions with an executive delegate and no
+    /// password, and red for regions with a password. Frontiers, which
+    /// Spyglass does not distinguish, are colored silver ahead of these.
+    pub fn defaults() -> Vec<ColorRule> {
+        Styling::default().color_rules()
+    }
+
+    pub(crate) fn matches(&self, row: &Row) -> bool {
+        self.conditions.iter().all(|condition| {
+            let value = match condition.attribute {
+                Attribute::Governor => row.has_governor,
+                Attribute::Executive => row.delegate_exec,
+                Attribute::Password => row.has_password,
+                Attribute::Frontier => row.is_frontier,
+                Attribute::Occupied => row.is_occupied,
+            };
+
+            value != condition.negated
+        })
+    }
+}
+
+impl FromStr for ColorRule {
+    type Err = SrsglassError;
+
+    /// Parse a rule of the form `color=condition,condition`, where the color
+    /// is a name like `lime` or a hex code like `#00FF00`, and each condition
+    /// is `governor`, `exec`, `password`, `frontier`, or `occupied`,
+    /// optionally prefixed with `-` to negate it.
+    fn from_str(s: &str) -> Result<Self> {
+        let (color, conditions) = s.split_once('=').ok_or_else(|| {
+            SrsglassError::InvalidOption(format!(
+                "Color rule {} must be of the form color=conditions",
+                s
+            ))
+        })?;
+
+        let color = parse_color(color)?;
+
+        let conditions = conditions
+            .split(',')
+            .map(str::trim)
+            .filter(|condition| !condition.is_empty())
+            .map(|condition| {
+                let (negated, name) = match condition.strip_prefix('-') {
+                    Some(name) => (true, name),
+                    None => (false, condition),
+                };
+
+                let attribute = match name {
+                    "governor" => Attribute::Governor,
+                    "exec" => Attribute::Executive,
+                    "password" => Attribute::Password,
+                    "frontier" => Attribute::Frontier,
+                    "occupied" => Attribute::Occupied,
+                    _ => {
+                        return Err(SrsglassError::InvalidOption(format!(
+                            "Unknown color rule condition {}",
+                            name
+                        )))
+                    }
+                };
+
+                Ok(Condition { attribute, negated })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(ColorRule { color, conditions })
+    }
+}
+
+/// Parse a color name like `lime` or a hex code like `#00FF00`.
+pub fn parse_color(color: &str) -> Result<Color> {
+    let color = match color.trim().to_lowercase().as_str() {
+        "black" => Color::Black,
+        "blue" => Color::Blue,
+        "brown" => Color::Brown,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "green" => Color::Green,
+        "lime" => Color::Lime,
+        "magenta" => Color::Magenta,
+        "navy" => Color::Navy,
+        "orange" => Color::Orange,
+        "pink" => Color::Pink,
+        "purple" => Color::Purple,
+        "red" => Color::Red,
+        "silver" => Color::Silver,
+        "white" => Color::White,
+        "yellow" => Color::Yellow,
+        hex => {
+            let unknown = || SrsglassError::InvalidOption(format!("Unknown color {}", hex));
+
+            let digits = hex.strip_prefix('#').ok_or_else(unknown)?;
+            Color::RGB(u32::from_str_radix(digits, 16).map_err(|_| unknown())?)
+        }
+    };
+
+    Ok(color)
+}
+
+/// Fill colors used to highlight cells in Excel and HTML timesheets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Styling {
+    /// Regions without a governor or password
+    pub open_target: Color,
+    /// Regions with an executive delegate and no password
+    pub executive_target: Color,
+    /// Regions with a password
+    pub passworded: Color,
+    /// Frontier regions
+    pub frontier: Color,
+    /// Endorsement counts of regions without a delegate
+    pub no_delegate: Color,
+    /// Embassies with friendly regions
+    pub friendly_embassies: Color,
+    /// Embassies with enemy regions
+    pub enemy_embassies: Color,
+}
+
+impl Default for Styling {
+    /// Use the colors Spyglass does, with frontiers in silver.
+    fn default() -> Self {
+        Self {
+            open_target: Color::Lime,
+            executive_target: Color::Yellow,
+            passworded: Color::Red,
+            frontier: Color::Silver,
+            no_delegate: Color::Red,
+            friendly_embassies: Color::Lime,
+            enemy_embassies: Color::Red,
+        }
+    }
+}
+
+impl Styling {
+    /// Get the default color rules, colored with this styling's colors. See
+    /// [`ColorRule::defaults`].
+    pub fn color_rules(&self) -> Vec<ColorRule> {
+        let rule = |color, conditions: &[(Attribute, bool)]| ColorRule {
+            color,
+            conditions: conditions
+                .iter()
+                .map(|&(attribute, negated)| Condition { attribute, negated })
+                .collect(),
+        };
+
+        vec![
+            rule(self.frontier, &[(Attribute::Frontier, false)]),
+            rule(
+                self.open_target,
+                &[(Attribute::Governor, true), (Attribute::Password, true)],
+            ),
+            rule(
+                self.executive_target,
+                &[
+                    (Attribute::Governor, false),
+                    (Attribute::Executive, false),
+                    (Attribute::Password, true),
+                ],
+            ),
+            rule(self.passworded, &[(Attribute::Password, false)]),
+        ]
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    canonical_name,
+    estimate::{self, Update},
+    rows::{format_duration, split_duration},
+    Dump, Result, SrsglassError, TimesheetOptions,
+};
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+/// A trigger region for a target, chosen so that it updates at least the
+/// requested lead time before the target.
+#[derive(Debug)]
+pub struct Trigger {
+    pub target: String,
+    /// Estimated update time of the target, in seconds from the start of the update
+    pub target_time: f64,
+    /// The trigger region, if any region updates early enough
+    pub trigger: Option<String>,
+    /// Estimated update time of the trigger, in seconds from the start of the update
+    pub trigger_time: Option<f64>,
+}
+
+impl Trigger {
+    /// Get the number of seconds between the trigger and target updating.
+    pub fn lead(&self) -> Option<f64> {
+        self.trigger_time.map(|time| self.target_time - time)
+    }
+}
+
+/// Find a trigger for each target region in the given update.
+///
+/// The trigger for each target is the last region estimated to update at
+/// least `lead_time` seconds before the target does.
+pub fn find_triggers(
+    dump: &Dump,
+    targets: &[String],
+    lead_time: f64,
+    update: Update,
+    options: &TimesheetOptions,
+) -> Result<Vec<Trigger>> {
+    let update_length = match update {
+        Update::Major => options.major_length,
+        Update::Minor => options.minor_length,
+    };
+
+    let positions = estimate::positions(dump, options.model)?;
+
+    // positions in each update are estimated along its own order
+    let order = match update {
+        Update::Major => estimate::update_order(dump),
+        Update::Minor => estimate::minor_order(dump),
+    };
+
+    let times: Vec<(&str, f64)> = order
+        .into_iter()
+        .filter_map(|index| {
+            Some((
+                dump.regions[index].name.as_deref()?,
+                positions[index]?.get(update) * update_length as f64,
+            ))
+        })
+        .collect();
+
+    targets
+        .iter()
+        .map(|target| {
+            let target_name = canonical_name(target);
+
+            let Some(&(name, target_time)) = times
+                .iter()
+                .find(|(name, _)| canonical_name(name) == target_name)
+            else {
+                return Err(SrsglassError::InvalidOption(format!(
+                    "Could not find target region {}",
+                    target
+                )));
+            };
+
+            // Regions are in update order, so estimated times never decrease
+            let index = times.partition_point(|(_, time)| *time <= target_time - lead_time);
+            let trigger = index.checked_sub(1).map(|index| times[index]);
+
+            Ok(Trigger {
+                target: name.to_string(),
+                target_time,
+                trigger: trigger.map(|(name, _)| name.to_string()),
+                trigger_time: trigger.map(|(_, time)| time),
+            })
+        })
+        .collect()
+}
+
+/// Format triggers as a plain text list, one target per line.
+pub fn to_text(triggers: &[Trigger], timestamp_precision: i32) -> String {
+    let mut text = String::new();
+
+    for trigger in triggers {
+        let target_time = format_duration(trigger.target_time, timestamp_precision);
+
+        let line = match (&trigger.trigger, trigger.trigger_time, trigger.lead()) {
+            (Some(name), Some(time), Some(lead)) => format!(
+                "{} ({}) <- {} ({}, {:.1}s lead)\n",
+                trigger.target,
+                target_time,
+                name,
+                format_duration(time, timestamp_precision),
+                lead
+            ),
+            _ => format!("{} ({}) <- no trigger\n", trigger.target, target_time),
+        };
+
+        text.push_str(&line);
+    }
+
+    text
+}
+
+/// Add a worksheet listing the triggers to a timesheet workbook.
+pub fn add_worksheet(workbook: &mut Workbook, triggers: &[Trigger]) -> Result<()> {
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name("Triggers")?;
+
+    worksheet.write_row(
+        0,
+        0,
+        ["Target", "Target Time", "Trigger", "Trigger Time", "Lead"],
+    )?;
+
+    worksheet.set_column_width(0, 45)?;
+    worksheet.set_column_width(2, 45)?;
+
+    let duration_format = Format::new().set_num_format("[h]:mm:ss");
+    worksheet.set_column_format(1, &duration_format)?;
+    worksheet.set_column_format(3, &duration_format)?;
+    worksheet.set_column_format(4, &Format::new().set_num_format("0.0"))?;
+
+    worksheet.set_freeze_panes(1, 0)?;
+
+    for (index, trigger) in triggers.iter().enumerate() {
+        let row_index = index as u32 + 1;
+
+        worksheet.write_string(row_index, 0, &trigger.target)?;
+
+        let (h, m, s, ms) = split_duration(trigger.target_time);
+        worksheet.write_datetime(row_index, 1, &ExcelDateTime::from_hms_milli(h, m, s, ms)?)?;
+
+        if let (Some(name), Some(time), Some(lead)) =
+            (&trigger.trigger, trigger.trigger_time, trigger.lead())
+        {
+            worksheet.write_string(row_index, 2, name)?;
+
+            let (h, m, s, ms) = split_duration(time);
+            worksheet.write_datetime(row_index, 3, &ExcelDateTime::from_hms_milli(h, m, s, ms)?)?;
+
+            worksheet.write_number(row_index, 4, lead)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use srsglass::{Dump, DumpExtras, TimesheetOptions};
+use std::fs::{self, File};
+
+fn fixture_dump() -> Dump {
+    let file = File::open("tests/fixtures/regions.xml.gz").unwrap();
+    Dump::from_reader(file, DumpExtras::default()).unwrap()
+}
+
+#[test]
+fn bbcode_table_links_filtered_regions() {
+    let path = std::env::temp_dir().join(format!("srsglass-bbcode-{}.txt", std::process::id()));
+    let options = TimesheetOptions {
+        filter: Some(vec!["lazarus".to_string()]),
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_bbcode(&path, &options).unwrap();
+
+    let bbcode = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        bbcode,
+        "[table]\n\
+         [tr][td][b]Region[/b][/td][td][b]Minor[/b][/td][td][b]Major[/b][/td][td][b]Del. Endos[/b][/td][/tr]\n\
+         [tr][td][region]Lazarus[/region][/td][td]0:23:40[/td][td]0:35:40[/td][td]11[/td][/tr]\n\
+         [/table]\n"
+    );
+}
@@ -0,0 +1,106 @@
+use chrono::NaiveDate;
+use srsglass::{
+    diff::{diff, Change, RegionChange},
+    Dump, Region,
+};
+
+fn region(name: &str, delegate: Option<&str>, exec: bool, governor: Option<&str>) -> Region {
+    Region {
+        name: Some(name.to_string()),
+        delegate: delegate.map(String::from),
+        delegate_exec: Some(exec),
+        governor: governor.map(String::from),
+        ..Region::default()
+    }
+}
+
+fn dump(regions: Vec<Region>, passwordless: &[&str]) -> Dump {
+    Dump {
+        dump_date: NaiveDate::from_ymd_opt(2024, 7, 31).unwrap(),
+        regions,
+        governorless: Vec::new(),
+        passwordless: passwordless.iter().map(|name| name.to_string()).collect(),
+        frontiers: Vec::new(),
+        invaders: Vec::new(),
+        defenders: Vec::new(),
+        incomplete: false,
+        sha256: None,
+    }
+}
+
+fn change(region: &str, change: Change) -> RegionChange {
+    RegionChange {
+        region: region.to_string(),
+        change,
+    }
+}
+
+#[test]
+fn new_delegate_is_not_an_executive_change() {
+    let old = dump(vec![region("Lazarus", Some("alice"), true, None)], &[]);
+    let new = dump(vec![region("Lazarus", Some("bob"), false, None)], &[]);
+
+    assert_eq!(
+        diff(&old, &new),
+        [change(
+            "Lazarus",
+            Change::DelegateChanged {
+                from: Some("alice".to_string()),
+                to: Some("bob".to_string()),
+            }
+        )]
+    );
+}
+
+#[test]
+fn same_delegate_losing_executive_authority_is_reported() {
+    let old = dump(vec![region("Lazarus", Some("alice"), true, None)], &[]);
+    let new = dump(vec![region("Lazarus", Some("alice"), false, None)], &[]);
+
+    assert_eq!(
+        diff(&old, &new),
+        [change(
+            "Lazarus",
+            Change::ExecutiveChanged {
+                from: true,
+                to: false
+            }
+        )]
+    );
+}
+
+#[test]
+fn changes_follow_the_new_dump_then_ceased_regions() {
+    let old = dump(
+        vec![
+            region("The Pacific", None, true, None),
+            region("Osiris", None, true, Some("carol")),
+            region("Tiny Village", None, true, None),
+        ],
+        &["the_pacific", "osiris"],
+    );
+    let new = dump(
+        vec![
+            region("the pacific", None, true, None),
+            region("Osiris", None, true, None),
+            region("Frontier Town", None, true, None),
+        ],
+        &["osiris", "frontier_town"],
+    );
+
+    assert_eq!(
+        diff(&old, &new),
+        [
+            change("the pacific", Change::PasswordAdded),
+            change(
+                "Osiris",
+                Change::GovernorChanged {
+                    from: Some("carol".to_string()),
+                    to: None,
+                }
+            ),
+            change("Frontier Town", Change::Founded),
+            change("Tiny Village", Change::CeasedToExist),
+        ]
+    );
+}
@@ -0,0 +1,353 @@
+use flate2::read::GzDecoder;
+use srsglass::{
+    estimate::Update, Client, ColorRule, Column, Dump, DumpExtras, SrsglassError, TagSet,
+    TimesheetOptions,
+};
+use std::{
+    fs::{self, File},
+    io::Read,
+};
+
+fn fixture_dump() -> Dump {
+    let file = File::open("tests/fixtures/regions.xml.gz").unwrap();
+    Dump::from_reader(file, DumpExtras::default()).unwrap()
+}
+
+/// Read the fixture dump's XML, decompressed.
+fn fixture_xml() -> Vec<u8> {
+    let mut xml = Vec::new();
+    GzDecoder::new(File::open("tests/fixtures/regions.xml.gz").unwrap())
+        .read_to_end(&mut xml)
+        .unwrap();
+    xml
+}
+
+#[test]
+fn regions_founded_since_major_update_do_not_date_the_dump() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>The Pacific</NAME><NUMNATIONS>40</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTUPDATE>1722484800</LASTUPDATE><LASTMAJORUPDATE>1722484800</LASTMAJORUPDATE><LASTMINORUPDATE>1722528000</LASTMINORUPDATE></REGION>
+<REGION><NAME>Lazarus</NAME><NUMNATIONS>20</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTUPDATE>1722484860</LASTUPDATE><LASTMAJORUPDATE>1722484860</LASTMAJORUPDATE><LASTMINORUPDATE>1722528036</LASTMINORUPDATE></REGION>
+<REGION><NAME>New Haven</NAME><NUMNATIONS>1</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTUPDATE>0</LASTUPDATE><LASTMAJORUPDATE>0</LASTMAJORUPDATE><LASTMINORUPDATE>0</LASTMINORUPDATE></REGION>
+</REGIONS>"#;
+
+    let dump = Dump::from_reader(xml.as_bytes(), DumpExtras::default()).unwrap();
+
+    assert!(dump.regions[2].is_new());
+    assert_eq!(dump.dump_date.to_string(), "2024-07-31");
+}
+
+#[test]
+fn estimated_time_is_seconds_into_the_update() {
+    let dump = fixture_dump();
+    let options = TimesheetOptions::default();
+
+    assert_eq!(
+        dump.estimated_time("lazarus", Update::Major, &options)
+            .unwrap(),
+        2140.0
+    );
+    assert_eq!(
+        dump.estimated_time("Lazarus", Update::Minor, &options)
+            .unwrap(),
+        1420.0
+    );
+}
+
+#[test]
+fn estimated_time_ignores_the_filter() {
+    let dump = fixture_dump();
+    let options = TimesheetOptions {
+        filter: Some(vec!["Osiris".to_string()]),
+        ..TimesheetOptions::default()
+    };
+
+    assert_eq!(
+        dump.estimated_time("Lazarus", Update::Major, &options)
+            .unwrap(),
+        2140.0
+    );
+}
+
+#[test]
+fn row_count_follows_the_filter() {
+    let dump = fixture_dump();
+    let options = TimesheetOptions {
+        filter: Some(vec!["lazarus".to_string(), "Osiris".to_string()]),
+        ..TimesheetOptions::default()
+    };
+
+    assert_eq!(dump.row_count(&options).unwrap(), 2);
+}
+
+#[test]
+fn describe_region_lists_times_and_flags() {
+    let description = fixture_dump()
+        .describe_region("Lazarus", &TimesheetOptions::default())
+        .unwrap();
+
+    assert_eq!(
+        description,
+        "Region:         Lazarus\n\
+         Link:           https://www.nationstates.net/region=lazarus\n\
+         Major:          0:35:40\n\
+         Major Time:     12:35:40 AM EDT\n\
+         Minor:          0:23:40\n\
+         Minor Time:     12:23:40 PM EDT\n\
+         Population:     20\n\
+         Total Nations:  40\n\
+         Progress:       40.00%\n\
+         Delegate:       lazarus_delegate\n\
+         Del. Endos:     11\n\
+         Exec. Delegate: false\n\
+         Has Governor:   true\n\
+         Password:       false\n\
+         Occupied:       false\n\
+         Type:           Stronghold\n"
+    );
+    assert!(fixture_dump()
+        .describe_region("nowhere", &TimesheetOptions::default())
+        .is_err());
+}
+
+#[test]
+fn describe_region_ignores_the_filter() {
+    let dump = fixture_dump();
+    let options = TimesheetOptions {
+        filter: Some(vec!["Osiris".to_string()]),
+        ..TimesheetOptions::default()
+    };
+
+    assert_eq!(
+        dump.describe_region("Lazarus", &options).unwrap(),
+        dump.describe_region("Lazarus", &TimesheetOptions::default())
+            .unwrap()
+    );
+}
+
+#[test]
+fn dump_from_reader_needs_no_client() {
+    let extras = DumpExtras {
+        governorless: Some(
+            ["the_pacific", "osiris", "tiny_village", "frontier_town"]
+                .map(String::from)
+                .to_vec(),
+        ),
+        passwordless: Some(
+            [
+                "the_pacific",
+                "lazarus",
+                "osiris",
+                "tiny_village",
+                "frontier_town",
+            ]
+            .map(String::from)
+            .to_vec(),
+        ),
+        frontiers: vec!["frontier_town".to_string()],
+        ..DumpExtras::default()
+    };
+    let dump = Dump::from_reader(fixture_xml().as_slice(), extras.clone()).unwrap();
+    let regions = Client::new("test")
+        .get_regions_from_file("tests/fixtures/regions.xml.gz")
+        .unwrap();
+
+    assert!(dump.sha256.is_some());
+    assert_eq!(dump.dump_date.to_string(), "2024-07-31");
+    assert_eq!(format!("{:?}", dump.regions), format!("{:?}", regions));
+    assert_eq!(Some(&dump.governorless), extras.governorless.as_ref());
+    assert_eq!(Some(&dump.passwordless), extras.passwordless.as_ref());
+    assert_eq!(dump.frontiers, extras.frontiers);
+
+    // regions from elsewhere have the nations before them counted
+    let regions = Dump::from_regions(dump.regions, extras).unwrap().regions;
+    let nations_before: Vec<_> = regions.iter().map(|region| region.nations_before).collect();
+    assert_eq!(
+        nations_before,
+        [Some(0), Some(40), Some(60), Some(70), Some(71), Some(75)]
+    );
+}
+
+#[test]
+fn uncompressed_dump_matches_gzipped() {
+    let client = Client::new("test");
+
+    let uncompressed = client
+        .get_dump_from_reader_offline(fixture_xml().as_slice())
+        .unwrap();
+    let gzipped = client
+        .get_dump_from_file_offline("tests/fixtures/regions.xml.gz")
+        .unwrap();
+
+    // the checksums differ, as they are of the bytes read
+    assert_ne!(uncompressed.sha256, gzipped.sha256);
+    assert_eq!(uncompressed.dump_date, gzipped.dump_date);
+    assert_eq!(
+        format!("{:?}", uncompressed.regions),
+        format!("{:?}", gzipped.regions)
+    );
+    assert_eq!(uncompressed.governorless, gzipped.governorless);
+}
+
+#[test]
+fn truncated_dump_is_kept_only_if_partial_dumps_are_allowed() {
+    // cut off partway through the fifth region
+    let xml = fixture_xml();
+    let truncated = &xml[..2000];
+
+    let result = Client::new("test").get_dump_from_reader_offline(truncated);
+    assert!(matches!(
+        result,
+        Err(SrsglassError::TruncatedDump { regions: 4, .. })
+    ));
+
+    let dump = Client::new("test")
+        .with_partial_dumps(true)
+        .get_dump_from_reader_offline(truncated)
+        .unwrap();
+    assert!(dump.incomplete);
+    assert_eq!(dump.regions.len(), 4);
+}
+
+#[test]
+fn flags_and_banners_are_read_as_urls() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>Lazarus</NAME><NUMNATIONS>20</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484860</LASTMAJORUPDATE><LASTMINORUPDATE>1722528036</LASTMINORUPDATE><FLAG>https://www.nationstates.net/images/flags/uploads/rflags/lazarus.png</FLAG><BANNERURL>/images/rbanners/uploads/lazarus.jpg</BANNERURL></REGION>
+<REGION><NAME>Osiris</NAME><NUMNATIONS>10</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484890</LASTMAJORUPDATE><LASTMINORUPDATE>1722528054</LASTMINORUPDATE><FLAG></FLAG><BANNERURL>/images/rbanners/d1.jpg</BANNERURL></REGION>
+</REGIONS>"#;
+
+    let dump = Client::new("test")
+        .get_dump_from_reader_offline(xml.as_bytes())
+        .unwrap();
+
+    assert_eq!(
+        dump.regions[0].flag.as_deref(),
+        Some("https://www.nationstates.net/images/flags/uploads/rflags/lazarus.png")
+    );
+    assert_eq!(
+        dump.regions[0].banner.as_deref(),
+        Some("https://www.nationstates.net/images/rbanners/uploads/lazarus.jpg")
+    );
+    assert_eq!(dump.regions[1].flag, None);
+    assert_eq!(
+        dump.regions[1].banner.as_deref(),
+        Some("https://www.nationstates.net/images/rbanners/d1.jpg")
+    );
+}
+
+#[test]
+fn founding_times_before_records_are_missing() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>Lazarus</NAME><NUMNATIONS>20</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484860</LASTMAJORUPDATE><LASTMINORUPDATE>1722528036</LASTMINORUPDATE><FOUNDEDTIME>0</FOUNDEDTIME></REGION>
+<REGION><NAME>Fresh Frontier</NAME><NUMNATIONS>1</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>0</LASTMAJORUPDATE><LASTMINORUPDATE>0</LASTMINORUPDATE><FOUNDEDTIME>1722500000</FOUNDEDTIME></REGION>
+</REGIONS>"#;
+
+    let dump = Client::new("test")
+        .get_dump_from_reader_offline(xml.as_bytes())
+        .unwrap();
+
+    assert_eq!(dump.regions[0].founded, None);
+    assert_eq!(dump.regions[1].founded, Some(1722500000));
+}
+
+#[test]
+fn delegate_authorities_are_read_from_their_codes() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>Lazarus</NAME><NUMNATIONS>20</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>XWABCEP</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484860</LASTMAJORUPDATE><LASTMINORUPDATE>1722528036</LASTMINORUPDATE></REGION>
+<REGION><NAME>Osiris</NAME><NUMNATIONS>10</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>WB</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484890</LASTMAJORUPDATE><LASTMINORUPDATE>1722528054</LASTMINORUPDATE></REGION>
+</REGIONS>"#;
+
+    let dump = Client::new("test")
+        .get_dump_from_reader_offline(xml.as_bytes())
+        .unwrap();
+
+    let authority = dump.regions[1].delegate_authority.unwrap();
+    assert!(authority.border_control);
+    assert!(!authority.executive && !authority.polls);
+    assert_eq!(dump.regions[1].delegate_exec, Some(false));
+
+    let path = std::env::temp_dir().join(format!("srsglass-authority-{}.csv", std::process::id()));
+    let options = TimesheetOptions {
+        columns: vec![
+            Column::Region,
+            Column::Executive,
+            Column::DelegateBorderControl,
+            Column::DelegatePolls,
+        ],
+        ..TimesheetOptions::default()
+    };
+    dump.to_csv(&path, &options).unwrap();
+
+    let csv = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        csv,
+        "Region,Exec. Delegate,Del. Border Control,Del. Polls\n\
+         Lazarus,true,true,true\n\
+         Osiris,false,true,false\n"
+    );
+}
+
+#[test]
+fn passworded_invader_regions_are_occupied() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>Lazarus</NAME><NUMNATIONS>20</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484860</LASTMAJORUPDATE><LASTMINORUPDATE>1722528036</LASTMINORUPDATE></REGION>
+<REGION><NAME>Osiris</NAME><NUMNATIONS>10</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484890</LASTMAJORUPDATE><LASTMINORUPDATE>1722528054</LASTMINORUPDATE></REGION>
+<REGION><NAME>Balder</NAME><NUMNATIONS>10</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484900</LASTMAJORUPDATE><LASTMINORUPDATE>1722528060</LASTMINORUPDATE></REGION>
+</REGIONS>"#;
+
+    // Osiris is tagged Invader but has no password, and Balder is tagged
+    // both, so only Lazarus is occupied
+    let extras = DumpExtras {
+        passwordless: Some(vec!["osiris".to_string()]),
+        invaders: ["lazarus", "osiris", "balder"].map(String::from).to_vec(),
+        defenders: vec!["balder".to_string()],
+        ..DumpExtras::default()
+    };
+    let dump = Dump::from_reader(xml.as_bytes(), extras).unwrap();
+
+    let path = std::env::temp_dir().join(format!("srsglass-occupied-{}.csv", std::process::id()));
+    let options = TimesheetOptions {
+        columns: vec![Column::Region, Column::Occupied],
+        ..TimesheetOptions::default()
+    };
+    dump.to_csv(&path, &options).unwrap();
+
+    let csv = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        csv,
+        "Region,Occupied\nLazarus,true\nOsiris,false\nBalder,false\n"
+    );
+    assert!("orange=occupied,-governor".parse::<ColorRule>().is_ok());
+}
+
+#[test]
+fn tag_sets_are_columns_headed_by_their_names() {
+    let dump = fixture_dump();
+
+    let path = std::env::temp_dir().join(format!("srsglass-tag-sets-{}.csv", std::process::id()));
+    let options = TimesheetOptions {
+        columns: vec![Column::Region, Column::TagSet(0)],
+        tag_sets: vec![TagSet {
+            name: "Fascist".to_string(),
+            regions: vec!["lazarus".to_string()],
+        }],
+        ..TimesheetOptions::default()
+    };
+    dump.to_csv(&path, &options).unwrap();
+
+    let csv = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("Region,Fascist"));
+    assert!(lines.any(|line| line == "Lazarus,true"));
+    assert!(csv.lines().any(|line| line == "Osiris,false"));
+}
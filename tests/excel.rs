@@ -0,0 +1,701 @@
+use chrono::{NaiveDate, TimeDelta, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use srsglass::{
+    estimate::{Anchor, Update},
+    Client, Column, Dump, LinkStyle, SrsglassError, TimesheetOptions,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Cursor, Read},
+    path::Path,
+};
+use zip::ZipArchive;
+
+/// Labels of world data cells whose values change between runs.
+const VOLATILE_LABELS: [&str; 2] = ["Srsglass Version", "Date Generated"];
+
+fn fixture_dump() -> Dump {
+    let regions = Client::new("test")
+        .get_regions_from_file("tests/fixtures/regions.xml.gz")
+        .unwrap();
+
+    Dump {
+        dump_date: NaiveDate::from_ymd_opt(2024, 7, 31).unwrap(),
+        regions,
+        governorless: ["the_pacific", "osiris", "tiny_village", "frontier_town"]
+            .map(String::from)
+            .to_vec(),
+        passwordless: [
+            "the_pacific",
+            "lazarus",
+            "osiris",
+            "tiny_village",
+            "frontier_town",
+        ]
+        .map(String::from)
+        .to_vec(),
+        frontiers: vec!["frontier_town".to_string()],
+        invaders: Vec::new(),
+        defenders: Vec::new(),
+        incomplete: false,
+        sha256: None,
+    }
+}
+
+/// Read the fixture dump's XML, decompressed.
+fn fixture_xml() -> Vec<u8> {
+    let mut xml = Vec::new();
+    GzDecoder::new(fs::File::open("tests/fixtures/regions.xml.gz").unwrap())
+        .read_to_end(&mut xml)
+        .unwrap();
+    xml
+}
+
+/// Read a file from an xlsx archive.
+fn read_part(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+    let mut part = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut part)
+        .unwrap();
+    part
+}
+
+/// Read the shared strings table that string cells index into.
+fn shared_strings(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut strings = Vec::new();
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event().unwrap() {
+            Event::Start(e) if e.name().as_ref() == b"si" => strings.push(String::new()),
+            Event::Start(e) => in_text = e.name().as_ref() == b"t",
+            Event::End(_) => in_text = false,
+            Event::Text(e) if in_text => {
+                let string: &mut String = strings.last_mut().unwrap();
+                string.push_str(&e.unescape().unwrap());
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    strings
+}
+
+/// Read the number format of each cell style, or `None` for the General
+/// format. Built-in formats are read as their ID.
+fn number_formats(xml: &str) -> Vec<Option<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut custom = BTreeMap::new();
+    let mut formats = Vec::new();
+    let mut in_cell_xfs = false;
+
+    loop {
+        match reader.read_event().unwrap() {
+            Event::Empty(e) if e.name().as_ref() == b"numFmt" => {
+                let id = attribute(&e, "numFmtId").unwrap();
+                custom.insert(id, attribute(&e, "formatCode").unwrap());
+            }
+            Event::Start(e) if e.name().as_ref() == b"cellXfs" => in_cell_xfs = true,
+            Event::End(e) if e.name().as_ref() == b"cellXfs" => in_cell_xfs = false,
+            Event::Start(e) | Event::Empty(e) if in_cell_xfs && e.name().as_ref() == b"xf" => {
+                let id = attribute(&e, "numFmtId").unwrap_or_else(|| "0".to_string());
+                formats.push(match custom.get(&id) {
+                    Some(code) => Some(code.clone()),
+                    None if id == "0" => None,
+                    None => Some(format!("builtin {}", id)),
+                });
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    formats
+}
+
+fn attribute(e: &BytesStart, name: &str) -> Option<String> {
+    let attribute = e.try_get_attribute(name).unwrap()?;
+    Some(attribute.unescape_value().unwrap().to_string())
+}
+
+/// A cell read from a worksheet.
+struct Cell {
+    value: String,
+    style: usize,
+}
+
+/// Read the cells of a worksheet as text, keyed by row and then column.
+/// Formulas are read as their formula prefixed with `=`.
+fn cells(xml: &str, strings: &[String]) -> BTreeMap<u32, BTreeMap<u16, Cell>> {
+    let mut reader = Reader::from_str(xml);
+    let mut cells: BTreeMap<u32, BTreeMap<u16, Cell>> = BTreeMap::new();
+
+    let mut cell = None;
+    let mut style = 0;
+    let mut is_shared = false;
+    let mut current_tag = Vec::new();
+
+    loop {
+        match reader.read_event().unwrap() {
+            Event::Start(e) if e.name().as_ref() == b"c" => {
+                cell = Some(parse_reference(&attribute(&e, "r").unwrap()));
+                style = attribute(&e, "s").map_or(0, |s| s.parse().unwrap());
+                is_shared = attribute(&e, "t").as_deref() == Some("s");
+            }
+            Event::Start(e) => current_tag = e.name().as_ref().to_vec(),
+            Event::Text(e) => {
+                let Some((row, column)) = cell else {
+                    continue;
+                };
+                let text = e.unescape().unwrap();
+
+                let value = match current_tag.as_slice() {
+                    b"f" => format!("={}", text),
+                    b"v" if is_shared => strings[text.parse::<usize>().unwrap()].clone(),
+                    b"v" => text.to_string(),
+                    _ => continue,
+                };
+
+                // a formula's cached value follows it, so keep the formula
+                cells
+                    .entry(row)
+                    .or_default()
+                    .entry(column)
+                    .or_insert(Cell { value, style });
+            }
+            Event::End(e) if e.name().as_ref() == b"c" => cell = None,
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+
+    cells
+}
+
+/// Split a cell reference such as `B12` into a zero-indexed row and column.
+fn parse_reference(reference: &str) -> (u32, u16) {
+    let split = reference.find(|c: char| c.is_ascii_digit()).unwrap();
+    let (letters, digits) = reference.split_at(split);
+
+    let column = letters
+        .bytes()
+        .fold(0, |column, letter| column * 26 + (letter - b'A' + 1) as u16);
+
+    (digits.parse::<u32>().unwrap() - 1, column - 1)
+}
+
+/// Render each worksheet of a workbook as tab-separated rows, masking values
+/// that change between runs. Each sheet is followed by the number format of
+/// each cell in its first region row.
+fn render(buffer: Vec<u8>) -> String {
+    let mut archive = ZipArchive::new(Cursor::new(buffer)).unwrap();
+    let strings = shared_strings(&read_part(&mut archive, "xl/sharedStrings.xml"));
+    let formats = number_formats(&read_part(&mut archive, "xl/styles.xml"));
+
+    let mut rendered = String::new();
+
+    for sheet in 1.. {
+        let name = format!("xl/worksheets/sheet{}.xml", sheet);
+        if archive.by_name(&name).is_err() {
+            break;
+        }
+
+        rendered.push_str(&format!("# {}\n", name));
+
+        let cells = cells(&read_part(&mut archive, &name), &strings);
+
+        for row in cells.values() {
+            let width = row.keys().max().map_or(0, |column| column + 1);
+            let mut values: Vec<&str> = (0..width)
+                .map(|column| row.get(&column).map_or("", |cell| cell.value.as_str()))
+                .collect();
+
+            for index in 1..values.len() {
+                if VOLATILE_LABELS.contains(&values[index - 1]) {
+                    values[index] = "*";
+                }
+            }
+
+            rendered.push_str(&values.join("\t"));
+            rendered.push('\n');
+        }
+
+        rendered.push_str("# formats\n");
+        for (column, cell) in cells.get(&1).into_iter().flatten() {
+            if let Some(format) = &formats[cell.style] {
+                rendered.push_str(&format!("{}\t{}\n", column, format));
+            }
+        }
+    }
+
+    rendered
+}
+
+/// Compare output against a golden file in `tests/fixtures`. Run with
+/// `UPDATE_GOLDEN=1` to rewrite the golden file after an intended change.
+fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new("tests/fixtures").join(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap();
+
+    for (line, (expected, actual)) in expected.lines().zip(actual.lines()).enumerate() {
+        assert_eq!(expected, actual, "{} differs at line {}", name, line + 1);
+    }
+    assert_eq!(expected.lines().count(), actual.lines().count());
+}
+
+#[test]
+fn workbook_matches_golden() {
+    let buffer = fixture_dump()
+        .to_excel_buffer(&TimesheetOptions::default())
+        .unwrap();
+
+    assert_golden("timesheet.golden", &render(buffer));
+}
+
+#[test]
+fn workbook_with_precision_matches_golden() {
+    let options = TimesheetOptions {
+        timestamp_precision: 3,
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+
+    assert_golden("timesheet_precision.golden", &render(buffer));
+}
+
+#[test]
+fn workbook_with_clock_times_matches_golden() {
+    let options = TimesheetOptions {
+        columns: vec![
+            Column::Region,
+            Column::Minor,
+            Column::Major,
+            Column::MinorTime,
+            Column::MajorTime,
+        ],
+        timezone: chrono_tz::Europe::London,
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+
+    assert_golden("timesheet_clock_times.golden", &render(buffer));
+}
+
+#[test]
+fn workbook_from_buffer_matches_file() {
+    let client = Client::new("test");
+    let options = TimesheetOptions::default();
+
+    let buffer = fs::read("tests/fixtures/regions.xml.gz").unwrap();
+    let from_buffer = client
+        .get_dump_from_reader_offline(buffer.as_slice())
+        .unwrap();
+    let from_file = client
+        .get_dump_from_file_offline("tests/fixtures/regions.xml.gz")
+        .unwrap();
+
+    assert_eq!(
+        render(from_buffer.to_excel_buffer(&options).unwrap()),
+        render(from_file.to_excel_buffer(&options).unwrap())
+    );
+}
+
+#[test]
+fn workbook_with_endorsements_needed_matches_golden() {
+    let options = TimesheetOptions {
+        columns: vec![
+            Column::Region,
+            Column::Delegate,
+            Column::DelegateEndorsements,
+            Column::EndorsementsNeeded,
+        ],
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+
+    assert_golden("timesheet_endorsements.golden", &render(buffer));
+}
+
+#[test]
+fn workbook_with_update_chart_plots_observed_progress() {
+    let options = TimesheetOptions {
+        update_chart: true,
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+    let rendered = render(buffer.clone());
+
+    let sheet = rendered.split("# xl/worksheets/").last().unwrap();
+    let mut lines = sheet.lines().skip(1);
+    assert_eq!(
+        lines.next(),
+        Some("Nations\tMajor\tNations\tMinor\tNations\tEstimated Major\tEstimated Minor")
+    );
+    assert_eq!(lines.next(), Some("0\t0\t0\t0\t0\t0\t0"));
+
+    let mut archive = ZipArchive::new(Cursor::new(buffer)).unwrap();
+    let chart = read_part(&mut archive, "xl/charts/chart1.xml");
+    assert!(chart.contains("'Update Speed'!$B$2"));
+    assert!(chart.contains("Estimated Major"));
+}
+
+#[test]
+fn workbook_with_observed_times_matches_golden() {
+    let options = TimesheetOptions {
+        columns: vec![
+            Column::Region,
+            Column::Major,
+            Column::MajorObserved,
+            Column::MajorResidual,
+            Column::Minor,
+            Column::MinorObserved,
+            Column::MinorResidual,
+        ],
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+
+    assert_golden("timesheet_observed.golden", &render(buffer));
+}
+
+#[test]
+fn dump_checksum_is_of_the_file_as_read() {
+    let dump = Client::new("test")
+        .get_dump_from_file_offline("tests/fixtures/regions.xml.gz")
+        .unwrap();
+
+    assert_eq!(
+        dump.sha256.as_deref(),
+        Some("f568f765d75b3f2b9452f397b43e64a98b36591333f7b1577f36ac69d46b75e4")
+    );
+    assert!(dump
+        .verify_sha256("F568F765D75B3F2B9452F397B43E64A98B36591333F7B1577F36AC69D46B75E4")
+        .is_ok());
+    assert!(dump.verify_sha256(&"0".repeat(64)).is_err());
+    assert!(fixture_dump().verify_sha256(&"0".repeat(64)).is_err());
+}
+
+#[test]
+fn incomplete_dump_is_marked_in_the_sheet_name() {
+    // cut off partway through the fifth region
+    let xml = fixture_xml();
+    let dump = Client::new("test")
+        .with_partial_dumps(true)
+        .get_dump_from_reader_offline(&xml[..2000])
+        .unwrap();
+
+    let buffer = dump.to_excel_buffer(&TimesheetOptions::default()).unwrap();
+    let mut archive = ZipArchive::new(Cursor::new(buffer)).unwrap();
+    assert!(read_part(&mut archive, "xl/workbook.xml").contains("Timesheet (Incomplete)"));
+}
+
+#[test]
+fn workbook_with_trigger_offset_matches_golden() {
+    let options = TimesheetOptions {
+        trigger_offset: 30.0,
+        columns: vec![
+            Column::Region,
+            Column::Minor,
+            Column::MinorTrigger,
+            Column::Major,
+            Column::MajorTrigger,
+        ],
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+
+    assert_golden("timesheet_trigger_offset.golden", &render(buffer));
+}
+
+#[test]
+fn workbook_with_uncertainty_matches_golden() {
+    let options = TimesheetOptions {
+        columns: vec![
+            Column::Region,
+            Column::Minor,
+            Column::MinorUncertainty,
+            Column::Major,
+            Column::MajorUncertainty,
+        ],
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+
+    assert_golden("timesheet_uncertainty.golden", &render(buffer));
+}
+
+#[test]
+fn anchors_rescale_later_estimates() {
+    let dump = fixture_dump();
+    let anchors = ["lazarus=40:00".parse::<Anchor>().unwrap()];
+    let estimates = dump
+        .estimate_with_anchors(5350, 3550, Update::Major, &anchors)
+        .unwrap();
+
+    let estimate = |name: &str| estimates.iter().find(|e| e.name == name).unwrap();
+    assert_eq!(estimate("Lazarus").major, 2400.0);
+    // the update is running 2400 / 2140 as long as estimated, and Osiris has
+    // half again as many nations before it as Lazarus
+    assert_eq!(estimate("Osiris").major, 3600.0);
+    // minor update is not anchored
+    assert_eq!(estimate("Lazarus").minor, 0.4 * 3550.0);
+
+    let missing = ["Nowhere=10".parse::<Anchor>().unwrap()];
+    assert!(matches!(
+        dump.estimate_with_anchors(5350, 3550, Update::Major, &missing),
+        Err(SrsglassError::InvalidOption(_))
+    ));
+    assert!("lazarus".parse::<Anchor>().is_err());
+    assert!("lazarus=soon".parse::<Anchor>().is_err());
+
+    // Osiris updates after Lazarus, so it can't have updated sooner
+    let backwards =
+        ["lazarus=40:00", "osiris=30:00"].map(|anchor| anchor.parse::<Anchor>().unwrap());
+    let Err(SrsglassError::InvalidOption(message)) =
+        dump.estimate_with_anchors(5350, 3550, Update::Major, &backwards)
+    else {
+        panic!("anchors going backwards were accepted");
+    };
+    assert_eq!(
+        message,
+        "Anchor region osiris updated before lazarus, which updates ahead of it"
+    );
+}
+
+#[test]
+fn regions_without_a_minor_timestamp_are_flagged() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>Lazarus</NAME><NUMNATIONS>20</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484860</LASTMAJORUPDATE><LASTMINORUPDATE>1722528036</LASTMINORUPDATE></REGION>
+<REGION><NAME>Refounded</NAME><NUMNATIONS>10</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484875</LASTMAJORUPDATE><LASTMINORUPDATE>0</LASTMINORUPDATE></REGION>
+<REGION><NAME>Osiris</NAME><NUMNATIONS>10</NUMNATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484890</LASTMAJORUPDATE><LASTMINORUPDATE>1722528054</LASTMINORUPDATE></REGION>
+</REGIONS>"#;
+
+    let dump = Client::new("test")
+        .get_dump_from_reader_offline(xml.as_bytes())
+        .unwrap();
+
+    let estimates = dump.estimate_times(5350, 3550).unwrap();
+    assert_eq!(estimates[1].name, "Refounded");
+    assert_eq!(estimates[1].last_major, Some(1722484875));
+    assert_eq!(estimates[1].last_minor, None);
+    // assumed to update after Osiris in minor, as the last region
+    assert_eq!(estimates[1].nations_before_minor, 30);
+    assert_eq!(estimates[2].last_minor, Some(1722528054));
+
+    let anchors = ["Refounded=10".parse::<Anchor>().unwrap()];
+    assert!(matches!(
+        dump.estimate_with_anchors(5350, 3550, Update::Minor, &anchors),
+        Err(SrsglassError::InvalidOption(_))
+    ));
+    assert!(dump
+        .estimate_with_anchors(5350, 3550, Update::Major, &anchors)
+        .is_ok());
+
+    let path = std::env::temp_dir().join(format!("srsglass-missing-{}.csv", std::process::id()));
+    let options = TimesheetOptions {
+        columns: vec![Column::Region, Column::Missing],
+        ..TimesheetOptions::default()
+    };
+    dump.to_csv(&path, &options).unwrap();
+
+    let csv = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        csv,
+        "Region,Missing Timestamps\nLazarus,\nRefounded,minor\nOsiris,\n"
+    );
+}
+
+#[test]
+fn workbook_with_friendly_embassy_flag_matches_golden() {
+    let options = TimesheetOptions {
+        friendly: vec!["lazarus".to_string()],
+        columns: vec![
+            Column::Region,
+            Column::EmbassyCount,
+            Column::HasFriendlyEmbassy,
+        ],
+        ..TimesheetOptions::default()
+    };
+    let buffer = fixture_dump().to_excel_buffer(&options).unwrap();
+
+    assert_golden("timesheet_friendly_embassy.golden", &render(buffer));
+}
+
+#[test]
+fn long_factbooks_are_marked_and_spilled_into_overflow_sheet() {
+    let mut dump = fixture_dump();
+    let factbook = format!("{}{}", "a".repeat(32_000), "b".repeat(1_000));
+    dump.regions[0].factbook = Some(factbook);
+
+    let options = TimesheetOptions {
+        columns: vec![Column::Region, Column::Factbook],
+        overflow_sheet: true,
+        ..TimesheetOptions::default()
+    };
+    let buffer = dump.to_excel_buffer(&options).unwrap();
+    let mut archive = ZipArchive::new(Cursor::new(buffer)).unwrap();
+
+    assert!(read_part(&mut archive, "xl/workbook.xml").contains("Overflow"));
+
+    let strings = shared_strings(&read_part(&mut archive, "xl/sharedStrings.xml"));
+    let kept = strings
+        .iter()
+        .find(|string| string.starts_with("aaa"))
+        .unwrap();
+    assert_eq!(kept.chars().count(), 32_767);
+    assert!(kept.ends_with("b [truncated]"));
+
+    // the overflow is the rest of the factbook after what was kept
+    let overflow = strings
+        .iter()
+        .find(|string| string.starts_with('b'))
+        .unwrap();
+    assert_eq!(
+        kept.chars().count() - " [truncated]".len() + overflow.chars().count(),
+        33_000
+    );
+}
+
+#[test]
+fn filter_matching_nothing_leaves_timesheet_empty() {
+    let dir = std::env::temp_dir();
+    let options = TimesheetOptions {
+        columns: vec![Column::Region, Column::Major],
+        filter: Some(Vec::new()),
+        ..TimesheetOptions::default()
+    };
+    let dump = fixture_dump();
+    assert_eq!(dump.row_count(&options).unwrap(), 0);
+
+    let path = dir.join(format!("srsglass-unmatched-{}.csv", std::process::id()));
+    dump.to_csv(&path, &options).unwrap();
+    let csv = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(csv, "Region,Major\n");
+
+    let path = dir.join(format!("srsglass-unmatched-{}.xlsx", std::process::id()));
+    dump.to_excel(&path, &options).unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn workbook_without_columns_has_no_table() {
+    let path =
+        std::env::temp_dir().join(format!("srsglass-no-columns-{}.xlsx", std::process::id()));
+    let options = TimesheetOptions {
+        columns: Vec::new(),
+        color_rules: Vec::new(),
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_excel(&path, &options).unwrap();
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn linked_region_names_replace_the_link_column() {
+    let path = std::env::temp_dir().join(format!("srsglass-linked-{}.ods", std::process::id()));
+    let options = TimesheetOptions {
+        columns: vec![Column::Region, Column::Link, Column::Population],
+        link_style: LinkStyle::Name,
+        color_rules: Vec::new(),
+        filter: Some(vec!["lazarus".to_string()]),
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_ods(&path, &options).unwrap();
+
+    let mut archive = ZipArchive::new(Cursor::new(fs::read(&path).unwrap())).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    let content = read_part(&mut archive, "content.xml");
+    assert!(!content.contains("<text:p>Link</text:p>"));
+    assert!(content.contains(
+        "<table:table-cell office:value-type=\"string\"><text:p><text:a xlink:type=\"simple\" xlink:href=\"https://www.nationstates.net/region=lazarus\">Lazarus</text:a></text:p></table:table-cell>\
+         <table:table-cell office:value-type=\"float\" office:value=\"20\">"
+    ));
+}
+
+#[test]
+fn template_links_turn_off_site_styling() {
+    let path = std::env::temp_dir().join(format!("srsglass-template-{}.md", std::process::id()));
+    let options = TimesheetOptions {
+        filter: Some(vec!["lazarus".to_string()]),
+        link_style: LinkStyle::Template,
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_markdown(&path, &options).unwrap();
+
+    let markdown = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(markdown.contains(
+        "| Lazarus | https://www.nationstates.net/template-overall=none/region=lazarus | 0:23:40 |"
+    ));
+}
+
+#[test]
+fn regions_before_the_current_time_are_marked_updated() {
+    let path = std::env::temp_dir().join(format!("srsglass-updated-{}.csv", std::process::id()));
+    let started = Utc.with_ymd_and_hms(2024, 8, 2, 4, 0, 5).unwrap();
+    let options = TimesheetOptions {
+        // Lazarus updates at 35:40 and Osiris at 53:30
+        update_started: Some(started),
+        now: Some(started + TimeDelta::minutes(45)),
+        filter: Some(vec!["lazarus".to_string(), "Osiris".to_string()]),
+        columns: vec![Column::Region, Column::Major, Column::Updated],
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_csv(&path, &options).unwrap();
+
+    let csv = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        csv,
+        "Region,Major,Updated\nLazarus,0:35:40,true\nOsiris,0:53:30,false\n"
+    );
+}
+
+#[test]
+fn update_windows_bucket_regions_by_estimate() {
+    let path = std::env::temp_dir().join(format!("srsglass-windows-{}.csv", std::process::id()));
+    let options = TimesheetOptions {
+        // minor update is 59:10 long, so the last 20 minutes start at 39:10
+        early_window: 1500.0,
+        late_window: 1200.0,
+        columns: vec![Column::Region, Column::Minor, Column::MinorWindow],
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_csv(&path, &options).unwrap();
+
+    let csv = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        csv,
+        "Region,Minor,Minor Window\n\
+         The Pacific,0:00:00,Early\n\
+         Lazarus,0:23:40,Early\n\
+         Osiris,0:35:30,Middle\n\
+         Tiny Village,0:41:25,Late\n\
+         Fortress,0:42:00,Late\n\
+         Frontier Town,0:44:22,Late\n"
+    );
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "ffi")]
+
+use srsglass::ffi::*;
+use std::ffi::{CStr, CString};
+
+#[test]
+fn dump_is_estimated_and_written_through_c_interface() {
+    let path = CString::new("tests/fixtures/regions.xml.gz").unwrap();
+    let output = std::env::temp_dir().join("srsglass-ffi-test.xlsx");
+    let output = CString::new(output.to_str().unwrap()).unwrap();
+
+    unsafe {
+        let dump = srsglass_parse_dump(path.as_ptr());
+        assert!(!dump.is_null());
+
+        let estimates = srsglass_estimate(dump, 5350, 3550);
+        assert_eq!(srsglass_estimates_len(estimates), 6);
+
+        let first = &*srsglass_estimates_get(estimates, 0);
+        assert_eq!(CStr::from_ptr(first.name).to_str().unwrap(), "The Pacific");
+        assert_eq!(first.major, 0.0);
+        assert!(srsglass_estimates_get(estimates, 6).is_null());
+
+        assert_eq!(srsglass_write_xlsx(dump, output.as_ptr(), 5350, 3550), 0);
+
+        srsglass_free_estimates(estimates);
+        srsglass_free_dump(dump);
+    }
+}
+
+#[test]
+fn failures_are_reported_through_last_error() {
+    let path = CString::new("tests/fixtures/missing.xml.gz").unwrap();
+
+    unsafe {
+        assert!(srsglass_parse_dump(path.as_ptr()).is_null());
+        assert!(!srsglass_last_error().is_null());
+
+        assert!(srsglass_parse_dump(std::ptr::null()).is_null());
+        let error = CStr::from_ptr(srsglass_last_error());
+        assert_eq!(error.to_str().unwrap(), "path is null");
+
+        assert_eq!(
+            srsglass_write_xlsx(std::ptr::null(), path.as_ptr(), 0, 0),
+            -1
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use srsglass::{Column, Dump, DumpExtras, TimesheetOptions};
+use std::fs;
+
+#[test]
+fn html_wraps_long_columns_wherever_they_are() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>Lazarus</NAME><FACTBOOK><![CDATA[A long factbook]]></FACTBOOK><NUMNATIONS>20</NUMNATIONS><DELEGATE>Lazarus Delegate</DELEGATE><DELEGATEVOTES>12</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTMAJORUPDATE>1722484860</LASTMAJORUPDATE><LASTMINORUPDATE>1722528036</LASTMINORUPDATE></REGION>
+</REGIONS>"#;
+    let dump = Dump::from_reader(xml.as_bytes(), DumpExtras::default()).unwrap();
+
+    let path = std::env::temp_dir().join(format!("srsglass-wrap-{}.html", std::process::id()));
+    let options = TimesheetOptions {
+        columns: vec![
+            Column::Region,
+            Column::Factbook,
+            Column::Delegate,
+            Column::Major,
+        ],
+        color_rules: Vec::new(),
+        ..TimesheetOptions::default()
+    };
+    dump.to_html(&path, &options).unwrap();
+
+    let html = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(html.contains("<td class=\"wrap\">A long factbook</td>"));
+    assert!(html.contains(
+        "<td><a href=\"https://www.nationstates.net/nation=lazarus_delegate\">Lazarus Delegate</a></td>"
+    ));
+}
@@ -0,0 +1,28 @@
+use srsglass::{Dump, DumpExtras, TimesheetOptions};
+use std::fs::{self, File};
+
+fn fixture_dump() -> Dump {
+    let file = File::open("tests/fixtures/regions.xml.gz").unwrap();
+    Dump::from_reader(file, DumpExtras::default()).unwrap()
+}
+
+#[test]
+fn markdown_table_lists_filtered_regions() {
+    let path = std::env::temp_dir().join(format!("srsglass-markdown-{}.md", std::process::id()));
+    let options = TimesheetOptions {
+        filter: Some(vec!["lazarus".to_string(), "Osiris".to_string()]),
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_markdown(&path, &options).unwrap();
+
+    let markdown = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        markdown,
+        "| Region | Link | Minor | Major | Del. Endos |\n\
+         | --- | --- | ---: | ---: | ---: |\n\
+         | Lazarus | https://www.nationstates.net/region=lazarus | 0:23:40 | 0:35:40 | 11 |\n\
+         | Osiris | https://www.nationstates.net/region=osiris | 0:35:30 | 0:53:30 | 0 |\n"
+    );
+}
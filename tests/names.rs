@@ -0,0 +1,77 @@
+use chrono::NaiveDate;
+use srsglass::{canonical_name, display_name, Dump, Region, TimesheetOptions};
+
+fn region(name: &str, population: i32, nations_before: i32) -> Region {
+    Region {
+        name: Some(name.to_string()),
+        factbook: Some(String::new()),
+        population: Some(population),
+        delegate_votes: Some(1),
+        delegate_exec: Some(true),
+        last_major: Some(1),
+        last_minor: Some(1),
+        nations_before: Some(nations_before),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn canonical_and_display_names() {
+    assert_eq!(canonical_name("The North Pacific"), "the_north_pacific");
+    assert_eq!(canonical_name("the_north_pacific"), "the_north_pacific");
+    assert_eq!(canonical_name(" Lazarus "), "lazarus");
+    assert_eq!(display_name("the_north_pacific"), "the north pacific");
+}
+
+#[test]
+fn tag_lists_match_regardless_of_name_form() {
+    let dump = Dump {
+        dump_date: NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(),
+        regions: vec![
+            region("The North Pacific", 10, 0),
+            region("Lazarus", 5, 10),
+            region("balder", 3, 15),
+        ],
+        // API responses use the canonical form, but lists may come from
+        // anywhere
+        governorless: vec!["the_north_pacific".to_string(), "LAZARUS".to_string()],
+        passwordless: vec!["Balder".to_string()],
+        frontiers: Vec::new(),
+        invaders: Vec::new(),
+        defenders: Vec::new(),
+        incomplete: false,
+        sha256: None,
+    };
+
+    let path = std::env::temp_dir().join(format!("srsglass-names-{}.csv", std::process::id()));
+    dump.to_csv(&path, &TimesheetOptions::default()).unwrap();
+
+    let mut reader = csv::Reader::from_path(&path).unwrap();
+    let headers = reader.headers().unwrap().clone();
+    let column = |name| headers.iter().position(|header| header == name).unwrap();
+    let (region, governor, password) =
+        (column("Region"), column("Has Governor"), column("Password"));
+
+    let rows: Vec<(String, String, String)> = reader
+        .records()
+        .map(|record| {
+            let record = record.unwrap();
+            (
+                record[region].to_string(),
+                record[governor].to_string(),
+                record[password].to_string(),
+            )
+        })
+        .collect();
+
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        rows,
+        [
+            ("The North Pacific".into(), "false".into(), "true".into()),
+            ("Lazarus".into(), "false".into(), "true".into()),
+            ("balder".into(), "true".into(), "false".into()),
+        ]
+    );
+}
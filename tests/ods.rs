@@ -0,0 +1,55 @@
+use srsglass::{Column, Dump, DumpExtras, TimesheetOptions};
+use std::{
+    fs::{self, File},
+    io::{Cursor, Read},
+};
+use zip::ZipArchive;
+
+fn fixture_dump() -> Dump {
+    let file = File::open("tests/fixtures/regions.xml.gz").unwrap();
+    Dump::from_reader(file, DumpExtras::default()).unwrap()
+}
+
+/// Read a file from an ods archive.
+fn read_part(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+    let mut part = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut part)
+        .unwrap();
+    part
+}
+
+#[test]
+fn ods_timesheet_has_typed_cells() {
+    let path = std::env::temp_dir().join(format!("srsglass-ods-{}.ods", std::process::id()));
+    let options = TimesheetOptions {
+        columns: vec![
+            Column::Region,
+            Column::Population,
+            Column::Major,
+            Column::New,
+        ],
+        ..TimesheetOptions::default()
+    };
+    fixture_dump().to_ods(&path, &options).unwrap();
+
+    let mut archive = ZipArchive::new(Cursor::new(fs::read(&path).unwrap())).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    // the mimetype must be the first file, so the format can be recognized
+    assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+    assert_eq!(
+        read_part(&mut archive, "mimetype"),
+        "application/vnd.oasis.opendocument.spreadsheet"
+    );
+
+    let content = read_part(&mut archive, "content.xml");
+    assert!(content.contains("<table:table table:name=\"Timesheet\">"));
+    assert!(content.contains(
+        "<table:table-cell office:value-type=\"float\" office:value=\"20\"><text:p>20</text:p></table:table-cell>\
+         <table:table-cell office:value-type=\"time\" office:time-value=\"PT0H35M40.000S\"><text:p>0:35:40</text:p></table:table-cell>\
+         <table:table-cell office:value-type=\"boolean\" office:boolean-value=\"false\"><text:p>false</text:p></table:table-cell>"
+    ));
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "python")]
+
+use pyo3::{ffi::c_str, prelude::*};
+use srsglass::python::srsglass;
+
+#[test]
+fn dump_is_parsed_and_estimated_from_python() {
+    pyo3::append_to_inittab!(srsglass);
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        py.run(
+            c_str!(
+                r#"
+import srsglass
+
+with open("tests/fixtures/regions.xml.gz", "rb") as file:
+    dump = srsglass.Dump.from_bytes(file.read())
+
+assert len(dump) == 6
+assert dump.regions[0] == "The Pacific"
+
+estimates = dump.estimate_times()
+assert len(estimates) == 6
+assert estimates[0].name == "The Pacific"
+assert estimates[0].major == 0.0
+assert dump.estimate_times(major_length=0)[-1].major == 0.0
+
+try:
+    srsglass.Dump.from_bytes(b"not a dump")
+except srsglass.SrsglassException:
+    pass
+else:
+    raise AssertionError("invalid dump was parsed")
+"#
+            ),
+            None,
+            None,
+        )
+        .unwrap();
+    });
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "serde")]
+
+use srsglass::{estimate::RegionEstimate, Client, Dump};
+
+#[test]
+fn dump_round_trips_through_json() {
+    let dump = Client::new("test")
+        .get_dump_from_file_offline("tests/fixtures/regions.xml.gz")
+        .unwrap();
+
+    let json = serde_json::to_string(&dump).unwrap();
+    let parsed: Dump = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed.dump_date, dump.dump_date);
+    assert_eq!(parsed.regions.len(), dump.regions.len());
+    assert_eq!(parsed.regions[0].embassies, dump.regions[0].embassies);
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+}
+
+#[test]
+fn estimates_round_trip_through_json() {
+    let dump = Client::new("test")
+        .get_dump_from_file_offline("tests/fixtures/regions.xml.gz")
+        .unwrap();
+    let estimates = dump.estimate_times(5350, 3550).unwrap();
+
+    let json = serde_json::to_string(&estimates).unwrap();
+    let parsed: Vec<RegionEstimate> = serde_json::from_str(&json).unwrap();
+
+    // serde_json parses floats to within one unit in the last place
+    assert_eq!(parsed.len(), estimates.len());
+    for (parsed, estimate) in parsed.iter().zip(&estimates) {
+        assert_eq!(parsed.name, estimate.name);
+        assert_eq!(parsed.nations_before, estimate.nations_before);
+        assert!((parsed.major - estimate.major).abs() < 1e-9);
+        assert!((parsed.minor - estimate.minor).abs() < 1e-9);
+    }
+}
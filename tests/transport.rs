@@ -0,0 +1,322 @@
+use chrono::Utc;
+use flate2::{write::GzEncoder, Compression};
+use srsglass::{
+    Client, DumpBody, NationCountWarning, Result, SrsglassError, TimesheetOptions, Transport,
+};
+use std::{
+    io::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+const REGIONS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<REGIONS>
+<REGION><NAME>The Pacific</NAME><FACTBOOK><![CDATA[Welcome]]></FACTBOOK><NUMNATIONS>3</NUMNATIONS><NATIONS></NATIONS><DELEGATE>del_1</DELEGATE><DELEGATEVOTES>3</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTUPDATE>1722484800</LASTUPDATE><LASTMAJORUPDATE>1722484800</LASTMAJORUPDATE><LASTMINORUPDATE>1722528000</LASTMINORUPDATE><EMBASSIES></EMBASSIES></REGION>
+<REGION><NAME>Lazarus</NAME><FACTBOOK><![CDATA[]]></FACTBOOK><NUMNATIONS>1</NUMNATIONS><NATIONS></NATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>gov_2</GOVERNOR><LASTUPDATE>1722484810</LASTUPDATE><LASTMAJORUPDATE>1722484810</LASTMAJORUPDATE><LASTMINORUPDATE>1722528010</LASTMINORUPDATE><EMBASSIES></EMBASSIES></REGION>
+</REGIONS>
+"#;
+
+const NATIONS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<NATIONS>
+<NATION><NAME>Del 1</NAME><UNSTATUS>WA Delegate</UNSTATUS><ENDORSEMENTS>member_1,member_2</ENDORSEMENTS><REGION>The Pacific</REGION></NATION>
+<NATION><NAME>Member 1</NAME><UNSTATUS>WA Member</UNSTATUS><ENDORSEMENTS></ENDORSEMENTS><REGION>The Pacific</REGION></NATION>
+<NATION><NAME>Member 2</NAME><UNSTATUS>WA Member</UNSTATUS><ENDORSEMENTS></ENDORSEMENTS><REGION>The Pacific</REGION></NATION>
+<NATION><NAME>Gov 2</NAME><UNSTATUS>Non-member</UNSTATUS><ENDORSEMENTS></ENDORSEMENTS><REGION>Lazarus</REGION></NATION>
+</NATIONS>
+"#;
+
+/// Founding happenings since the dump above, newest first.
+const FOUNDINGS: &str = r#"<WORLD><HAPPENINGS>
+<EVENT id="4"><TIMESTAMP>1722490000</TIMESTAMP><TEXT><![CDATA[@@founder_1@@ founded the region %%new_haven%%.]]></TEXT></EVENT>
+<EVENT id="3"><TIMESTAMP>1722489000</TIMESTAMP><TEXT><![CDATA[@@newcomer@@ was founded in %%lazarus%%.]]></TEXT></EVENT>
+<EVENT id="2"><TIMESTAMP>1722488000</TIMESTAMP><TEXT><![CDATA[@@founder_2@@ refounded the region %%lazarus%%.]]></TEXT></EVENT>
+<EVENT id="1"><TIMESTAMP>1722487000</TIMESTAMP><TEXT><![CDATA[@@founder_3@@ founded the region %%old_town%%.]]></TEXT></EVENT>
+</HAPPENINGS></WORLD>"#;
+
+/// Gzip a dump as NS serves it.
+fn gzipped(dump: &str) -> Result<DumpBody> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(dump.as_bytes())?;
+    let body = encoder.finish()?;
+
+    Ok(DumpBody {
+        length: Some(body.len() as u64),
+        reader: Box::new(std::io::Cursor::new(body)),
+    })
+}
+
+/// Serves a canned regions dump and API responses instead of contacting NS.
+struct FixtureTransport;
+
+impl Transport for FixtureTransport {
+    fn fetch_dump(&self, url: &str) -> Result<DumpBody> {
+        let dump = match url {
+            "https://www.nationstates.net/pages/regions.xml.gz" => REGIONS,
+            "https://www.nationstates.net/pages/nations.xml.gz" => NATIONS,
+            _ => return Err(SrsglassError::InvalidOption(format!("Unexpected {}", url))),
+        };
+
+        gzipped(dump)
+    }
+
+    fn fetch_api(&self, url: &str) -> Result<String> {
+        if url.contains("q=happenings") {
+            assert!(url.ends_with("sincetime=1722484810"));
+            return Ok(FOUNDINGS.to_string());
+        }
+
+        if url.ends_with("q=numnations") {
+            return Ok("<WORLD><NUMNATIONS>100</NUMNATIONS></WORLD>".to_string());
+        }
+
+        let regions = match url.split("tags=").nth(1) {
+            Some("governorless") => "the_pacific",
+            Some("-password") => "the_pacific,lazarus",
+            Some("frontier") => "",
+            Some("invader") => "lazarus",
+            Some("defender") => "",
+            _ => return Err(SrsglassError::InvalidOption(format!("Unexpected {}", url))),
+        };
+
+        Ok(format!("<WORLD><REGIONS>{}</REGIONS></WORLD>", regions))
+    }
+}
+
+#[test]
+fn dump_from_transport_builds_workbook() {
+    let client = Client::new("test").with_transport(FixtureTransport);
+    let dump = client.get_dump().unwrap();
+
+    assert_eq!(dump.regions.len(), 2);
+    assert_eq!(dump.regions[1].nations_before, Some(3));
+    assert_eq!(dump.governorless, ["the_pacific"]);
+    assert_eq!(dump.passwordless, ["the_pacific", "lazarus"]);
+    assert_eq!(dump.invaders, ["lazarus"]);
+    assert_eq!(dump.dump_date.to_string(), "2024-07-31");
+
+    let buffer = dump.to_excel_buffer(&TimesheetOptions::default()).unwrap();
+    assert!(!buffer.is_empty());
+}
+
+#[test]
+fn nation_count_far_from_world_count_is_flagged() {
+    let client = Client::new("test").with_transport(FixtureTransport);
+    let dump = client.get_dump().unwrap();
+    let world = client.get_world_nation_count().unwrap();
+
+    assert_eq!(world, 100);
+    assert_eq!(
+        dump.check_nation_counts(Some(world)),
+        [NationCountWarning::TotalMismatch {
+            dump: 4,
+            world: 100
+        }]
+    );
+    assert!(dump.check_nation_counts(Some(4)).is_empty());
+}
+
+#[test]
+fn regions_founded_since_dump_update_last() {
+    let client = Client::new("test").with_transport(FixtureTransport);
+    let mut dump = client.get_dump().unwrap();
+
+    let new_regions = client.get_new_regions(dump.last_update().unwrap()).unwrap();
+    assert_eq!(new_regions, ["old_town", "lazarus", "new_haven"]);
+    assert_eq!(dump.add_new_regions(&new_regions), 2);
+
+    let estimates = dump.estimate_times(5350, 3550).unwrap();
+    let names: Vec<&str> = estimates.iter().map(|estimate| estimate.name).collect();
+    assert_eq!(names, ["The Pacific", "Lazarus", "old town", "new haven"]);
+    assert_eq!(estimates[3].nations_before, 5);
+}
+
+#[test]
+fn nations_dump_counts_wa_members() {
+    let client = Client::new("test").with_transport(FixtureTransport);
+    let mut dump = client.get_dump().unwrap();
+    let nations = client.get_nations_dump().unwrap();
+
+    dump.add_endorsements(&nations);
+    dump.add_wa_members(&nations);
+
+    assert_eq!(dump.regions[0].wa_members, Some(3));
+    assert_eq!(dump.regions[0].delegate_endorsements, Some(2));
+    assert_eq!(dump.regions[1].wa_members, Some(0));
+}
+
+#[test]
+fn dump_from_reader_requests_region_statuses() {
+    let client = Client::new("test").with_transport(FixtureTransport);
+    let dump = client.get_dump_from_reader(REGIONS.as_bytes()).unwrap();
+
+    assert_eq!(dump.regions.len(), 2);
+    assert_eq!(dump.governorless, ["the_pacific"]);
+    assert_eq!(dump.passwordless, ["the_pacific", "lazarus"]);
+    assert_eq!(dump.dump_date.to_string(), "2024-07-31");
+}
+
+/// Number of dumps [`StaleTransport`] has served.
+static STALE_DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Serves the same out-of-date dump every time, counting downloads.
+struct StaleTransport;
+
+impl Transport for StaleTransport {
+    fn fetch_dump(&self, url: &str) -> Result<DumpBody> {
+        STALE_DOWNLOADS.fetch_add(1, Ordering::SeqCst);
+        FixtureTransport.fetch_dump(url)
+    }
+
+    fn fetch_api(&self, url: &str) -> Result<String> {
+        FixtureTransport.fetch_api(url)
+    }
+}
+
+#[test]
+fn stale_dump_is_downloaded_again() {
+    let client = Client::new("test")
+        .with_transport(StaleTransport)
+        .with_stale_retries(2, Duration::ZERO);
+
+    let dump = client.get_dump().unwrap();
+
+    assert!(dump.is_stale());
+    assert_eq!(STALE_DOWNLOADS.load(Ordering::SeqCst), 3);
+}
+
+/// Number of dumps [`FreshTransport`] has served.
+static FRESH_DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Serves a dump from the latest major update, with a region founded since
+/// that update, counting downloads.
+struct FreshTransport;
+
+impl Transport for FreshTransport {
+    fn fetch_dump(&self, url: &str) -> Result<DumpBody> {
+        assert_eq!(url, "https://www.nationstates.net/pages/regions.xml.gz");
+        FRESH_DOWNLOADS.fetch_add(1, Ordering::SeqCst);
+
+        // the dump NS should be serving by now started updating two hours ago
+        let start = Utc::now().timestamp() - 2 * 60 * 60;
+        let dump = REGIONS
+            .replace("1722484800", &start.to_string())
+            .replace("1722484810", &(start + 10).to_string())
+            .replace(
+                "</REGIONS>",
+                "<REGION><NAME>New Haven</NAME><NUMNATIONS>1</NUMNATIONS><NATIONS></NATIONS><DELEGATE>0</DELEGATE><DELEGATEVOTES>0</DELEGATEVOTES><DELEGATEAUTH>X</DELEGATEAUTH><GOVERNOR>0</GOVERNOR><LASTUPDATE>0</LASTUPDATE><LASTMAJORUPDATE>0</LASTMAJORUPDATE><LASTMINORUPDATE>0</LASTMINORUPDATE><EMBASSIES></EMBASSIES></REGION>\n</REGIONS>",
+            );
+
+        gzipped(&dump)
+    }
+
+    fn fetch_api(&self, url: &str) -> Result<String> {
+        FixtureTransport.fetch_api(url)
+    }
+}
+
+#[test]
+fn fresh_dump_with_new_region_is_not_downloaded_again() {
+    let client = Client::new("test")
+        .with_transport(FreshTransport)
+        .with_stale_retries(2, Duration::ZERO);
+
+    let dump = client.get_dump().unwrap();
+
+    assert!(dump.regions[2].is_new());
+    assert!(!dump.is_stale());
+    assert_eq!(FRESH_DOWNLOADS.load(Ordering::SeqCst), 1);
+}
+
+/// Number of tag queries [`TagTransport`] has answered.
+static TAG_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Answers tag queries, listing fewer regions for queries without
+/// passworded regions, and counts them.
+struct TagTransport;
+
+impl Transport for TagTransport {
+    fn fetch_dump(&self, url: &str) -> Result<DumpBody> {
+        FixtureTransport.fetch_dump(url)
+    }
+
+    fn fetch_api(&self, url: &str) -> Result<String> {
+        TAG_REQUESTS.fetch_add(1, Ordering::SeqCst);
+
+        let tags = url.split("tags=").nth(1).unwrap();
+        assert!(tags.split(',').count() <= 10);
+
+        let regions = if tags.contains("-password") {
+            "lazarus"
+        } else {
+            "the_pacific,lazarus"
+        };
+
+        Ok(format!("<WORLD><REGIONS>{}</REGIONS></WORLD>", regions))
+    }
+}
+
+#[test]
+fn long_tag_queries_are_batched_and_cached() {
+    let client = Client::new("test").with_transport(TagTransport);
+    let include: Vec<String> = (0..11).map(|tag| format!("tag_{}", tag)).collect();
+    let include: Vec<&str> = include.iter().map(String::as_str).collect();
+
+    let regions = client.get_regions_by_tags(&include, &["password"]).unwrap();
+    assert_eq!(regions, ["lazarus"]);
+    assert_eq!(TAG_REQUESTS.load(Ordering::SeqCst), 2);
+
+    let regions = client.get_regions_by_tags(&include, &["password"]).unwrap();
+    assert_eq!(regions, ["lazarus"]);
+    assert_eq!(TAG_REQUESTS.load(Ordering::SeqCst), 2);
+
+    assert!(client.get_regions_by_tags(&[], &[]).is_err());
+}
+
+/// Number of tag queries [`CachedTransport`] has answered.
+static CACHED_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Answers tag queries as [`FixtureTransport`] does, counting them.
+struct CachedTransport;
+
+impl Transport for CachedTransport {
+    fn fetch_dump(&self, url: &str) -> Result<DumpBody> {
+        FixtureTransport.fetch_dump(url)
+    }
+
+    fn fetch_api(&self, url: &str) -> Result<String> {
+        CACHED_REQUESTS.fetch_add(1, Ordering::SeqCst);
+        FixtureTransport.fetch_api(url)
+    }
+}
+
+#[test]
+fn tag_responses_are_cached_between_clients() {
+    let cache_dir = std::env::temp_dir().join(format!("srsglass-tag-cache-{}", std::process::id()));
+    let client = || {
+        Client::new("test")
+            .with_transport(CachedTransport)
+            .with_tag_cache(&cache_dir, Duration::from_secs(60))
+    };
+
+    assert_eq!(
+        client().get_passwordless_regions().unwrap(),
+        ["the_pacific", "lazarus"]
+    );
+    assert_eq!(CACHED_REQUESTS.load(Ordering::SeqCst), 1);
+
+    assert_eq!(
+        client().get_passwordless_regions().unwrap(),
+        ["the_pacific", "lazarus"]
+    );
+    assert_eq!(CACHED_REQUESTS.load(Ordering::SeqCst), 1);
+
+    // responses older than the TTL are requested again
+    Client::new("test")
+        .with_transport(CachedTransport)
+        .with_tag_cache(&cache_dir, Duration::ZERO)
+        .get_passwordless_regions()
+        .unwrap();
+    assert_eq!(CACHED_REQUESTS.load(Ordering::SeqCst), 2);
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
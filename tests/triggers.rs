@@ -0,0 +1,91 @@
+use chrono::NaiveDate;
+use srsglass::{
+    estimate::Update,
+    triggers::{find_triggers, to_text},
+    Dump, Region, SrsglassError, TimesheetOptions,
+};
+
+/// Build a dump of regions updating 0, 10, 30, and 60 seconds into a
+/// 100-second major update.
+fn dump() -> Dump {
+    let regions = [
+        ("Alpha", 10),
+        ("Bravo", 20),
+        ("Charlie Point", 30),
+        ("Delta", 40),
+    ];
+
+    let mut nations_before = 0;
+    let regions = regions
+        .into_iter()
+        .map(|(name, population)| {
+            let region = Region {
+                name: Some(name.to_string()),
+                population: Some(population),
+                nations_before: Some(nations_before),
+                ..Region::default()
+            };
+            nations_before += population;
+            region
+        })
+        .collect();
+
+    Dump {
+        dump_date: NaiveDate::from_ymd_opt(2024, 7, 31).unwrap(),
+        regions,
+        governorless: Vec::new(),
+        passwordless: Vec::new(),
+        frontiers: Vec::new(),
+        invaders: Vec::new(),
+        defenders: Vec::new(),
+        incomplete: false,
+        sha256: None,
+    }
+}
+
+fn options() -> TimesheetOptions {
+    TimesheetOptions {
+        major_length: 100,
+        ..TimesheetOptions::default()
+    }
+}
+
+#[test]
+fn trigger_exactly_the_lead_time_ahead_is_chosen() {
+    let dump = dump();
+    let targets = ["delta".to_string()];
+
+    let triggers = find_triggers(&dump, &targets, 30.0, Update::Major, &options()).unwrap();
+    assert_eq!(triggers[0].target, "Delta");
+    assert_eq!(triggers[0].target_time, 60.0);
+    assert_eq!(triggers[0].trigger.as_deref(), Some("Charlie Point"));
+    assert_eq!(triggers[0].lead(), Some(30.0));
+
+    // just past the boundary, the region before it is the trigger instead
+    let triggers = find_triggers(&dump, &targets, 30.5, Update::Major, &options()).unwrap();
+    assert_eq!(triggers[0].trigger.as_deref(), Some("Bravo"));
+    assert_eq!(triggers[0].lead(), Some(50.0));
+}
+
+#[test]
+fn target_too_early_for_the_lead_time_has_no_trigger() {
+    let dump = dump();
+    let targets = ["Bravo".to_string()];
+
+    let triggers = find_triggers(&dump, &targets, 15.0, Update::Major, &options()).unwrap();
+    assert_eq!(triggers[0].trigger, None);
+    assert_eq!(triggers[0].lead(), None);
+    assert_eq!(to_text(&triggers, 0), "Bravo (0:00:10) <- no trigger\n");
+}
+
+#[test]
+fn unknown_target_is_an_error() {
+    let dump = dump();
+    let targets = ["Delta".to_string(), "Echo".to_string()];
+
+    let error = find_triggers(&dump, &targets, 5.0, Update::Major, &options()).unwrap_err();
+    assert!(matches!(
+        error,
+        SrsglassError::InvalidOption(message) if message == "Could not find target region Echo"
+    ));
+}